@@ -1,5 +1,8 @@
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
+use crate::config::DuplicatePolicy;
+use crate::file::ExportFormat;
+use crate::file::filter::{parse_attribute, validate_tag};
 
 #[derive(Parser, Debug)]
 #[command(name = "nebula")]
@@ -42,24 +45,293 @@ pub enum Commands {
         /// Run as daemon (background process)
         #[arg(short, long)]
         daemon: bool,
+        
+        /// With --daemon, sweep expired files once and exit instead of
+        /// running until SIGINT or SIGTERM
+        #[arg(long, requires = "daemon")]
+        once: bool,
     },
     
     /// Store a file in the distributed file system
     Put {
-        /// File path to store
+        /// File path to store (or directory with --recursive)
         file: PathBuf,
+        /// Store a whole directory as a tree of content-addressed objects
+        #[arg(short, long)]
+        recursive: bool,
+        /// Logical path to store under; re-using a path adds a new version
+        #[arg(long)]
+        path: Option<String>,
+        /// Tag to attach (repeatable)
+        #[arg(long = "tag", value_parser = validate_tag)]
+        tags: Vec<String>,
+        /// Attribute to attach as key=value (repeatable)
+        #[arg(long = "attr", value_parser = parse_attribute)]
+        attributes: Vec<(String, String)>,
+        /// Store a symlink as a link instead of the file it points to
+        #[arg(short = 'P', long)]
+        no_dereference: bool,
+        /// What to do if identical content is already registered: reuse, warn or new
+        /// (defaults to the configured duplicate policy)
+        #[arg(long)]
+        on_duplicate: Option<DuplicatePolicy>,
+        /// Expire the file after this long, e.g. 90m, 12h or 7d
+        #[arg(long)]
+        ttl: Option<String>,
         /// Optional custom storage location
         #[arg(short, long)]
         storage: Option<PathBuf>,
-        /// Output format (id, short, json)
+        /// Output format (id, short, json, addresses)
         #[arg(long, default_value = "id")]
         format: String,
+        /// Also build a BLAKE3 outboard tree for verified range reads
+        #[arg(long, conflicts_with = "recursive")]
+        verified: bool,
     },
     
     /// Retrieve a file from the distributed file system
     Get {
-        /// File ID to retrieve
+        /// File ID or content address (any unique prefix), logical path or ref name
+        file_id: String,
+        /// Output file path (destination directory with --recursive)
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Rebuild a directory stored with `put --recursive`
+        #[arg(short, long)]
+        recursive: bool,
+        /// Treat the argument as a logical path and fetch this version number
+        #[arg(long, conflicts_with = "at")]
+        version: Option<u32>,
+        /// Treat the argument as a logical path and fetch the version current at this Unix timestamp
+        #[arg(long)]
+        at: Option<u64>,
+        /// Restore permissions, timestamps, ownership and extended attributes
+        /// (trees only record permissions, which are always restored)
+        #[arg(short, long, conflicts_with = "recursive")]
+        preserve: bool,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Move a file to the trash, or delete it and release the chunks no other file references
+    Rm {
+        /// File ID (any unique prefix) or logical path
         file_id: String,
+        /// Delete immediately instead of moving the file to the trash
+        #[arg(long)]
+        permanent: bool,
+        /// Leave the file's chunks in the store for a later `gc` run
+        #[arg(long, requires = "permanent")]
+        keep_chunks: bool,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// List, restore or empty deleted files
+    Trash {
+        #[command(subcommand)]
+        action: TrashCommands,
+        /// Optional custom storage location
+        #[arg(short, long, global = true)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Manage refs: mutable names pointing at files or content
+    Ref {
+        #[command(subcommand)]
+        action: RefCommands,
+        /// Optional custom storage location
+        #[arg(short, long, global = true)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Write the registry to a file for migration to another node
+    ExportRegistry {
+        /// File to write
+        output: PathBuf,
+        /// Output format: jsonl or csv (defaults to csv for a .csv file, jsonl otherwise)
+        #[arg(long)]
+        format: Option<ExportFormat>,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Register the entries of a JSON-lines registry export
+    ImportRegistry {
+        /// Export written by `export-registry`
+        input: PathBuf,
+        /// Give entries whose ID is already in use a new ID instead of failing
+        #[arg(long)]
+        rekey: bool,
+        /// Copy the chunks the entries need from this node storage or content directory
+        #[arg(long)]
+        chunks_from: Option<PathBuf>,
+        /// Register entries even if some of their chunks are not stored here
+        #[arg(long)]
+        allow_missing: bool,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Back up a directory, reusing files unchanged since the last backup of the same name
+    Backup {
+        /// Directory to back up
+        dir: PathBuf,
+        /// Name to record the backup under (defaults to the directory name)
+        #[arg(long)]
+        name: Option<String>,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// List backups, oldest first
+    Backups {
+        /// Only backups recorded under this name
+        name: Option<String>,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Forget backups a retention policy does not keep and release their chunks
+    #[command(group = clap::ArgGroup::new("policy").required(true).multiple(true).args(["keep_last", "keep_daily", "keep_weekly"]))]
+    Forget {
+        /// Only apply the policy to backups recorded under this name
+        name: Option<String>,
+        /// Keep the N most recent backups
+        #[arg(long)]
+        keep_last: Option<usize>,
+        /// Keep the newest backup of each of the last N days that have one
+        #[arg(long)]
+        keep_daily: Option<usize>,
+        /// Keep the newest backup of each of the last N weeks that have one
+        #[arg(long)]
+        keep_weekly: Option<usize>,
+        /// Only report what would be forgotten
+        #[arg(long)]
+        dry_run: bool,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Capture, list, restore and delete snapshots of the registered file set
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotCommands,
+        /// Optional custom storage location
+        #[arg(short, long, global = true)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Pin files so they are never removed, expired or collected
+    Pin {
+        #[command(subcommand)]
+        action: PinCommands,
+        /// Optional custom storage location
+        #[arg(short, long, global = true)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Delete stored chunks that no registered file references
+    Gc {
+        /// Only report what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Remove files whose time-to-live has run out and release their chunks
+    Expire {
+        /// Only report what would be removed
+        #[arg(long)]
+        dry_run: bool,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Compare the chunks of two stored files without downloading them
+    Diff {
+        /// Old file: ID (any unique prefix) or logical path
+        old: String,
+        /// New file: ID (any unique prefix) or logical path
+        new: String,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Show logical, shared and unique bytes for each registered file
+    Du {
+        /// Only show the N files that would free the most space
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// List the files that share the most chunks with a file
+    Similar {
+        /// File ID (any unique prefix) or logical path
+        file_id: String,
+        /// Maximum number of files to list
+        #[arg(long, default_value = "10")]
+        limit: usize,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Report registered files with identical content
+    Dupes {
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Show the version history of a logical path
+    Log {
+        /// Logical path to show
+        path: String,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Remove old versions of a logical path
+    Prune {
+        /// Logical path to prune
+        path: String,
+        /// Keep the N most recent versions
+        #[arg(long)]
+        keep_last: Option<usize>,
+        /// Keep versions newer than this age (e.g. 30d, 12h)
+        #[arg(long)]
+        keep_within: Option<String>,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Retrieve a verified byte range of a file stored with --verified
+    GetRange {
+        /// File ID (full UUID or any unique prefix)
+        file_id: String,
+        /// Byte offset to start reading from
+        #[arg(long, default_value = "0")]
+        offset: u64,
+        /// Number of bytes to read (defaults to the rest of the file)
+        #[arg(long)]
+        length: Option<u64>,
         /// Output file path
         #[arg(short, long)]
         output: PathBuf,
@@ -68,6 +340,41 @@ pub enum Commands {
         storage: Option<PathBuf>,
     },
     
+    /// Print a Merkle inclusion proof for one chunk of a file
+    Prove {
+        /// File ID (full UUID or any unique prefix)
+        file_id: String,
+        /// Zero-based index of the chunk to prove
+        chunk_index: usize,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Check that registered files are intact in the store, or verify a
+    /// Merkle inclusion proof offline
+    #[command(group = clap::ArgGroup::new("subject").required(true).args(["file_id", "all", "proof"]))]
+    Verify {
+        /// File ID (any unique prefix), logical path or ref to check
+        file_id: Option<String>,
+        /// Check every registered file
+        #[arg(long)]
+        all: bool,
+        /// Proof as printed by `nebula prove`, checked without the store
+        #[arg(long, requires = "root", conflicts_with = "storage")]
+        proof: Option<String>,
+        /// Trusted Merkle root of the file, as shown by `nebula list-files --verbose`;
+        /// the root carried inside the proof is not trusted
+        #[arg(long, requires = "proof")]
+        root: Option<String>,
+        /// Optional file containing the chunk data to check against the proof
+        #[arg(long, requires = "proof")]
+        chunk: Option<PathBuf>,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
     /// List stored content
     List {
         #[arg(short, long)]
@@ -84,6 +391,83 @@ pub enum Commands {
         /// Show detailed information
         #[arg(long)]
         verbose: bool,
+        /// Only files whose name or path contains this text
+        #[arg(long)]
+        name: Option<String>,
+        /// Only files carrying this tag (repeatable)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Only files with this attribute as key=value (repeatable)
+        #[arg(long = "attr", value_parser = parse_attribute)]
+        attributes: Vec<(String, String)>,
+        /// Only files of this MIME type, e.g. `image/png`, `image/*` or `image`
+        #[arg(long)]
+        mime: Option<String>,
+    },
+    
+    /// Search registered files, e.g. `nebula find name:*.log size>10MB tag:prod`
+    Find {
+        /// Query terms; all must match (see the `Query` syntax)
+        query: Vec<String>,
+        /// Sort by name, path, size, created or version
+        #[arg(long, default_value = "path")]
+        sort: String,
+        /// Sort in descending order
+        #[arg(long)]
+        desc: bool,
+        /// Maximum number of results to show
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Number of results to skip
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Add or remove tags on a file
+    Tag {
+        /// File ID (any unique prefix) or logical path
+        file_id: String,
+        /// Tag to add (repeatable)
+        #[arg(long, value_parser = validate_tag)]
+        add: Vec<String>,
+        /// Tag to remove (repeatable)
+        #[arg(long)]
+        remove: Vec<String>,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Set or unset key/value attributes on a file
+    Attr {
+        /// File ID (any unique prefix) or logical path
+        file_id: String,
+        /// Attribute to set as key=value (repeatable)
+        #[arg(long, value_parser = parse_attribute)]
+        set: Vec<(String, String)>,
+        /// Attribute key to remove (repeatable)
+        #[arg(long)]
+        unset: Vec<String>,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
+    },
+    
+    /// Rename or move a file to a new logical path, keeping its ID and history
+    Mv {
+        /// File ID (any unique prefix) or logical path
+        file_id: String,
+        /// New logical path (a bare name keeps the file at the top level)
+        new_name: String,
+        /// Fail if another file already uses the new path or name
+        #[arg(long)]
+        unique: bool,
+        /// Optional custom storage location
+        #[arg(short, long)]
+        storage: Option<PathBuf>,
     },
     
     /// Show storage statistics
@@ -116,4 +500,116 @@ pub enum Commands {
         #[arg(short, long)]
         storage: Option<PathBuf>,
     },
+}
+#[derive(Subcommand, Debug)]
+pub enum TrashCommands {
+    /// Show deleted files and how long they remain restorable
+    List,
+    
+    /// Put a deleted file back
+    Restore {
+        /// File ID (any unique prefix) or logical path
+        file_id: String,
+    },
+    
+    /// Permanently delete trashed files and release their chunks
+    Empty {
+        /// Only files deleted at least this long ago, e.g. `3d`
+        #[arg(long)]
+        older_than: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SnapshotCommands {
+    /// Snapshot the registered files as they are now
+    Create {
+        /// Name to refer to the snapshot by, in addition to its ID
+        #[arg(long)]
+        name: Option<String>,
+    },
+    
+    /// List snapshots, oldest first
+    #[command(alias = "ls")]
+    List,
+    
+    /// Make the registered files exactly those of a snapshot
+    Restore {
+        /// Snapshot name or ID (any unique prefix)
+        snapshot: String,
+    },
+    
+    /// Delete a snapshot and release the chunks only it kept
+    #[command(alias = "rm")]
+    Delete {
+        /// Snapshot name or ID (any unique prefix)
+        snapshot: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PinCommands {
+    /// Pin a file or directory
+    Add {
+        /// File ID (any unique prefix), logical path or ref
+        file_id: String,
+        /// Also hold every entry registered below its logical path
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    
+    /// Remove a pin
+    Rm {
+        /// File ID (any unique prefix), logical path or ref
+        file_id: String,
+    },
+    
+    /// List pinned files
+    #[command(alias = "list")]
+    Ls,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RefCommands {
+    /// Point a ref at a file or content
+    Set {
+        /// Ref name, e.g. `nightly-build/latest`
+        name: String,
+        /// File ID, logical path, content address or another ref
+        target: String,
+        /// Only update if the ref currently points here (compare-and-swap)
+        #[arg(long, conflicts_with = "create")]
+        expect: Option<String>,
+        /// Only create the ref; fail if it already exists
+        #[arg(long)]
+        create: bool,
+    },
+    
+    /// Show what a ref points at
+    Show {
+        /// Ref name
+        name: String,
+    },
+    
+    /// List refs
+    #[command(alias = "ls")]
+    List {
+        /// Only refs starting with this prefix
+        prefix: Option<String>,
+    },
+    
+    /// Show where a ref has pointed over time
+    Log {
+        /// Ref name
+        name: String,
+    },
+    
+    /// Delete a ref (its history is kept)
+    Rm {
+        /// Ref name
+        name: String,
+        /// Only delete if the ref currently points here
+        #[arg(long)]
+        expect: Option<String>,
+    },
 }
\ No newline at end of file
@@ -18,7 +18,7 @@ impl Config {
         
         // Apply command-specific arguments based on the command
         match &args.command {
-            crate::args::Commands::Start { port, storage, address, daemon } => {
+            crate::args::Commands::Start { port, storage, address, daemon, .. } => {
                 self.listen_port = *port;
                 if let Some(storage_path) = storage {
                     self.storage_dir = storage_path.clone();
@@ -29,6 +29,31 @@ impl Config {
             // For commands that specify storage directory
             crate::args::Commands::Put { storage, .. } |
             crate::args::Commands::Get { storage, .. } |
+            crate::args::Commands::Rm { storage, .. } |
+            crate::args::Commands::Trash { storage, .. } |
+            crate::args::Commands::Gc { storage, .. } |
+            crate::args::Commands::Find { storage, .. } |
+            crate::args::Commands::Tag { storage, .. } |
+            crate::args::Commands::Attr { storage, .. } |
+            crate::args::Commands::Mv { storage, .. } |
+            crate::args::Commands::Ref { storage, .. } |
+            crate::args::Commands::Dupes { storage, .. } |
+            crate::args::Commands::Similar { storage, .. } |
+            crate::args::Commands::Du { storage, .. } |
+            crate::args::Commands::Diff { storage, .. } |
+            crate::args::Commands::Expire { storage, .. } |
+            crate::args::Commands::Pin { storage, .. } |
+            crate::args::Commands::Snapshot { storage, .. } |
+            crate::args::Commands::Backup { storage, .. } |
+            crate::args::Commands::Backups { storage, .. } |
+            crate::args::Commands::Forget { storage, .. } |
+            crate::args::Commands::ExportRegistry { storage, .. } |
+            crate::args::Commands::ImportRegistry { storage, .. } |
+            crate::args::Commands::Verify { storage, .. } |
+            crate::args::Commands::Log { storage, .. } |
+            crate::args::Commands::Prune { storage, .. } |
+            crate::args::Commands::GetRange { storage, .. } |
+            crate::args::Commands::Prove { storage, .. } |
             crate::args::Commands::List { storage, .. } |
             crate::args::Commands::ListFiles { storage, .. } |
             crate::args::Commands::Stats { storage } |
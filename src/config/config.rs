@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::fs;
-use super::enums::LogLevel;
+use super::enums::{DuplicatePolicy, LogLevel};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -18,6 +18,18 @@ pub struct Config {
     
     // Global options from CLI
     pub verbose: bool,
+    
+    // Retention
+    #[serde(default = "default_trash_retention")]
+    pub trash_retention: u64, // Seconds a deleted file stays restorable
+    
+    // Ingest
+    #[serde(default)]
+    pub duplicate_policy: DuplicatePolicy, // What put does with content that is already registered
+}
+
+fn default_trash_retention() -> u64 {
+    crate::file::trash::DEFAULT_TRASH_RETENTION
 }
 
 impl Default for Config {
@@ -34,6 +46,8 @@ impl Default for Config {
             log_level: LogLevel::default(),
             daemon_mode: false,
             verbose: false,
+            trash_retention: default_trash_retention(),
+            duplicate_policy: DuplicatePolicy::default(),
         }
     }
 }
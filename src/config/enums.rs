@@ -1,21 +1,16 @@
 use serde::{Deserialize, Serialize};
 
-/// Log level enumeration
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+/// Log level enumeration, ordered from least to most verbose
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LogLevel {
     Error,
     Warn,
+    #[default]
     Info,
     Debug,
     Trace,
 }
 
-impl Default for LogLevel {
-    fn default() -> Self {
-        LogLevel::Info
-    }
-}
-
 impl std::fmt::Display for LogLevel {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -43,9 +38,46 @@ impl std::str::FromStr for LogLevel {
     }
 }
 
+/// What `put` does when identical content is already registered
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum DuplicatePolicy {
+    /// Return the existing entry instead of registering a new one, even if
+    /// it is registered under another path
+    Reuse,
+    /// Register a new entry and point out the existing one
+    #[default]
+    Warn,
+    /// Register a new entry without comment
+    New,
+}
+
+impl std::fmt::Display for DuplicatePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DuplicatePolicy::Reuse => write!(f, "reuse"),
+            DuplicatePolicy::Warn => write!(f, "warn"),
+            DuplicatePolicy::New => write!(f, "new"),
+        }
+    }
+}
+
+impl std::str::FromStr for DuplicatePolicy {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "reuse" => Ok(DuplicatePolicy::Reuse),
+            "warn" => Ok(DuplicatePolicy::Warn),
+            "new" => Ok(DuplicatePolicy::New),
+            _ => Err(format!("Invalid duplicate policy: {} (expected reuse, warn or new)", s)),
+        }
+    }
+}
+
 /// Node state enumeration
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub enum NodeState {
+    #[default]
     Stopped,
     Starting,
     Running,
@@ -53,12 +85,6 @@ pub enum NodeState {
     Error,
 }
 
-impl Default for NodeState {
-    fn default() -> Self {
-        NodeState::Stopped
-    }
-}
-
 impl std::fmt::Display for NodeState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -1,10 +1,11 @@
 // Main config module
 
 pub mod enums;
+#[allow(clippy::module_inception)]
 pub mod config;
 pub mod builders;
 
 // Re-export public items for easier access
-pub use enums::{LogLevel, NodeState};
+pub use enums::{DuplicatePolicy, LogLevel, NodeState};
 pub use config::Config;
 // Builder methods are directly implemented on Config struct
@@ -4,18 +4,13 @@ use std::fmt;
 use std::str::FromStr;
 
 /// Hash algorithm used for content addressing
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum HashAlgorithm {
+    #[default]
     Sha256,
     Blake3,
 }
 
-impl Default for HashAlgorithm {
-    fn default() -> Self {
-        HashAlgorithm::Sha256
-    }
-}
-
 impl fmt::Display for HashAlgorithm {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -58,6 +53,11 @@ impl ContentAddress {
         Self { hash, algorithm }
     }
     
+    /// Create content address from an already computed hash
+    pub fn from_hash(hash: [u8; 32], algorithm: HashAlgorithm) -> Self {
+        Self { hash, algorithm }
+    }
+    
     /// Convert to hexadecimal string representation
     pub fn to_hex(&self) -> String {
         format!("{}:{}", self.algorithm, hex::encode(self.hash))
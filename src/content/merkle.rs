@@ -0,0 +1,376 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::address::{ContentAddress, HashAlgorithm};
+
+/// Domain separation prefixes so a leaf, an inner node and the root can
+/// never be confused with one another
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+const ROOT_PREFIX: u8 = 0x02;
+
+/// Version byte at the start of the compact proof encoding
+const PROOF_VERSION: u8 = 2;
+
+/// Hash the given parts with the requested algorithm
+fn digest(algorithm: HashAlgorithm, parts: &[&[u8]]) -> [u8; 32] {
+    let data = parts.concat();
+    *ContentAddress::from_data_with_algorithm(&data, algorithm).hash_bytes()
+}
+
+/// Leaves commit to their position, so a proof cannot claim another index
+fn hash_leaf(algorithm: HashAlgorithm, index: usize, leaf: &ContentAddress) -> [u8; 32] {
+    digest(algorithm, &[&[LEAF_PREFIX], &(index as u64).to_be_bytes(), leaf.hash_bytes()])
+}
+
+fn hash_node(algorithm: HashAlgorithm, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    digest(algorithm, &[&[NODE_PREFIX], left, right])
+}
+
+/// The root commits to the number of leaves, which fixes the tree's shape
+fn hash_root(algorithm: HashAlgorithm, leaf_count: usize, top: &[u8; 32]) -> [u8; 32] {
+    digest(algorithm, &[&[ROOT_PREFIX], &(leaf_count as u64).to_be_bytes(), top])
+}
+
+/// Binary Merkle tree built over the chunk addresses of a file.
+///
+/// Leaves are paired left to right; an unpaired node at the end of a level
+/// is promoted to the next level unchanged. Each leaf hash includes its
+/// index and the root includes the leaf count, so neither can be forged.
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    algorithm: HashAlgorithm,
+    leaves: Vec<ContentAddress>,
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    /// Build a tree from an ordered list of chunk addresses.
+    /// Returns `None` when there are no chunks (empty file).
+    pub fn from_addresses(addresses: &[ContentAddress]) -> Option<Self> {
+        let algorithm = addresses.first()?.algorithm();
+
+        let mut levels = vec![addresses.iter().enumerate().map(|(index, addr)| hash_leaf(algorithm, index, addr)).collect::<Vec<_>>()];
+        while levels.last().is_some_and(|level| level.len() > 1) {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_node(algorithm, left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        Some(Self {
+            algorithm,
+            leaves: addresses.to_vec(),
+            levels,
+        })
+    }
+
+    /// Root of the tree as a content address
+    pub fn root(&self) -> ContentAddress {
+        let top = self.levels.last().and_then(|level| level.first()).copied().unwrap_or_default();
+        ContentAddress::from_hash(hash_root(self.algorithm, self.leaf_count(), &top), self.algorithm)
+    }
+
+    /// Number of leaves (chunks) in the tree
+    pub fn leaf_count(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Generate an inclusion proof for the chunk at `index`
+    pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+        let leaf = self.leaves.get(index)?.clone();
+
+        let mut siblings = Vec::new();
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = position ^ 1;
+            if sibling < level.len() {
+                siblings.push(level[sibling]);
+            }
+            position /= 2;
+        }
+
+        Some(MerkleProof {
+            leaf_index: index,
+            leaf_count: self.leaf_count(),
+            leaf,
+            siblings,
+            root: self.root(),
+        })
+    }
+}
+
+/// Proof that a single chunk is part of a file with a given Merkle root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub leaf_index: usize,
+    pub leaf_count: usize,
+    pub leaf: ContentAddress,
+    pub siblings: Vec<[u8; 32]>,
+    pub root: ContentAddress,
+}
+
+impl MerkleProof {
+    /// Check that the leaf and sibling path hash up to `trusted_root`.
+    ///
+    /// The root carried in the proof is only informational; a proof is
+    /// worthless unless the root comes from somewhere the verifier trusts.
+    pub fn verify(&self, trusted_root: &ContentAddress) -> bool {
+        if self.root != *trusted_root
+            || self.leaf_index >= self.leaf_count
+            || self.leaf.algorithm() != self.root.algorithm()
+        {
+            return false;
+        }
+
+        let algorithm = self.root.algorithm();
+        let mut hash = hash_leaf(algorithm, self.leaf_index, &self.leaf);
+        let mut position = self.leaf_index;
+        let mut width = self.leaf_count;
+        let mut siblings = self.siblings.iter();
+
+        while width > 1 {
+            if position % 2 == 1 {
+                let Some(left) = siblings.next() else { return false };
+                hash = hash_node(algorithm, left, &hash);
+            } else if position + 1 < width {
+                let Some(right) = siblings.next() else { return false };
+                hash = hash_node(algorithm, &hash, right);
+            }
+            position /= 2;
+            width = width.div_ceil(2);
+        }
+
+        siblings.next().is_none() && hash_root(algorithm, self.leaf_count, &hash) == *self.root.hash_bytes()
+    }
+
+    /// Check that `data` is the proven chunk and that the proof is valid
+    pub fn verify_chunk(&self, data: &[u8], trusted_root: &ContentAddress) -> bool {
+        ContentAddress::from_data_with_algorithm(data, self.leaf.algorithm()) == self.leaf && self.verify(trusted_root)
+    }
+
+    /// Encode the proof as a single hex string
+    pub fn to_compact(&self) -> String {
+        let mut bytes = Vec::with_capacity(74 + self.siblings.len() * 32);
+        bytes.push(PROOF_VERSION);
+        bytes.push(algorithm_tag(self.root.algorithm()));
+        bytes.extend_from_slice(&(self.leaf_index as u32).to_be_bytes());
+        bytes.extend_from_slice(&(self.leaf_count as u32).to_be_bytes());
+        bytes.extend_from_slice(self.leaf.hash_bytes());
+        bytes.extend_from_slice(self.root.hash_bytes());
+        for sibling in &self.siblings {
+            bytes.extend_from_slice(sibling);
+        }
+        hex::encode(bytes)
+    }
+
+    /// Decode a proof produced by [`MerkleProof::to_compact`]
+    pub fn from_compact(encoded: &str) -> Result<Self, MerkleProofError> {
+        let bytes = hex::decode(encoded.trim()).map_err(|_| MerkleProofError::InvalidHex)?;
+        if bytes.len() < 74 || (bytes.len() - 74) % 32 != 0 {
+            return Err(MerkleProofError::InvalidLength);
+        }
+        if bytes[0] != PROOF_VERSION {
+            return Err(MerkleProofError::UnsupportedVersion(bytes[0]));
+        }
+
+        let algorithm = match bytes[1] {
+            0 => HashAlgorithm::Sha256,
+            1 => HashAlgorithm::Blake3,
+            _ => return Err(MerkleProofError::UnsupportedAlgorithm),
+        };
+        let leaf_index = u32::from_be_bytes(bytes[2..6].try_into().unwrap()) as usize;
+        let leaf_count = u32::from_be_bytes(bytes[6..10].try_into().unwrap()) as usize;
+        let leaf = ContentAddress::from_hash(bytes[10..42].try_into().unwrap(), algorithm);
+        let root = ContentAddress::from_hash(bytes[42..74].try_into().unwrap(), algorithm);
+        let siblings = bytes[74..]
+            .chunks(32)
+            .map(|sibling| sibling.try_into().unwrap())
+            .collect();
+
+        Ok(Self { leaf_index, leaf_count, leaf, siblings, root })
+    }
+}
+
+fn algorithm_tag(algorithm: HashAlgorithm) -> u8 {
+    match algorithm {
+        HashAlgorithm::Sha256 => 0,
+        HashAlgorithm::Blake3 => 1,
+    }
+}
+
+impl fmt::Display for MerkleProof {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_compact())
+    }
+}
+
+impl FromStr for MerkleProof {
+    type Err = MerkleProofError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_compact(s)
+    }
+}
+
+/// Errors that can occur when decoding a Merkle proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MerkleProofError {
+    InvalidHex,
+    InvalidLength,
+    UnsupportedVersion(u8),
+    UnsupportedAlgorithm,
+}
+
+impl fmt::Display for MerkleProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MerkleProofError::InvalidHex => write!(f, "Invalid hexadecimal encoding"),
+            MerkleProofError::InvalidLength => write!(f, "Invalid proof length"),
+            MerkleProofError::UnsupportedVersion(v) => write!(f, "Unsupported proof version: {}", v),
+            MerkleProofError::UnsupportedAlgorithm => write!(f, "Unsupported hash algorithm"),
+        }
+    }
+}
+
+impl std::error::Error for MerkleProofError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addresses(count: usize) -> Vec<ContentAddress> {
+        (0..count)
+            .map(|i| ContentAddress::from_data(format!("chunk {}", i).as_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn test_empty_tree() {
+        assert!(MerkleTree::from_addresses(&[]).is_none());
+    }
+
+    #[test]
+    fn test_single_leaf_tree() {
+        let leaves = addresses(1);
+        let tree = MerkleTree::from_addresses(&leaves).unwrap();
+        let proof = tree.proof(0).unwrap();
+
+        assert!(proof.siblings.is_empty());
+        assert!(proof.verify(&tree.root()));
+    }
+
+    #[test]
+    fn test_all_proofs_verify() {
+        for count in 1..=9 {
+            let leaves = addresses(count);
+            let tree = MerkleTree::from_addresses(&leaves).unwrap();
+
+            for index in 0..count {
+                let proof = tree.proof(index).unwrap();
+                assert!(proof.verify(&tree.root()), "proof {} of {} failed", index, count);
+                assert_eq!(proof.root, tree.root());
+            }
+            assert!(tree.proof(count).is_none());
+        }
+    }
+
+    #[test]
+    fn test_root_depends_on_order() {
+        let mut leaves = addresses(4);
+        let root = MerkleTree::from_addresses(&leaves).unwrap().root();
+        leaves.swap(0, 1);
+
+        assert_ne!(root, MerkleTree::from_addresses(&leaves).unwrap().root());
+    }
+
+    #[test]
+    fn test_tampered_proof_fails() {
+        let leaves = addresses(5);
+        let tree = MerkleTree::from_addresses(&leaves).unwrap();
+
+        let root = tree.root();
+
+        let mut proof = tree.proof(2).unwrap();
+        proof.leaf = ContentAddress::from_data(b"something else");
+        assert!(!proof.verify(&root));
+
+        let mut proof = tree.proof(2).unwrap();
+        proof.siblings[0][0] ^= 0xff;
+        assert!(!proof.verify(&root));
+
+        let mut proof = tree.proof(2).unwrap();
+        proof.leaf_index = 3;
+        assert!(!proof.verify(&root));
+    }
+
+    #[test]
+    fn test_proof_must_match_trusted_root() {
+        let tree = MerkleTree::from_addresses(&addresses(4)).unwrap();
+        let other = MerkleTree::from_addresses(&addresses(3)).unwrap();
+
+        // A self-consistent proof for another tree is rejected
+        let proof = other.proof(1).unwrap();
+        assert!(proof.verify(&other.root()));
+        assert!(!proof.verify(&tree.root()));
+
+        // Rewriting the carried root to the trusted one does not help
+        let mut forged = proof.clone();
+        forged.root = tree.root();
+        assert!(!forged.verify(&tree.root()));
+    }
+
+    #[test]
+    fn test_count_and_index_are_committed() {
+        // With a promoted last leaf, 3 and 4 leaves share the same shape
+        // for the leading pair; only the committed count tells them apart
+        let leaves = addresses(4);
+        let tree = MerkleTree::from_addresses(&leaves).unwrap();
+        assert_ne!(tree.root(), MerkleTree::from_addresses(&leaves[..3]).unwrap().root());
+
+        let mut proof = tree.proof(0).unwrap();
+        proof.leaf_count = 2;
+        proof.siblings.truncate(1);
+        assert!(!proof.verify(&tree.root()));
+
+        // The promoted leaf of a 5-leaf tree cannot pose as another index
+        let tree = MerkleTree::from_addresses(&addresses(5)).unwrap();
+        let mut proof = tree.proof(4).unwrap();
+        proof.leaf_index = 6;
+        proof.leaf_count = 7;
+        assert!(!proof.verify(&tree.root()));
+    }
+
+    #[test]
+    fn test_verify_chunk_data() {
+        let data = b"chunk 3";
+        let leaves = addresses(6);
+        let tree = MerkleTree::from_addresses(&leaves).unwrap();
+        let proof = tree.proof(3).unwrap();
+
+        assert!(proof.verify_chunk(data, &tree.root()));
+        assert!(!proof.verify_chunk(b"chunk 4", &tree.root()));
+    }
+
+    #[test]
+    fn test_compact_roundtrip() {
+        let leaves = addresses(7);
+        let tree = MerkleTree::from_addresses(&leaves).unwrap();
+        let proof = tree.proof(6).unwrap();
+
+        let encoded = proof.to_compact();
+        let decoded: MerkleProof = encoded.parse().unwrap();
+        assert_eq!(proof, decoded);
+        assert!(decoded.verify(&tree.root()));
+
+        assert!(MerkleProof::from_compact("zz").is_err());
+        assert!(MerkleProof::from_compact(&encoded[..encoded.len() - 2]).is_err());
+    }
+}
@@ -1,6 +1,12 @@
 // Content addressing module
 
 pub mod address;
+pub mod merkle;
+pub mod prefix;
+pub mod stream;
 
 // Re-export commonly used items
 pub use address::{ContentAddress, HashAlgorithm};
+pub use merkle::{MerkleTree, MerkleProof};
+pub use stream::Outboard;
+pub use prefix::PrefixIndex;
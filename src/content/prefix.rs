@@ -0,0 +1,99 @@
+/// Sorted index of identifiers supporting git-style abbreviation.
+///
+/// Used both for file IDs and content addresses: any prefix can be resolved
+/// to the keys it matches, and every key can be shown by its shortest
+/// prefix that no other key shares.
+#[derive(Debug, Clone, Default)]
+pub struct PrefixIndex {
+    keys: Vec<String>,
+}
+
+impl PrefixIndex {
+    /// Build an index from a set of keys (duplicates are ignored)
+    pub fn new<I: IntoIterator<Item = String>>(keys: I) -> Self {
+        let mut keys: Vec<String> = keys.into_iter().collect();
+        keys.sort();
+        keys.dedup();
+        Self { keys }
+    }
+
+    /// All keys starting with `prefix`, in sorted order
+    pub fn matches(&self, prefix: &str) -> &[String] {
+        let start = self.keys.partition_point(|key| key.as_str() < prefix);
+        let len = self.keys[start..].partition_point(|key| key.starts_with(prefix));
+        &self.keys[start..start + len]
+    }
+
+    /// Length of the shortest prefix of `key` that matches no other key,
+    /// never shorter than `min_len` (or longer than the key itself)
+    pub fn unique_prefix_len(&self, key: &str, min_len: usize) -> usize {
+        let position = self.keys.partition_point(|k| k.as_str() < key);
+        let mut shared = 0;
+        if position > 0 {
+            shared = shared.max(common_prefix_len(key, &self.keys[position - 1]));
+        }
+        let next = if self.keys.get(position).map(String::as_str) == Some(key) { position + 1 } else { position };
+        if let Some(neighbour) = self.keys.get(next) {
+            shared = shared.max(common_prefix_len(key, neighbour));
+        }
+        (shared + 1).max(min_len).min(key.len())
+    }
+
+    /// Shortest unique abbreviation of `key`
+    pub fn shortest_unique<'a>(&self, key: &'a str, min_len: usize) -> &'a str {
+        &key[..self.unique_prefix_len(key, min_len)]
+    }
+
+    /// Number of keys in the index
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Whether the index holds no keys
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> PrefixIndex {
+        PrefixIndex::new(["abc123", "abd456", "ffff00", "abc199"].map(String::from))
+    }
+
+    #[test]
+    fn test_matches() {
+        let index = index();
+
+        assert_eq!(index.matches("ab").len(), 3);
+        assert_eq!(index.matches("abc"), &["abc123".to_string(), "abc199".to_string()]);
+        assert_eq!(index.matches("abc12"), &["abc123".to_string()]);
+        assert!(index.matches("0").is_empty());
+        assert_eq!(index.matches("").len(), 4);
+    }
+
+    #[test]
+    fn test_shortest_unique() {
+        let index = index();
+
+        assert_eq!(index.shortest_unique("abc123", 1), "abc12");
+        assert_eq!(index.shortest_unique("abd456", 1), "abd");
+        assert_eq!(index.shortest_unique("ffff00", 1), "f");
+        assert_eq!(index.shortest_unique("ffff00", 4), "ffff");
+        assert_eq!(index.shortest_unique("ffff00", 10), "ffff00");
+    }
+
+    #[test]
+    fn test_shortest_unique_for_unknown_key() {
+        let index = index();
+
+        // Keys not in the index are abbreviated against their would-be neighbours
+        assert_eq!(index.shortest_unique("abc150", 1), "abc15");
+    }
+}
@@ -0,0 +1,131 @@
+use std::io::{self, Cursor, Read, Seek};
+
+use super::address::{ContentAddress, HashAlgorithm};
+
+/// BLAKE3 tree hash of a file together with its bao outboard encoding.
+///
+/// The root is the plain BLAKE3 hash of the file contents, so it can be
+/// compared with any other BLAKE3 digest of the same data.
+#[derive(Debug, Clone)]
+pub struct Outboard {
+    pub root: ContentAddress,
+    pub data: Vec<u8>,
+}
+
+impl Outboard {
+    /// Hash `content` as a stream and build its outboard tree
+    pub fn build<R: Read>(mut content: R) -> io::Result<Self> {
+        let mut encoder = bao::encode::Encoder::new_outboard(Cursor::new(Vec::new()));
+        io::copy(&mut content, &mut encoder)?;
+        let hash = encoder.finalize()?;
+
+        Ok(Self {
+            root: ContentAddress::from_hash(*hash.as_bytes(), HashAlgorithm::Blake3),
+            data: encoder.into_inner().into_inner(),
+        })
+    }
+}
+
+/// Read `len` bytes starting at `start` from `content`, verifying every byte
+/// against `root` using the outboard tree. Fails with `InvalidData` on mismatch.
+pub fn read_verified_range<C, O>(
+    content: C,
+    outboard: O,
+    root: &ContentAddress,
+    start: u64,
+    len: u64,
+) -> io::Result<Vec<u8>>
+where
+    C: Read + Seek,
+    O: Read + Seek,
+{
+    if root.algorithm() != HashAlgorithm::Blake3 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("verified streaming requires a blake3 root, got {}", root.algorithm()),
+        ));
+    }
+
+    let mut slice = Vec::new();
+    let mut extractor = bao::encode::SliceExtractor::new_outboard(content, outboard, start, len);
+    extractor.read_to_end(&mut slice)?;
+
+    let hash = bao::Hash::from(*root.hash_bytes());
+    let mut decoder = bao::decode::SliceDecoder::new(&*slice, &hash, start, len);
+    let mut verified = Vec::new();
+    decoder.read_to_end(&mut verified)?;
+    Ok(verified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_data() -> Vec<u8> {
+        (0..20_000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_root_matches_blake3() {
+        let data = sample_data();
+        let outboard = Outboard::build(&data[..]).unwrap();
+
+        assert_eq!(outboard.root, ContentAddress::from_data_with_algorithm(&data, HashAlgorithm::Blake3));
+        assert!(!outboard.data.is_empty());
+    }
+
+    #[test]
+    fn test_verified_range() {
+        let data = sample_data();
+        let outboard = Outboard::build(&data[..]).unwrap();
+
+        for (start, len) in [(0, 1024), (3000, 5000), (19_990, 100), (1023, 2)] {
+            let range = read_verified_range(
+                Cursor::new(&data),
+                Cursor::new(&outboard.data),
+                &outboard.root,
+                start,
+                len,
+            ).unwrap();
+            let end = ((start + len) as usize).min(data.len());
+            assert_eq!(range, &data[start as usize..end]);
+        }
+    }
+
+    #[test]
+    fn test_corrupted_content_detected() {
+        let data = sample_data();
+        let outboard = Outboard::build(&data[..]).unwrap();
+
+        let mut corrupted = data.clone();
+        corrupted[4100] ^= 0xff;
+
+        let err = read_verified_range(
+            Cursor::new(&corrupted),
+            Cursor::new(&outboard.data),
+            &outboard.root,
+            4096,
+            1024,
+        ).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Ranges that do not touch the corrupted block still verify
+        let range = read_verified_range(
+            Cursor::new(&corrupted),
+            Cursor::new(&outboard.data),
+            &outboard.root,
+            0,
+            4096,
+        ).unwrap();
+        assert_eq!(range, &data[..4096]);
+    }
+
+    #[test]
+    fn test_wrong_root_rejected() {
+        let data = sample_data();
+        let outboard = Outboard::build(&data[..]).unwrap();
+        let wrong_root = ContentAddress::from_data_with_algorithm(b"other", HashAlgorithm::Blake3);
+
+        assert!(read_verified_range(Cursor::new(&data), Cursor::new(&outboard.data), &wrong_root, 0, 10).is_err());
+    }
+}
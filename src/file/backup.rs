@@ -0,0 +1,263 @@
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use redb::{Database, ReadableTable, TableDefinition};
+use uuid::Uuid;
+use crate::content::ContentAddress;
+
+/// Backup ID as an integer -> JSON encoded `BackupRecord`
+const BACKUPS: TableDefinition<u128, &[u8]> = TableDefinition::new("backups");
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// One run of `backup`: a tree snapshot of a directory recorded under a
+/// backup name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupRecord {
+    pub id: Uuid, // Time-ordered, so backups taken within a second still sort
+    pub name: String,
+    pub source: PathBuf, // Directory that was backed up
+    pub created_at: u64, // Unix timestamp
+    pub root: ContentAddress, // Root tree of the directory
+    pub stats: ContentAddress, // Stat cache object used to skip unchanged files next time
+    pub files: usize,
+    pub directories: usize,
+    pub total_size: u64,
+    pub reused: usize, // Files taken from the previous backup without being read
+}
+
+impl BackupRecord {
+    /// Abbreviated ID shown to users. IDs start with their timestamp, so
+    /// this takes the random tail instead.
+    pub fn short_id(&self) -> String {
+        let id = self.id.simple().to_string();
+        id[id.len() - 8..].to_string()
+    }
+}
+
+/// Rules deciding which backups of a name survive a `forget`. A backup is
+/// kept if any rule keeps it; the newest backup is always kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackupPolicy {
+    /// Keep the N most recent backups
+    pub keep_last: Option<usize>,
+    /// Keep the newest backup of each of the last N days that have one
+    pub keep_daily: Option<usize>,
+    /// Keep the newest backup of each of the last N weeks (Monday to Sunday,
+    /// UTC) that have one
+    pub keep_weekly: Option<usize>,
+}
+
+impl BackupPolicy {
+    /// Whether the policy has at least one rule
+    pub fn is_empty(&self) -> bool {
+        self.keep_last.is_none() && self.keep_daily.is_none() && self.keep_weekly.is_none()
+    }
+
+    /// Select the backups to forget. `backups` must be sorted oldest first.
+    pub fn backups_to_forget(&self, backups: &[&BackupRecord]) -> Vec<Uuid> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        let mut daily = Bucketed::new(self.keep_daily);
+        let mut weekly = Bucketed::new(self.keep_weekly);
+        let mut forget = Vec::new();
+        for (position, backup) in backups.iter().rev().enumerate() {
+            let day = backup.created_at / DAY_SECS;
+            // Day 0 was a Thursday, so shifting by three starts weeks on Monday
+            let week = (day + 3) / 7;
+
+            let recent = self.keep_last.is_some_and(|n| position < n);
+            // Evaluate every rule so each one sees this backup
+            let kept_daily = daily.keep(day);
+            let kept_weekly = weekly.keep(week);
+            if position != 0 && !recent && !kept_daily && !kept_weekly {
+                forget.push(backup.id);
+            }
+        }
+        forget
+    }
+}
+
+/// Keeps the first backup seen in each of the first `limit` distinct buckets
+struct Bucketed {
+    remaining: usize,
+    last: Option<u64>,
+}
+
+impl Bucketed {
+    fn new(limit: Option<usize>) -> Self {
+        Self { remaining: limit.unwrap_or(0), last: None }
+    }
+
+    fn keep(&mut self, bucket: u64) -> bool {
+        if self.remaining == 0 || self.last == Some(bucket) {
+            return false;
+        }
+        self.last = Some(bucket);
+        self.remaining -= 1;
+        true
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Database error: {0}")]
+    Database(Box<redb::Error>),
+}
+
+impl_from_redb_error!(BackupError);
+
+pub type BackupResult<T> = Result<T, BackupError>;
+
+/// Index of directory backups.
+///
+/// Trees and stat caches live in the content store; this small redb
+/// database next to the file registry records which backups exist.
+#[derive(Debug)]
+pub struct BackupStore {
+    database_path: PathBuf,
+}
+
+impl BackupStore {
+    /// Open or create the backup index in `storage_dir`
+    pub fn new<P: AsRef<Path>>(storage_dir: P) -> BackupResult<Self> {
+        std::fs::create_dir_all(storage_dir.as_ref())?;
+        let store = Self {
+            database_path: storage_dir.as_ref().join("backups.redb"),
+        };
+
+        // Make sure the table exists so read transactions can open it
+        let database = store.open()?;
+        let txn = database.begin_write()?;
+        txn.open_table(BACKUPS)?;
+        txn.commit()?;
+        Ok(store)
+    }
+
+    fn open(&self) -> BackupResult<Database> {
+        Ok(Database::create(&self.database_path)?)
+    }
+
+    pub fn add(&self, record: &BackupRecord) -> BackupResult<()> {
+        let database = self.open()?;
+        let txn = database.begin_write()?;
+        txn.open_table(BACKUPS)?.insert(record.id.as_u128(), serde_json::to_vec(record)?.as_slice())?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Backups, optionally only those of one name, oldest first
+    pub fn list(&self, name: Option<&str>) -> BackupResult<Vec<BackupRecord>> {
+        let database = self.open()?;
+        let txn = database.begin_read()?;
+        let table = txn.open_table(BACKUPS)?;
+
+        let mut records = Vec::new();
+        for row in table.iter()? {
+            let (_, bytes) = row?;
+            let record: BackupRecord = serde_json::from_slice(bytes.value())?;
+            if name.is_none_or(|name| record.name == name) {
+                records.push(record);
+            }
+        }
+        records.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        Ok(records)
+    }
+
+    /// Most recent backup of a name
+    pub fn latest(&self, name: &str) -> BackupResult<Option<BackupRecord>> {
+        Ok(self.list(Some(name))?.pop())
+    }
+
+    /// Drop backups from the index in one transaction, returning those
+    /// that existed
+    pub fn remove(&self, ids: &[Uuid]) -> BackupResult<Vec<BackupRecord>> {
+        let database = self.open()?;
+        let txn = database.begin_write()?;
+        let mut removed = Vec::new();
+        {
+            let mut table = txn.open_table(BACKUPS)?;
+            for id in ids {
+                if let Some(bytes) = table.remove(id.as_u128())? {
+                    removed.push(serde_json::from_slice(bytes.value())?);
+                }
+            }
+        }
+        txn.commit()?;
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn backup(name: &str, created_at: u64) -> BackupRecord {
+        BackupRecord {
+            id: Uuid::now_v7(),
+            name: name.to_string(),
+            source: PathBuf::from("/data"),
+            created_at,
+            root: ContentAddress::from_data(b"root"),
+            stats: ContentAddress::from_data(b"stats"),
+            files: 1,
+            directories: 0,
+            total_size: 1,
+            reused: 0,
+        }
+    }
+
+    #[test]
+    fn test_daily_and_weekly_retention() {
+        // Two backups a day for 21 days, starting on a Monday (1970-01-05)
+        let start = 4 * DAY_SECS;
+        let backups: Vec<BackupRecord> = (0..42)
+            .map(|i| backup("home", start + i / 2 * DAY_SECS + i % 2 * 3600))
+            .collect();
+        let refs: Vec<&BackupRecord> = backups.iter().collect();
+        let kept = |policy: &BackupPolicy| {
+            let forget = policy.backups_to_forget(&refs);
+            backups.iter().filter(|b| !forget.contains(&b.id)).map(|b| b.created_at).collect::<Vec<_>>()
+        };
+
+        let last_of_day = |day: u64| start + day * DAY_SECS + 3600;
+        let daily = BackupPolicy { keep_daily: Some(3), ..BackupPolicy::default() };
+        assert_eq!(kept(&daily), vec![last_of_day(18), last_of_day(19), last_of_day(20)]);
+
+        // Weeks end on Sundays: days 6 and 13 close the first two weeks
+        let weekly = BackupPolicy { keep_daily: Some(2), keep_weekly: Some(3), ..BackupPolicy::default() };
+        assert_eq!(kept(&weekly), vec![last_of_day(6), last_of_day(13), last_of_day(19), last_of_day(20)]);
+
+        let last = BackupPolicy { keep_last: Some(1), ..BackupPolicy::default() };
+        assert_eq!(kept(&last), vec![last_of_day(20)]);
+        assert!(BackupPolicy::default().backups_to_forget(&refs).is_empty());
+    }
+
+    #[test]
+    fn test_store_lists_by_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = BackupStore::new(temp_dir.path()).unwrap();
+        let old = backup("home", 10);
+        let new = backup("home", 20);
+        let other = backup("work", 15);
+        for record in [&new, &old, &other] {
+            store.add(record).unwrap();
+        }
+
+        assert_eq!(store.list(Some("home")).unwrap(), vec![old.clone(), new.clone()]);
+        assert_eq!(store.latest("home").unwrap(), Some(new.clone()));
+        assert_eq!(store.list(None).unwrap().len(), 3);
+
+        let store = BackupStore::new(temp_dir.path()).unwrap();
+        assert_eq!(store.remove(&[old.id, Uuid::new_v4()]).unwrap(), vec![old]);
+        assert_eq!(store.list(None).unwrap(), vec![other, new]);
+    }
+}
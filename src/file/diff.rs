@@ -0,0 +1,205 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::content::ContentAddress;
+
+/// A half-open byte range `[start, end)` within a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Totals for one class of chunks in a diff; each distinct chunk counts once
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChunkTally {
+    pub chunks: usize,
+    pub bytes: u64,
+}
+
+/// Chunk-level comparison of an old and a new file.
+///
+/// Tallies count distinct chunks, which is what a transfer costs; ranges
+/// come from aligning the two chunk sequences in order, so moved or
+/// repeated chunks show up as changed even when no new content is needed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkDiff {
+    /// Chunks present in both files
+    pub shared: ChunkTally,
+    /// Chunks only in the old file
+    pub removed: ChunkTally,
+    /// Chunks only in the new file
+    pub added: ChunkTally,
+    /// Parts of the old file that do not line up with the new file
+    pub removed_ranges: Vec<ByteRange>,
+    /// Parts of the new file that do not line up with the old file
+    pub changed_ranges: Vec<ByteRange>,
+}
+
+impl ChunkDiff {
+    /// Compare two chunk lists given as `(address, size)` pairs in file order
+    pub fn compute(old: &[(ContentAddress, u64)], new: &[(ContentAddress, u64)]) -> Self {
+        let old_set: HashSet<&ContentAddress> = old.iter().map(|(address, _)| address).collect();
+        let new_set: HashSet<&ContentAddress> = new.iter().map(|(address, _)| address).collect();
+
+        let mut diff = ChunkDiff::default();
+        let mut seen = HashSet::new();
+        for (address, size) in old.iter().chain(new) {
+            if !seen.insert(address) {
+                continue;
+            }
+            let tally = match (old_set.contains(address), new_set.contains(address)) {
+                (true, true) => &mut diff.shared,
+                (true, false) => &mut diff.removed,
+                _ => &mut diff.added,
+            };
+            tally.chunks += 1;
+            tally.bytes += size;
+        }
+
+        let (old_aligned, new_aligned) = align(old, new);
+        diff.removed_ranges = unaligned_ranges(old, &old_aligned);
+        diff.changed_ranges = unaligned_ranges(new, &new_aligned);
+        diff
+    }
+
+    /// Bytes a holder of the old file must fetch to rebuild the new one:
+    /// every distinct chunk it does not already have
+    pub fn transfer_bytes(&self) -> u64 {
+        self.added.bytes
+    }
+
+    /// Whether both files are the same chunks in the same order
+    pub fn is_identical(&self) -> bool {
+        self.removed_ranges.is_empty() && self.changed_ranges.is_empty()
+    }
+}
+
+/// Longest common subsequence of two chunk lists, returned as flags
+/// marking which entries of `old` and `new` belong to it.
+///
+/// Reduces to a longest increasing subsequence over the old positions of
+/// each new chunk (Hunt–Szymanski), which stays cheap for files that
+/// share most of their chunks.
+fn align(old: &[(ContentAddress, u64)], new: &[(ContentAddress, u64)]) -> (Vec<bool>, Vec<bool>) {
+    let mut positions: HashMap<&(ContentAddress, u64), Vec<usize>> = HashMap::new();
+    for (index, chunk) in old.iter().enumerate() {
+        positions.entry(chunk).or_default().push(index);
+    }
+
+    // Matches as (old index, new index, previous match in the chain)
+    let mut matches: Vec<(usize, usize, Option<usize>)> = Vec::new();
+    // Last match of the best chain of each length, ordered by old index
+    let mut tails: Vec<usize> = Vec::new();
+    for (new_index, chunk) in new.iter().enumerate() {
+        let Some(old_indexes) = positions.get(chunk) else { continue };
+        // Descending, so one new chunk never extends a chain through itself
+        for &old_index in old_indexes.iter().rev() {
+            let length = tails.partition_point(|&tail| matches[tail].0 < old_index);
+            let previous = length.checked_sub(1).map(|length| tails[length]);
+            matches.push((old_index, new_index, previous));
+            if length == tails.len() {
+                tails.push(matches.len() - 1);
+            } else {
+                tails[length] = matches.len() - 1;
+            }
+        }
+    }
+
+    let mut old_aligned = vec![false; old.len()];
+    let mut new_aligned = vec![false; new.len()];
+    let mut next = tails.last().copied();
+    while let Some(current) = next {
+        let (old_index, new_index, previous) = matches[current];
+        old_aligned[old_index] = true;
+        new_aligned[new_index] = true;
+        next = previous;
+    }
+    (old_aligned, new_aligned)
+}
+
+/// Byte ranges of `chunks` not flagged in `aligned`, with adjacent ranges merged
+fn unaligned_ranges(chunks: &[(ContentAddress, u64)], aligned: &[bool]) -> Vec<ByteRange> {
+    let mut ranges: Vec<ByteRange> = Vec::new();
+    let mut offset = 0;
+    for ((_, size), &aligned) in chunks.iter().zip(aligned) {
+        let end = offset + size;
+        if !aligned {
+            match ranges.last_mut() {
+                Some(last) if last.end == offset => last.end = end,
+                _ => ranges.push(ByteRange { start: offset, end }),
+            }
+        }
+        offset = end;
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(label: &str, size: u64) -> (ContentAddress, u64) {
+        (ContentAddress::from_data(label.as_bytes()), size)
+    }
+
+    #[test]
+    fn test_diff_reports_tallies_and_ranges() {
+        let old = vec![chunk("a", 100), chunk("b", 50), chunk("c", 70), chunk("d", 30)];
+        let new = vec![chunk("a", 100), chunk("x", 40), chunk("y", 60), chunk("c", 70), chunk("x", 40)];
+
+        let diff = ChunkDiff::compute(&old, &new);
+        assert_eq!(diff.shared, ChunkTally { chunks: 2, bytes: 170 });
+        assert_eq!(diff.removed, ChunkTally { chunks: 2, bytes: 80 });
+        // The repeated chunk only needs to be transferred once
+        assert_eq!(diff.added, ChunkTally { chunks: 2, bytes: 100 });
+        assert_eq!(diff.transfer_bytes(), 100);
+
+        assert_eq!(diff.changed_ranges, vec![ByteRange { start: 100, end: 200 }, ByteRange { start: 270, end: 310 }]);
+        assert_eq!(diff.removed_ranges, vec![ByteRange { start: 100, end: 150 }, ByteRange { start: 220, end: 250 }]);
+        assert!(!diff.is_identical());
+    }
+
+    #[test]
+    fn test_identical_files() {
+        let chunks = vec![chunk("a", 10), chunk("b", 20)];
+        let diff = ChunkDiff::compute(&chunks, &chunks);
+        assert!(diff.is_identical());
+        assert!(diff.changed_ranges.is_empty());
+        assert_eq!(diff.transfer_bytes(), 0);
+    }
+
+    #[test]
+    fn test_reordered_and_repeated_chunks_are_changes() {
+        // Same chunk set, different layout: nothing to transfer, but not identical
+        let old = vec![chunk("a", 10), chunk("b", 20), chunk("a", 10)];
+        let new = vec![chunk("b", 20), chunk("a", 10)];
+        let diff = ChunkDiff::compute(&old, &new);
+        assert!(!diff.is_identical());
+        assert_eq!(diff.transfer_bytes(), 0);
+        assert_eq!(diff.removed_ranges, vec![ByteRange { start: 0, end: 10 }]);
+        assert!(diff.changed_ranges.is_empty());
+
+        // A chunk moved to the front is reported where it now sits
+        let old = vec![chunk("a", 10), chunk("b", 20), chunk("c", 30)];
+        let new = vec![chunk("c", 30), chunk("a", 10), chunk("b", 20)];
+        let diff = ChunkDiff::compute(&old, &new);
+        assert_eq!(diff.changed_ranges, vec![ByteRange { start: 0, end: 30 }]);
+        assert_eq!(diff.removed_ranges, vec![ByteRange { start: 30, end: 60 }]);
+
+        // Duplicating a region shows the extra copy
+        let new = vec![chunk("a", 10), chunk("b", 20), chunk("b", 20), chunk("c", 30)];
+        let diff = ChunkDiff::compute(&old, &new);
+        assert_eq!(diff.changed_ranges.iter().map(ByteRange::len).sum::<u64>(), 20);
+        assert!(diff.removed_ranges.is_empty());
+    }
+}
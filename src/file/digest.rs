@@ -0,0 +1,26 @@
+use std::io::{self, Read};
+
+/// BLAKE3 hash of everything `reader` yields, hex encoded
+pub fn digest_reader<R: Read>(mut reader: R) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_streamed_digest_matches_in_memory_hash() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("data.bin");
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 253) as u8).collect();
+        std::fs::write(&path, &data).unwrap();
+
+        assert_eq!(digest_reader(File::open(&path).unwrap()).unwrap(), blake3::hash(&data).to_hex().to_string());
+        assert_eq!(digest_reader(&b""[..]).unwrap(), blake3::hash(b"").to_hex().to_string());
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use super::registry::FileMetadata;
+
+/// Formats a registry can be exported in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One JSON-encoded entry per line; lossless and accepted by import
+    #[default]
+    JsonLines,
+    /// One row per entry with the commonly used fields, for spreadsheets
+    /// and scripts; export only
+    Csv,
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::JsonLines => write!(f, "jsonl"),
+            ExportFormat::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "jsonl" | "json-lines" | "ndjson" => Ok(ExportFormat::JsonLines),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(format!("Unknown export format '{}' (expected jsonl or csv)", s)),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Line {line}: {source}")]
+    InvalidLine { line: usize, source: serde_json::Error },
+}
+
+pub type ExportResult<T> = Result<T, ExportError>;
+
+/// Column headings of the CSV export, in order
+pub const CSV_COLUMNS: &[&str] = &[
+    "id", "path", "version", "parent", "original_name", "total_size", "chunk_count", "created_at",
+    "mime_type", "digest", "tags", "pin", "expires_at", "tree_root", "chunk_addresses",
+];
+
+/// Write `entries` in the given format
+pub fn write_entries<W: Write>(entries: &[&FileMetadata], format: ExportFormat, mut writer: W) -> ExportResult<()> {
+    match format {
+        ExportFormat::JsonLines => {
+            for metadata in entries {
+                serde_json::to_writer(&mut writer, metadata)?;
+                writer.write_all(b"\n")?;
+            }
+        }
+        ExportFormat::Csv => {
+            writeln!(writer, "{}", CSV_COLUMNS.join(","))?;
+            for metadata in entries {
+                writeln!(writer, "{}", csv_row(metadata).join(","))?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read entries written as JSON lines, skipping blank lines
+pub fn read_entries<R: BufRead>(reader: R) -> ExportResult<Vec<FileMetadata>> {
+    let mut entries = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let metadata = serde_json::from_str(&line)
+            .map_err(|source| ExportError::InvalidLine { line: index + 1, source })?;
+        entries.push(metadata);
+    }
+    Ok(entries)
+}
+
+fn csv_row(metadata: &FileMetadata) -> Vec<String> {
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    let fields = [
+        metadata.id.to_string(),
+        metadata.path.clone(),
+        metadata.version.to_string(),
+        optional(metadata.parent.map(|parent| parent.to_string())),
+        metadata.original_name.clone(),
+        metadata.total_size.to_string(),
+        metadata.chunk_count.to_string(),
+        metadata.created_at.to_string(),
+        metadata.content_type().to_string(),
+        optional(metadata.digest.clone()),
+        metadata.tags.iter().cloned().collect::<Vec<_>>().join(";"),
+        optional(metadata.pin.map(|pin| pin.to_string())),
+        optional(metadata.expires_at.map(|expires_at| expires_at.to_string())),
+        optional(metadata.tree_root.as_ref().map(|root| root.to_string())),
+        metadata.chunk_addresses.iter().map(|address| address.to_string()).collect::<Vec<_>>().join(" "),
+    ];
+    fields.iter().map(|field| csv_field(field)).collect()
+}
+
+/// Quote a field if it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::ContentAddress;
+
+    #[test]
+    fn test_json_lines_round_trip() {
+        let mut metadata = FileMetadata::new("a.txt".to_string(), vec![ContentAddress::from_data(b"a")], 1);
+        metadata.tags.insert("keep".to_string());
+        let other = FileMetadata::new("b.txt".to_string(), vec![], 0);
+
+        let mut buffer = Vec::new();
+        write_entries(&[&metadata, &other], ExportFormat::JsonLines, &mut buffer).unwrap();
+        buffer.extend_from_slice(b"\n");
+        assert_eq!(read_entries(&buffer[..]).unwrap(), vec![metadata, other]);
+
+        let error = read_entries(&b"\nnot json\n"[..]).unwrap_err();
+        assert!(matches!(error, ExportError::InvalidLine { line: 2, .. }));
+    }
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        let mut metadata = FileMetadata::new("report, final \"v2\".txt".to_string(), vec![], 0);
+        metadata.tags.extend(["a".to_string(), "b".to_string()]);
+
+        let mut buffer = Vec::new();
+        write_entries(&[&metadata], ExportFormat::Csv, &mut buffer).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        let mut lines = output.lines();
+        assert_eq!(lines.next().unwrap(), CSV_COLUMNS.join(","));
+        let row = lines.next().unwrap();
+        assert!(row.contains(",\"report, final \"\"v2\"\".txt\",1,,"));
+        assert!(row.contains(",a;b,"));
+        assert!(lines.next().is_none());
+    }
+}
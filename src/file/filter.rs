@@ -0,0 +1,101 @@
+use super::mime;
+use super::registry::FileMetadata;
+
+/// Criteria for selecting registered files; every set criterion must match
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileFilter {
+    /// Substring of the original name or logical path
+    pub name: Option<String>,
+    /// Tags the file must carry
+    pub tags: Vec<String>,
+    /// Attributes the file must have with exactly these values
+    pub attributes: Vec<(String, String)>,
+    /// MIME type, `type/*` or bare top-level type such as `image`
+    pub mime: Option<String>,
+}
+
+impl FileFilter {
+    /// Whether the filter has no criteria and so matches every file
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.tags.is_empty() && self.attributes.is_empty() && self.mime.is_none()
+    }
+
+    /// Check a file against the filter
+    pub fn matches(&self, metadata: &FileMetadata) -> bool {
+        let name_matches = self.name.as_ref().is_none_or(|pattern| {
+            metadata.original_name.contains(pattern.as_str()) || metadata.path.contains(pattern.as_str())
+        });
+        let tags_match = self.tags.iter().all(|tag| metadata.tags.contains(tag));
+        let attributes_match = self
+            .attributes
+            .iter()
+            .all(|(key, value)| metadata.attributes.get(key) == Some(value));
+        let mime_matches = self.mime.as_ref().is_none_or(|pattern| mime::matches(pattern, metadata.content_type()));
+
+        name_matches && tags_match && attributes_match && mime_matches
+    }
+}
+
+/// Validate a tag: non-empty and free of whitespace and commas
+pub fn validate_tag(tag: &str) -> Result<String, String> {
+    if tag.is_empty() || tag.chars().any(|c| c.is_whitespace() || c == ',') {
+        return Err(format!("Invalid tag '{}': tags must be non-empty without spaces or commas", tag));
+    }
+    Ok(tag.to_string())
+}
+
+/// Parse a `key=value` attribute assignment
+pub fn parse_attribute(input: &str) -> Result<(String, String), String> {
+    match input.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.to_string())),
+        _ => Err(format!("Invalid attribute '{}': expected key=value", input)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> FileMetadata {
+        let mut metadata = FileMetadata::new("build.log".to_string(), vec![], 10);
+        metadata.tags.insert("prod".to_string());
+        metadata.attributes.insert("project".to_string(), "nebula".to_string());
+        metadata
+    }
+
+    #[test]
+    fn test_filter_matching() {
+        let metadata = sample();
+
+        assert!(FileFilter::default().matches(&metadata));
+        assert!(FileFilter { name: Some("build".to_string()), ..Default::default() }.matches(&metadata));
+        assert!(FileFilter { tags: vec!["prod".to_string()], ..Default::default() }.matches(&metadata));
+        assert!(!FileFilter { tags: vec!["prod".to_string(), "dev".to_string()], ..Default::default() }.matches(&metadata));
+
+        let attributes = vec![("project".to_string(), "nebula".to_string())];
+        assert!(FileFilter { attributes, ..Default::default() }.matches(&metadata));
+        let attributes = vec![("project".to_string(), "other".to_string())];
+        assert!(!FileFilter { attributes, ..Default::default() }.matches(&metadata));
+
+        // Entries without a detected type fall back to their extension
+        assert!(FileFilter { mime: Some("text/*".to_string()), ..Default::default() }.matches(&metadata));
+        assert!(!FileFilter { mime: Some("image".to_string()), ..Default::default() }.matches(&metadata));
+    }
+
+    #[test]
+    fn test_parse_attribute() {
+        assert_eq!(parse_attribute("owner=ops").unwrap(), ("owner".to_string(), "ops".to_string()));
+        assert_eq!(parse_attribute("note=a=b").unwrap(), ("note".to_string(), "a=b".to_string()));
+        assert_eq!(parse_attribute("empty=").unwrap(), ("empty".to_string(), String::new()));
+        assert!(parse_attribute("novalue").is_err());
+        assert!(parse_attribute("=value").is_err());
+    }
+
+    #[test]
+    fn test_validate_tag() {
+        assert!(validate_tag("prod").is_ok());
+        assert!(validate_tag("").is_err());
+        assert!(validate_tag("two words").is_err());
+        assert!(validate_tag("a,b").is_err());
+    }
+}
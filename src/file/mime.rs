@@ -0,0 +1,171 @@
+/// Type reported when nothing more specific is known
+pub const OCTET_STREAM: &str = "application/octet-stream";
+/// Type recorded for directories stored with `put -r`
+pub const DIRECTORY: &str = "inode/directory";
+/// Type recorded for symlinks stored without following them
+pub const SYMLINK: &str = "inode/symlink";
+
+/// How many leading bytes `sniff` looks at; enough for every signature and
+/// for a reasonable text check
+pub const SNIFF_LEN: usize = 8192;
+
+/// Magic byte signatures: offset, bytes and the type they identify
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (0, b"BM", "image/bmp"),
+    (0, b"II*\0", "image/tiff"),
+    (0, b"MM\0*", "image/tiff"),
+    (0, b"\0\0\x01\0", "image/x-icon"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"\x1f\x8b", "application/gzip"),
+    (0, b"BZh", "application/x-bzip2"),
+    (0, b"\xfd7zXZ\0", "application/x-xz"),
+    (0, b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (0, b"Rar!\x1a\x07", "application/vnd.rar"),
+    (257, b"ustar", "application/x-tar"),
+    (0, b"\x7fELF", "application/x-executable"),
+    (0, b"\0asm", "application/wasm"),
+    (0, b"SQLite format 3\0", "application/vnd.sqlite3"),
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"fLaC", "audio/flac"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"\x1a\x45\xdf\xa3", "video/webm"),
+    (0, b"%!PS", "application/postscript"),
+];
+
+/// Detect a MIME type from the first bytes of a file, falling back to the
+/// file name's extension and then to a check for plain text
+pub fn sniff(head: &[u8], name: &str) -> String {
+    detect_magic(head)
+        .or_else(|| from_extension(name))
+        .or_else(|| looks_like_text(head).then_some("text/plain"))
+        .unwrap_or(OCTET_STREAM)
+        .to_string()
+}
+
+/// Type implied by magic bytes alone
+pub fn detect_magic(head: &[u8]) -> Option<&'static str> {
+    // RIFF and ISO media containers carry their format further in
+    if head.len() >= 12 && &head[..4] == b"RIFF" {
+        return match &head[8..12] {
+            b"WEBP" => Some("image/webp"),
+            b"WAVE" => Some("audio/wav"),
+            b"AVI " => Some("video/x-msvideo"),
+            _ => None,
+        };
+    }
+    if head.len() >= 12 && &head[4..8] == b"ftyp" {
+        return match &head[8..12] {
+            b"heic" | b"heix" | b"mif1" => Some("image/heic"),
+            b"avif" => Some("image/avif"),
+            b"qt  " => Some("video/quicktime"),
+            b"M4A " => Some("audio/mp4"),
+            _ => Some("video/mp4"),
+        };
+    }
+
+    SIGNATURES
+        .iter()
+        .find(|(offset, magic, _)| head.get(*offset..offset + magic.len()) == Some(*magic))
+        .map(|(_, _, mime)| *mime)
+}
+
+/// Type implied by the extension of `name`
+pub fn from_extension(name: &str) -> Option<&'static str> {
+    let (_, extension) = name.rsplit_once('.')?;
+    let mime = match extension.to_ascii_lowercase().as_str() {
+        "txt" | "log" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "csv" => "text/csv",
+        "tsv" => "text/tab-separated-values",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "yaml" | "yml" => "application/yaml",
+        "toml" => "application/toml",
+        "rs" => "text/x-rust",
+        "py" => "text/x-python",
+        "sh" => "application/x-sh",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" | "tgz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "wasm" => "application/wasm",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// Whether `pattern` selects `mime`: an exact type, `type/*` or a bare
+/// top-level type such as `image`, compared case-insensitively
+pub fn matches(pattern: &str, mime: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    let mime = mime.to_ascii_lowercase();
+    match pattern.strip_suffix("/*") {
+        Some(top_level) => mime.split('/').next() == Some(top_level),
+        None if !pattern.contains('/') => mime.split('/').next() == Some(pattern.as_str()),
+        None => mime == pattern,
+    }
+}
+
+/// Valid UTF-8 (allowing a character cut off at the end) without NUL bytes
+fn looks_like_text(head: &[u8]) -> bool {
+    if head.is_empty() || head.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none() && head.len() - e.valid_up_to() < 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_magic_takes_precedence_over_extension() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n....", "photo.jpg"), "image/png");
+        assert_eq!(sniff(b"%PDF-1.7\n", "report"), "application/pdf");
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 ", "x"), "image/webp");
+        assert_eq!(sniff(b"\0\0\0\x18ftypmp42", "clip"), "video/mp4");
+
+        let mut tar = vec![0u8; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(sniff(&tar, "archive"), "application/x-tar");
+    }
+
+    #[test]
+    fn test_fallbacks() {
+        assert_eq!(sniff(b"{\"a\": 1}", "data.json"), "application/json");
+        assert_eq!(sniff("héllo wörld".as_bytes(), "notes"), "text/plain");
+        assert_eq!(sniff(b"\0\x01\x02\x03", "blob"), OCTET_STREAM);
+        assert_eq!(sniff(b"", "empty"), OCTET_STREAM);
+        // A multi-byte character cut off by the sniff window is still text
+        assert_eq!(sniff(&"ü".as_bytes()[..1], "cut"), "text/plain");
+    }
+
+    #[test]
+    fn test_pattern_matching() {
+        assert!(matches("image/*", "image/png"));
+        assert!(matches("image", "image/png"));
+        assert!(matches("Image/PNG", "image/png"));
+        assert!(!matches("image/*", "application/pdf"));
+        assert!(!matches("text/plain", "text/csv"));
+    }
+}
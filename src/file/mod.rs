@@ -1,3 +1,50 @@
+// redb reports failures through several error types; fold them all into the
+// `Database` variant of the given error enum
+macro_rules! impl_from_redb_error {
+    ($target:ident) => {
+        impl_from_redb_error!($target:
+            redb::Error,
+            redb::DatabaseError,
+            redb::TransactionError,
+            redb::TableError,
+            redb::StorageError,
+            redb::CommitError
+        );
+    };
+    ($target:ident: $($error:ty),*) => {
+        $(impl From<$error> for $target {
+            fn from(e: $error) -> Self {
+                $target::Database(Box::new(e.into()))
+            }
+        })*
+    };
+}
+
+pub mod atomic;
+pub mod backup;
+pub mod diff;
+pub mod digest;
+pub mod export;
+pub mod filter;
+pub mod mime;
+pub mod posix;
+pub mod query;
+pub mod refs;
 pub mod registry;
+pub mod snapshot;
+pub mod trash;
+pub mod tree;
+pub mod units;
+pub mod version;
 
-pub use registry::{FileRegistry, FileMetadata, FileId};
+pub use registry::{FileRegistry, FileRegistryError, FileMetadata, FileId, ImportReport, PinMode, RegistryContents, RegistryRecovery, Rename};
+pub use export::{ExportError, ExportFormat};
+pub use refs::{RefStore, RefError, RefTarget, RefCondition, RefEntry, RefUpdate};
+pub use backup::{BackupError, BackupPolicy, BackupRecord, BackupStore};
+pub use snapshot::{Snapshot, SnapshotError, SnapshotRecord, SnapshotStore};
+pub use trash::TrashEntry;
+pub use tree::{Tree, TreeEntry, EntryKind, FileManifest, FileStat, StatCache, TreeSummary};
+pub use version::RetentionPolicy;
+pub use filter::FileFilter;
+pub use posix::{PosixMetadata, RestoreOptions};
+pub use query::{Query, QueryOptions, SortKey};
@@ -0,0 +1,282 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use filetime::FileTime;
+
+/// Filesystem metadata captured on put so a restore can reproduce the
+/// original file, not just its contents
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PosixMetadata {
+    pub mode: u32, // Permission bits including setuid/setgid/sticky
+    pub mtime: i64,
+    #[serde(default)]
+    pub mtime_nsec: u32,
+    pub atime: i64,
+    #[serde(default)]
+    pub atime_nsec: u32,
+    #[serde(default)]
+    pub uid: Option<u32>,
+    #[serde(default)]
+    pub gid: Option<u32>,
+    #[serde(default)]
+    pub symlink_target: Option<String>, // Set when the entry is a symlink rather than a file
+    #[serde(default)]
+    pub xattrs: BTreeMap<String, String>, // Extended attribute values, hex encoded
+}
+
+/// Which parts of the captured metadata to apply when restoring
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RestoreOptions {
+    pub permissions: bool,
+    pub times: bool,
+    pub ownership: bool,
+    pub xattrs: bool,
+}
+
+impl RestoreOptions {
+    /// Restore everything that was captured
+    pub fn all() -> Self {
+        Self {
+            permissions: true,
+            times: true,
+            ownership: true,
+            xattrs: true,
+        }
+    }
+}
+
+impl PosixMetadata {
+    /// Capture the metadata of `path` without following a final symlink.
+    /// A symlink whose target is not valid UTF-8 is an `InvalidData` error,
+    /// since a lossy copy would restore a link to somewhere else.
+    pub fn capture<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let metadata = fs::symlink_metadata(path)?;
+
+        let symlink_target = if metadata.file_type().is_symlink() {
+            let target = fs::read_link(path)?.into_os_string().into_string().map_err(|_| io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("symlink target of {} is not valid UTF-8", path.display()),
+            ))?;
+            Some(target)
+        } else {
+            None
+        };
+
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        let atime = FileTime::from_last_access_time(&metadata);
+        let (mode, uid, gid) = ownership(&metadata);
+
+        Ok(Self {
+            mode,
+            mtime: mtime.unix_seconds(),
+            mtime_nsec: mtime.nanoseconds(),
+            atime: atime.unix_seconds(),
+            atime_nsec: atime.nanoseconds(),
+            uid,
+            gid,
+            symlink_target,
+            xattrs: read_xattrs(path),
+        })
+    }
+
+    /// Whether the captured entry was a symlink
+    pub fn is_symlink(&self) -> bool {
+        self.symlink_target.is_some()
+    }
+
+    /// Apply the selected metadata to an already restored `path`.
+    ///
+    /// Ownership and extended attributes often cannot be set by unprivileged
+    /// users; such failures are returned as warnings instead of errors.
+    pub fn apply<P: AsRef<Path>>(&self, path: P, options: RestoreOptions) -> io::Result<Vec<String>> {
+        let path = path.as_ref();
+        let mut warnings = Vec::new();
+
+        if options.xattrs {
+            for (name, value) in &self.xattrs {
+                let result = hex::decode(value)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+                    .and_then(|value| set_xattr(path, name, &value));
+                if let Err(e) = result {
+                    warnings.push(format!("Could not restore xattr {} on {}: {}", name, path.display(), e));
+                }
+            }
+        }
+
+        // Changing owner clears setuid/setgid, so it must happen before chmod
+        if options.ownership {
+            if let Err(e) = set_ownership(path, self.uid, self.gid) {
+                warnings.push(format!("Could not restore ownership of {}: {}", path.display(), e));
+            }
+        }
+
+        // Symlink permissions are not meaningful on Unix
+        if options.permissions && !self.is_symlink() {
+            set_mode(path, self.mode)?;
+        }
+
+        if options.times {
+            let atime = FileTime::from_unix_time(self.atime, self.atime_nsec);
+            let mtime = FileTime::from_unix_time(self.mtime, self.mtime_nsec);
+            filetime::set_symlink_file_times(path, atime, mtime)?;
+        }
+
+        Ok(warnings)
+    }
+}
+
+/// Recreate a captured symlink at `path`
+pub fn create_symlink<P: AsRef<Path>>(target: &str, path: P) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(target, path)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (target, path);
+        Err(io::Error::new(io::ErrorKind::Unsupported, "symlinks can only be restored on Unix"))
+    }
+}
+
+#[cfg(unix)]
+fn ownership(metadata: &fs::Metadata) -> (u32, Option<u32>, Option<u32>) {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.mode() & 0o7777, Some(metadata.uid()), Some(metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn ownership(metadata: &fs::Metadata) -> (u32, Option<u32>, Option<u32>) {
+    let mode = if metadata.permissions().readonly() { 0o444 } else { 0o644 };
+    (mode, None, None)
+}
+
+#[cfg(unix)]
+fn read_xattrs(path: &Path) -> BTreeMap<String, String> {
+    // Filesystems without xattr support simply yield no attributes
+    let Ok(names) = xattr::list(path) else {
+        return BTreeMap::new();
+    };
+
+    names
+        .filter_map(|name| {
+            let value = xattr::get(path, &name).ok().flatten()?;
+            Some((name.to_string_lossy().into_owned(), hex::encode(value)))
+        })
+        .collect()
+}
+
+#[cfg(not(unix))]
+fn read_xattrs(_path: &Path) -> BTreeMap<String, String> {
+    BTreeMap::new()
+}
+
+#[cfg(unix)]
+fn set_xattr(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+    xattr::set(path, name, value)
+}
+
+#[cfg(not(unix))]
+fn set_xattr(_path: &Path, _name: &str, _value: &[u8]) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "extended attributes require Unix"))
+}
+
+#[cfg(unix)]
+fn set_ownership(path: &Path, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+    if uid.is_none() && gid.is_none() {
+        return Ok(());
+    }
+    std::os::unix::fs::lchown(path, uid, gid)
+}
+
+#[cfg(not(unix))]
+fn set_ownership(_path: &Path, _uid: Option<u32>, _gid: Option<u32>) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(mode & 0o222 == 0);
+    fs::set_permissions(path, permissions)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_capture_and_apply_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source.sh");
+        fs::write(&source, b"#!/bin/sh\n").unwrap();
+        fs::set_permissions(&source, fs::Permissions::from_mode(0o750)).unwrap();
+        filetime::set_file_times(&source, FileTime::from_unix_time(1_000_000, 5), FileTime::from_unix_time(2_000_000, 7)).unwrap();
+
+        let captured = PosixMetadata::capture(&source).unwrap();
+        assert_eq!(captured.mode, 0o750);
+        assert_eq!((captured.mtime, captured.mtime_nsec), (2_000_000, 7));
+        assert_eq!(captured.uid, Some(fs::metadata(&source).unwrap().uid()));
+        assert!(!captured.is_symlink());
+
+        let restored = temp.path().join("restored.sh");
+        fs::write(&restored, b"#!/bin/sh\n").unwrap();
+        let warnings = captured.apply(&restored, RestoreOptions::all()).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let metadata = fs::metadata(&restored).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o7777, 0o750);
+        assert_eq!(metadata.mtime(), 2_000_000);
+        assert_eq!(metadata.atime(), 1_000_000);
+    }
+
+    #[test]
+    fn test_capture_symlink_without_following() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("target.txt"), b"data").unwrap();
+        let link = temp.path().join("link");
+        create_symlink("target.txt", &link).unwrap();
+
+        let captured = PosixMetadata::capture(&link).unwrap();
+        assert_eq!(captured.symlink_target.as_deref(), Some("target.txt"));
+
+        let restored = temp.path().join("restored-link");
+        create_symlink(captured.symlink_target.as_ref().unwrap(), &restored).unwrap();
+        captured.apply(&restored, RestoreOptions::all()).unwrap();
+        assert_eq!(fs::read_link(&restored).unwrap(), Path::new("target.txt"));
+    }
+
+    #[test]
+    fn test_capture_rejects_non_utf8_symlink_targets() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp = TempDir::new().unwrap();
+        let link = temp.path().join("link");
+        std::os::unix::fs::symlink(std::ffi::OsStr::from_bytes(b"target-\xff"), &link).unwrap();
+
+        let error = PosixMetadata::capture(&link).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_default_options_change_nothing() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("file");
+        fs::write(&path, b"x").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+        let captured = PosixMetadata { mode: 0o777, ..PosixMetadata::default() };
+        captured.apply(&path, RestoreOptions::default()).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o7777, 0o600);
+    }
+}
@@ -0,0 +1,505 @@
+use std::ops::Range;
+use std::str::FromStr;
+
+use super::mime;
+use super::registry::FileMetadata;
+use super::units::{now_secs, parse_date, parse_duration, parse_size};
+
+/// Errors produced while parsing a query
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum QueryError {
+    #[error("Unknown query field '{0}' (use name, path, tag, attr, type, mime, size, created, version or chunks)")]
+    UnknownField(String),
+
+    #[error("Field '{field}' does not support '{operator}'")]
+    UnsupportedOperator { field: String, operator: String },
+
+    #[error("Invalid value for '{field}': {reason}")]
+    InvalidValue { field: String, reason: String },
+
+    #[error("Unterminated quote in query")]
+    UnterminatedQuote,
+
+    #[error("Unknown sort key '{0}' (use name, path, size, created or version)")]
+    UnknownSortKey(String),
+}
+
+/// Comparison operator of a numeric term
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Numeric field a term can compare against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumericField {
+    Size,
+    Created,
+    Version,
+    Chunks,
+}
+
+/// Half-open range `[start, end)` named by a query value; a plain number is a
+/// range of one, a date covers the whole day (or minute) it names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Span {
+    start: u64,
+    end: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Predicate {
+    Name(String),
+    Path(String),
+    Tag(String),
+    Attribute(String, Option<String>),
+    Directory(bool),
+    Mime(String),
+    Compare(NumericField, Operator, Span),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Term {
+    negated: bool,
+    predicate: Predicate,
+}
+
+/// Parsed registry query. Terms are separated by whitespace and must all
+/// match; a leading `-` negates a term.
+///
+/// ```text
+/// name:*.log size>10MB created>2026-01-01 tag:prod -attr:team=infra
+/// ```
+///
+/// Text fields (`name`, `path`) take `*`/`?` globs; a bare word matches
+/// names and paths containing it. `mime` takes a type such as
+/// `image/png`, `image/*` or `image`. `size`, `created`, `version` and
+/// `chunks` compare with `=`, `<`, `<=`, `>` or `>=`. Dates are UTC
+/// `YYYY-MM-DD[THH:MM[:SS]]`, Unix timestamps, or ages such as `7d`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+impl Query {
+    /// Parse a query, resolving relative dates against `now`
+    pub fn parse_at(input: &str, now: u64) -> Result<Self, QueryError> {
+        let terms = tokenize(input)?
+            .iter()
+            .map(|token| parse_term(token, now))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { terms })
+    }
+
+    /// Whether the query has no terms and so matches every file
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Range of creation times every match falls in, if the query bounds
+    /// them, so a creation time index can narrow the candidates
+    pub fn created_range(&self) -> Option<Range<u64>> {
+        let mut range: Option<Range<u64>> = None;
+        for term in self.terms.iter().filter(|term| !term.negated) {
+            let Predicate::Compare(NumericField::Created, operator, span) = &term.predicate else {
+                continue;
+            };
+            let (start, end) = match operator {
+                Operator::Eq => (span.start, span.end),
+                Operator::Lt => (0, span.start),
+                Operator::Le => (0, span.end),
+                Operator::Gt => (span.end, u64::MAX),
+                Operator::Ge => (span.start, u64::MAX),
+            };
+            let range = range.get_or_insert(0..u64::MAX);
+            range.start = range.start.max(start);
+            range.end = range.end.min(end);
+        }
+        range
+    }
+
+    /// Evaluate the query against a file
+    pub fn matches(&self, metadata: &FileMetadata) -> bool {
+        self.terms
+            .iter()
+            .all(|term| term.predicate.matches(metadata) != term.negated)
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_at(s, now_secs())
+    }
+}
+
+impl Predicate {
+    fn matches(&self, metadata: &FileMetadata) -> bool {
+        match self {
+            Predicate::Name(pattern) if has_wildcards(pattern) => {
+                glob_match(pattern, &metadata.original_name)
+                    || glob_match(pattern, base_name(&metadata.path))
+            }
+            Predicate::Name(text) => {
+                metadata.original_name.contains(text.as_str()) || metadata.path.contains(text.as_str())
+            }
+            Predicate::Path(pattern) => glob_match(pattern, &metadata.path),
+            Predicate::Tag(tag) => metadata.tags.contains(tag),
+            Predicate::Attribute(key, value) => match (metadata.attributes.get(key), value) {
+                (Some(actual), Some(expected)) => actual == expected,
+                (Some(_), None) => true,
+                (None, _) => false,
+            },
+            Predicate::Directory(directory) => metadata.is_directory() == *directory,
+            Predicate::Mime(pattern) => mime::matches(pattern, metadata.content_type()),
+            Predicate::Compare(field, operator, span) => {
+                let actual = match field {
+                    NumericField::Size => metadata.total_size,
+                    NumericField::Created => metadata.created_at,
+                    NumericField::Version => metadata.version as u64,
+                    NumericField::Chunks => metadata.chunk_count as u64,
+                };
+                match operator {
+                    Operator::Eq => span.start <= actual && actual < span.end,
+                    Operator::Lt => actual < span.start,
+                    Operator::Le => actual < span.end,
+                    Operator::Gt => actual >= span.end,
+                    Operator::Ge => actual >= span.start,
+                }
+            }
+        }
+    }
+}
+
+/// Split a query on whitespace, keeping double-quoted sections together
+fn tokenize(input: &str) -> Result<Vec<String>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut started = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                started = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if started {
+                    tokens.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            c => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+
+    if in_quotes {
+        return Err(QueryError::UnterminatedQuote);
+    }
+    if started {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+fn parse_term(token: &str, now: u64) -> Result<Term, QueryError> {
+    let (negated, token) = match token.strip_prefix('-') {
+        Some(rest) if !rest.is_empty() => (true, rest),
+        _ => (false, token),
+    };
+
+    let split = token.find([':', '=', '<', '>']);
+    let Some(split) = split.filter(|&i| i > 0) else {
+        return Ok(Term { negated, predicate: Predicate::Name(token.to_string()) });
+    };
+
+    let field = token[..split].to_ascii_lowercase();
+    let rest = &token[split..];
+    let (operator, value) = ["<=", ">=", ":", "=", "<", ">"]
+        .iter()
+        .find_map(|op| rest.strip_prefix(op).map(|value| (*op, value)))
+        .expect("split is at an operator character");
+
+    let invalid = |reason: String| QueryError::InvalidValue { field: field.clone(), reason };
+    let unsupported = || QueryError::UnsupportedOperator {
+        field: field.clone(),
+        operator: operator.to_string(),
+    };
+    if value.is_empty() {
+        return Err(invalid("missing value".to_string()));
+    }
+
+    let predicate = match field.as_str() {
+        "name" | "path" | "tag" | "attr" | "type" | "mime" if operator != ":" => return Err(unsupported()),
+        "name" => Predicate::Name(value.to_string()),
+        "path" => Predicate::Path(value.to_string()),
+        "tag" => Predicate::Tag(value.to_string()),
+        "attr" => match value.split_once('=') {
+            Some((key, expected)) => Predicate::Attribute(key.to_string(), Some(expected.to_string())),
+            None => Predicate::Attribute(value.to_string(), None),
+        },
+        "type" => match value {
+            "file" => Predicate::Directory(false),
+            "dir" | "directory" => Predicate::Directory(true),
+            _ => return Err(invalid(format!("'{}' is not 'file' or 'dir'", value))),
+        },
+        "mime" => Predicate::Mime(value.to_string()),
+        "size" | "created" | "version" | "chunks" => {
+            let operator = match operator {
+                ":" | "=" => Operator::Eq,
+                "<" => Operator::Lt,
+                "<=" => Operator::Le,
+                ">" => Operator::Gt,
+                _ => Operator::Ge,
+            };
+            let (numeric, span) = match field.as_str() {
+                "size" => (NumericField::Size, single(parse_size(value).map_err(invalid)?)),
+                "created" => (NumericField::Created, parse_time(value, now).map_err(invalid)?),
+                "version" => (NumericField::Version, single(parse_count(value).map_err(invalid)?)),
+                _ => (NumericField::Chunks, single(parse_count(value).map_err(invalid)?)),
+            };
+            Predicate::Compare(numeric, operator, span)
+        }
+        _ => return Err(QueryError::UnknownField(field)),
+    };
+
+    Ok(Term { negated, predicate })
+}
+
+fn single(value: u64) -> Span {
+    Span { start: value, end: value.saturating_add(1) }
+}
+
+fn parse_count(value: &str) -> Result<u64, String> {
+    value.parse().map_err(|_| format!("'{}' is not a number", value))
+}
+
+/// A date, a Unix timestamp, or an age relative to `now`
+fn parse_time(value: &str, now: u64) -> Result<Span, String> {
+    if value.bytes().all(|b| b.is_ascii_digit()) {
+        return parse_count(value).map(single);
+    }
+    if value.contains('-') {
+        let (start, length) = parse_date(value)?;
+        return Ok(Span { start, end: start + length });
+    }
+    let age = parse_duration(value)?;
+    Ok(single(now.saturating_sub(age)))
+}
+
+fn has_wildcards(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+fn base_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Match `text` against a glob where `*` is any run and `?` any one character
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Field to order query results by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    #[default]
+    Path,
+    Size,
+    Created,
+    Version,
+}
+
+impl FromStr for SortKey {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "name" => Ok(SortKey::Name),
+            "path" => Ok(SortKey::Path),
+            "size" => Ok(SortKey::Size),
+            "created" | "date" => Ok(SortKey::Created),
+            "version" => Ok(SortKey::Version),
+            _ => Err(QueryError::UnknownSortKey(s.to_string())),
+        }
+    }
+}
+
+/// Ordering and pagination of query results
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryOptions {
+    pub sort: SortKey,
+    pub descending: bool,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl QueryOptions {
+    /// Sort `files` and cut out the requested page
+    pub fn apply<'a>(&self, mut files: Vec<&'a FileMetadata>) -> Vec<&'a FileMetadata> {
+        files.sort_by(|a, b| {
+            let ordering = match self.sort {
+                SortKey::Name => a.original_name.cmp(&b.original_name),
+                SortKey::Path => a.path.cmp(&b.path).then(a.version.cmp(&b.version)),
+                SortKey::Size => a.total_size.cmp(&b.total_size),
+                SortKey::Created => a.created_at.cmp(&b.created_at),
+                SortKey::Version => a.version.cmp(&b.version),
+            };
+            // Fall back to the ID so results are stable between runs
+            let ordering = ordering.then_with(|| a.id.cmp(&b.id));
+            if self.descending { ordering.reverse() } else { ordering }
+        });
+
+        files
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_800_000_000;
+
+    fn file(path: &str, size: u64, created_at: u64) -> FileMetadata {
+        let mut metadata = FileMetadata::new(base_name(path).to_string(), vec![], size);
+        metadata.path = path.to_string();
+        metadata.created_at = created_at;
+        metadata
+    }
+
+    fn query(input: &str) -> Query {
+        Query::parse_at(input, NOW).unwrap()
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.log", "build.log"));
+        assert!(glob_match("b?ild.*", "build.log"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(!glob_match("*.log", "build.txt"));
+        assert!(!glob_match("a*b", "acbd"));
+    }
+
+    #[test]
+    fn test_text_terms() {
+        let mut log = file("logs/build.log", 10, NOW);
+        log.tags.insert("prod".to_string());
+        log.attributes.insert("team".to_string(), "infra".to_string());
+
+        assert!(query("").matches(&log));
+        assert!(query("name:*.log").matches(&log));
+        assert!(query("build").matches(&log));
+        assert!(query("path:logs/*").matches(&log));
+        assert!(!query("path:*.txt").matches(&log));
+        assert!(query("tag:prod attr:team=infra attr:team").matches(&log));
+        assert!(!query("attr:team=web").matches(&log));
+        assert!(query("-tag:dev type:file").matches(&log));
+        assert!(!query("-tag:prod").matches(&log));
+        assert!(query("\"name:*.log\"").matches(&log));
+        assert!(query("mime:text/*").matches(&log));
+        log.mime_type = Some("image/png".to_string());
+        assert!(query("mime:image -mime:image/jpeg").matches(&log));
+    }
+
+    #[test]
+    fn test_numeric_terms() {
+        let (day, _) = parse_date("2026-01-01").unwrap();
+        let big = file("big.bin", 20 * 1_000_000, day + 3600);
+
+        assert!(query("size>10MB").matches(&big));
+        assert!(query("size>=20MB size<=20MB size=20MB").matches(&big));
+        assert!(!query("size<20MB").matches(&big));
+        assert!(query("created=2026-01-01").matches(&big));
+        assert!(query("created>=2026-01-01 created<2026-01-02").matches(&big));
+        assert!(!query("created>2026-01-01").matches(&big));
+        assert!(query("created>2025-12-31").matches(&big));
+        assert!(query("version=1 chunks:0").matches(&big));
+
+        // Only positive created terms bound the range an index has to scan
+        let range = query("created>=2026-01-01 created<2026-01-03 -created=2026-01-01 size>1").created_range();
+        assert_eq!(range, Some(day..day + 2 * 86_400));
+        assert_eq!(query("size>1 -created>2026-01-01").created_range(), None);
+    }
+
+    #[test]
+    fn test_relative_dates() {
+        let recent = file("a", 1, NOW - 3600);
+        let old = file("b", 1, NOW - 30 * 86_400);
+
+        assert!(query("created>1d").matches(&recent));
+        assert!(!query("created>1d").matches(&old));
+        assert!(query("created<1w").matches(&old));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Query::parse_at("owner:me", NOW), Err(QueryError::UnknownField("owner".to_string())));
+        assert!(matches!(Query::parse_at("tag>prod", NOW), Err(QueryError::UnsupportedOperator { .. })));
+        assert!(matches!(Query::parse_at("size>lots", NOW), Err(QueryError::InvalidValue { .. })));
+        assert!(matches!(Query::parse_at("size>", NOW), Err(QueryError::InvalidValue { .. })));
+        assert_eq!(Query::parse_at("\"name:a", NOW), Err(QueryError::UnterminatedQuote));
+    }
+
+    #[test]
+    fn test_sort_and_paginate() {
+        let files = [file("c", 300, 1), file("a", 100, 3), file("b", 200, 2)];
+        let refs: Vec<&FileMetadata> = files.iter().collect();
+
+        let by_path = QueryOptions::default().apply(refs.clone());
+        assert_eq!(by_path.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(), ["a", "b", "c"]);
+
+        let options = QueryOptions {
+            sort: SortKey::Size,
+            descending: true,
+            offset: 1,
+            limit: Some(1),
+        };
+        let page = options.apply(refs);
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].path, "b");
+
+        assert_eq!("created".parse::<SortKey>().unwrap(), SortKey::Created);
+        assert!("owner".parse::<SortKey>().is_err());
+    }
+}
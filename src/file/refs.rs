@@ -0,0 +1,295 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use redb::{Database, ReadableTable, TableDefinition};
+use crate::content::ContentAddress;
+use super::registry::FileId;
+use super::units::now_secs;
+
+/// Ref name -> JSON encoded `RefRecord`
+const REFS: TableDefinition<&str, &[u8]> = TableDefinition::new("refs");
+
+/// What a ref points at
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "target", rename_all = "snake_case")]
+pub enum RefTarget {
+    File(FileId),
+    Content(ContentAddress),
+}
+
+impl fmt::Display for RefTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RefTarget::File(file_id) => write!(f, "file {}", file_id),
+            RefTarget::Content(address) => write!(f, "content {}", address),
+        }
+    }
+}
+
+/// One entry in a ref's history; a `None` target records a deletion
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefUpdate {
+    pub target: Option<RefTarget>,
+    pub at: u64, // Unix timestamp
+}
+
+/// A ref that currently points somewhere
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefEntry {
+    pub name: String,
+    pub target: RefTarget,
+    pub updated_at: u64,
+}
+
+/// What the current value of a ref must be for an update to go through
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefCondition {
+    /// Update unconditionally
+    Any,
+    /// The ref must not exist yet
+    Absent,
+    /// The ref must currently point at this target
+    Target(RefTarget),
+}
+
+/// Stored state of a ref, kept after deletion so its history survives
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RefRecord {
+    target: Option<RefTarget>,
+    history: Vec<RefUpdate>, // Oldest first
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RefError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Database error: {0}")]
+    Database(Box<redb::Error>),
+
+    #[error("Invalid ref name '{0}'")]
+    InvalidName(String),
+
+    #[error("Ref not found: {0}")]
+    NotFound(String),
+
+    #[error("Ref '{name}' changed: expected {expected}, found {actual}")]
+    Conflict { name: String, expected: String, actual: String },
+}
+
+impl_from_redb_error!(RefError);
+
+pub type RefResult<T> = Result<T, RefError>;
+
+/// Mutable names such as `nightly-build/latest` that point at registered
+/// files or content roots.
+///
+/// Refs live in their own small redb database next to the file registry.
+/// Every update is a write transaction, so compare-and-swap checks cannot
+/// race with other writers.
+#[derive(Debug)]
+pub struct RefStore {
+    database_path: PathBuf,
+}
+
+impl RefStore {
+    /// Open or create the ref database in `storage_dir`
+    pub fn new<P: AsRef<Path>>(storage_dir: P) -> RefResult<Self> {
+        std::fs::create_dir_all(storage_dir.as_ref())?;
+        let store = Self {
+            database_path: storage_dir.as_ref().join("refs.redb"),
+        };
+
+        // Make sure the table exists so read transactions can open it
+        let database = store.open()?;
+        let txn = database.begin_write()?;
+        txn.open_table(REFS)?;
+        txn.commit()?;
+        Ok(store)
+    }
+
+    fn open(&self) -> RefResult<Database> {
+        Ok(Database::create(&self.database_path)?)
+    }
+
+    /// Current target of a ref
+    pub fn get(&self, name: &str) -> RefResult<Option<RefTarget>> {
+        Ok(self.record(name)?.and_then(|record| record.target))
+    }
+
+    /// Every existing ref, sorted by name
+    pub fn list(&self) -> RefResult<Vec<RefEntry>> {
+        let database = self.open()?;
+        let txn = database.begin_read()?;
+        let table = txn.open_table(REFS)?;
+
+        let mut entries = Vec::new();
+        for row in table.iter()? {
+            let (name, bytes) = row?;
+            let record: RefRecord = serde_json::from_slice(bytes.value())?;
+            if let Some(target) = record.target {
+                entries.push(RefEntry {
+                    name: name.value().to_string(),
+                    target,
+                    updated_at: record.history.last().map_or(0, |update| update.at),
+                });
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Where a ref has pointed over time, oldest first. Deleted refs keep
+    /// their history.
+    pub fn history(&self, name: &str) -> RefResult<Vec<RefUpdate>> {
+        self.record(name)?
+            .map(|record| record.history)
+            .ok_or_else(|| RefError::NotFound(name.to_string()))
+    }
+
+    /// Point `name` at `target` if its current value satisfies `condition`
+    pub fn set(&self, name: &str, target: RefTarget, condition: &RefCondition) -> RefResult<()> {
+        validate_ref_name(name)?;
+        self.update(name, Some(target), condition)
+    }
+
+    /// Delete a ref if its current value satisfies `condition`
+    pub fn delete(&self, name: &str, condition: &RefCondition) -> RefResult<RefTarget> {
+        let current = self.get(name)?.ok_or_else(|| RefError::NotFound(name.to_string()))?;
+        // Pin the check to the value just read so a concurrent change is not lost
+        let condition = match condition {
+            RefCondition::Any => RefCondition::Target(current.clone()),
+            other => other.clone(),
+        };
+        self.update(name, None, &condition)?;
+        Ok(current)
+    }
+
+    /// Check the condition and write the new value in one transaction
+    fn update(&self, name: &str, target: Option<RefTarget>, condition: &RefCondition) -> RefResult<()> {
+        let database = self.open()?;
+        let txn = database.begin_write()?;
+        {
+            let mut table = txn.open_table(REFS)?;
+            let mut record: RefRecord = match table.get(name)? {
+                Some(bytes) => serde_json::from_slice(bytes.value())?,
+                None => RefRecord::default(),
+            };
+
+            let satisfied = match condition {
+                RefCondition::Any => true,
+                RefCondition::Absent => record.target.is_none(),
+                RefCondition::Target(expected) => record.target.as_ref() == Some(expected),
+            };
+            if !satisfied {
+                return Err(RefError::Conflict {
+                    name: name.to_string(),
+                    expected: match condition {
+                        RefCondition::Target(expected) => expected.to_string(),
+                        _ => "no ref".to_string(),
+                    },
+                    actual: record.target.as_ref().map_or("no ref".to_string(), RefTarget::to_string),
+                });
+            }
+
+            record.history.push(RefUpdate { target: target.clone(), at: now_secs() });
+            record.target = target;
+            table.insert(name, serde_json::to_vec(&record)?.as_slice())?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn record(&self, name: &str) -> RefResult<Option<RefRecord>> {
+        let database = self.open()?;
+        let txn = database.begin_read()?;
+        let table = txn.open_table(REFS)?;
+        let record = match table.get(name)? {
+            Some(bytes) => Some(serde_json::from_slice(bytes.value())?),
+            None => None,
+        };
+        Ok(record)
+    }
+}
+
+/// Ref names are `/`-separated segments of letters, digits, `.`, `_` and `-`
+pub fn validate_ref_name(name: &str) -> RefResult<()> {
+    let valid_segment = |segment: &str| {
+        !segment.is_empty()
+            && segment != "."
+            && segment != ".."
+            && !segment.starts_with('-')
+            && segment.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    };
+
+    if name.split('/').all(valid_segment) {
+        Ok(())
+    } else {
+        Err(RefError::InvalidName(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_set_get_and_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RefStore::new(temp_dir.path()).unwrap();
+        let first = RefTarget::File(Uuid::new_v4());
+        let second = RefTarget::Content(ContentAddress::from_data(b"build"));
+
+        store.set("nightly-build/latest", first.clone(), &RefCondition::Absent).unwrap();
+        store.set("nightly-build/latest", second.clone(), &RefCondition::Target(first.clone())).unwrap();
+        assert_eq!(store.get("nightly-build/latest").unwrap(), Some(second.clone()));
+        assert_eq!(store.get("missing").unwrap(), None);
+
+        let removed = store.delete("nightly-build/latest", &RefCondition::Any).unwrap();
+        assert_eq!(removed, second);
+        assert!(store.list().unwrap().is_empty());
+
+        // History outlives the ref and reopening the store
+        let store = RefStore::new(temp_dir.path()).unwrap();
+        let targets: Vec<Option<RefTarget>> = store.history("nightly-build/latest").unwrap()
+            .into_iter()
+            .map(|update| update.target)
+            .collect();
+        assert_eq!(targets, vec![Some(first), Some(second), None]);
+    }
+
+    #[test]
+    fn test_compare_and_swap_conflicts() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = RefStore::new(temp_dir.path()).unwrap();
+        let current = RefTarget::File(Uuid::new_v4());
+        let stale = RefTarget::File(Uuid::new_v4());
+
+        store.set("release", current.clone(), &RefCondition::Any).unwrap();
+        assert!(matches!(
+            store.set("release", stale.clone(), &RefCondition::Absent),
+            Err(RefError::Conflict { .. })
+        ));
+        assert!(matches!(
+            store.set("release", current.clone(), &RefCondition::Target(stale.clone())),
+            Err(RefError::Conflict { .. })
+        ));
+        assert!(matches!(store.delete("release", &RefCondition::Target(stale)), Err(RefError::Conflict { .. })));
+        assert_eq!(store.list().unwrap().len(), 1);
+        assert_eq!(store.history("release").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_ref_names() {
+        for name in ["latest", "nightly-build/latest", "v1.2_rc"] {
+            assert!(validate_ref_name(name).is_ok(), "{}", name);
+        }
+        for name in ["", "a//b", "/a", "a/", "../x", "a b", "-f"] {
+            assert!(validate_ref_name(name).is_err(), "{}", name);
+        }
+    }
+}
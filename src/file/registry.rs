@@ -1,13 +1,24 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use crate::content::ContentAddress;
+use redb::{Database, MultimapTableDefinition, ReadableTable, ReadableTableMetadata, TableDefinition, WriteTransaction};
+use crate::content::{ContentAddress, MerkleTree, Outboard, PrefixIndex};
+use super::units::now_secs;
+use super::version::RetentionPolicy;
+use super::filter::FileFilter;
+use super::mime;
+use super::query::Query;
+use super::posix::PosixMetadata;
+use super::atomic::write_atomic;
+use super::trash::TrashEntry;
 
 pub type FileId = Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FileMetadata {
     pub id: FileId,
     pub original_name: String,
@@ -15,6 +26,62 @@ pub struct FileMetadata {
     pub total_size: u64,
     pub created_at: u64, // Unix timestamp
     pub chunk_count: usize,
+    #[serde(default)]
+    pub merkle_root: Option<ContentAddress>, // Root over chunk_addresses (None for empty files)
+    #[serde(default)]
+    pub stream_root: Option<ContentAddress>, // BLAKE3 root of the outboard tree, if one was built
+    #[serde(default)]
+    pub tree_root: Option<ContentAddress>, // Set when the entry is a directory stored with `put -r`
+    #[serde(default)]
+    pub path: String, // Logical path; every upload to the same path is a new version
+    #[serde(default)]
+    pub version: u32, // 1-based version number within `path`
+    #[serde(default)]
+    pub parent: Option<FileId>, // Previous version of the same path
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>, // User-defined key/value metadata
+    #[serde(default)]
+    pub posix: Option<PosixMetadata>, // Mode, times, ownership, symlink target and xattrs at put time
+    #[serde(default)]
+    pub renames: Vec<Rename>, // Every move of this entry, oldest first
+    #[serde(default)]
+    pub digest: Option<String>, // BLAKE3 hash of the whole file, hex encoded (None for directories and symlinks)
+    #[serde(default)]
+    pub mime_type: Option<String>, // Detected at ingest from magic bytes, then the extension
+    #[serde(default)]
+    pub expires_at: Option<u64>, // Unix timestamp after which `expire` removes the entry
+    #[serde(default)]
+    pub pin: Option<PinMode>, // Pinned entries cannot be removed and nothing they reference is ever released
+}
+
+/// What a pin holds on to besides the pinned entry itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PinMode {
+    /// Only the pinned entry, with everything it references
+    Direct,
+    /// Also every entry registered below the pinned entry's logical path,
+    /// including ones added after the pin
+    Recursive,
+}
+
+impl std::fmt::Display for PinMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            PinMode::Direct => "direct",
+            PinMode::Recursive => "recursive",
+        })
+    }
+}
+
+/// A recorded change of an entry's logical path
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+    pub at: u64, // Unix timestamp
 }
 
 impl FileMetadata {
@@ -25,14 +92,50 @@ impl FileMetadata {
     ) -> Self {
         Self {
             id: Uuid::new_v4(),
+            path: original_name.clone(),
+            version: 1,
+            parent: None,
+            tags: BTreeSet::new(),
+            attributes: BTreeMap::new(),
+            posix: None,
+            renames: Vec::new(),
+            digest: None,
+            mime_type: None,
+            expires_at: None,
+            pin: None,
             original_name,
             chunk_count: chunk_addresses.len(),
+            merkle_root: MerkleTree::from_addresses(&chunk_addresses).map(|tree| tree.root()),
+            stream_root: None,
+            tree_root: None,
             chunk_addresses,
             total_size,
-            created_at: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            created_at: now_secs(),
+        }
+    }
+    
+    /// Whether this entry is a directory tree rather than a single file
+    pub fn is_directory(&self) -> bool {
+        self.tree_root.is_some()
+    }
+    
+    /// Whether the entry's time-to-live has run out. A pin overrides the
+    /// time-to-live for as long as it is in place.
+    pub fn is_expired(&self, now: u64) -> bool {
+        !self.is_pinned() && self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+    
+    pub fn is_pinned(&self) -> bool {
+        self.pin.is_some()
+    }
+    
+    /// MIME type to serve the file with: the detected type, or a guess from
+    /// the name for entries registered before detection existed
+    pub fn content_type(&self) -> &str {
+        match &self.mime_type {
+            Some(mime_type) => mime_type,
+            None if self.is_directory() => mime::DIRECTORY,
+            None => mime::from_extension(&self.original_name).unwrap_or(mime::OCTET_STREAM),
         }
     }
     
@@ -45,11 +148,15 @@ impl FileMetadata {
         }
     }
     
-    pub fn short_id(&self) -> String {
-        format!("{:.8}", self.id.to_string().replace('-', ""))
+    /// Key used for prefix lookups: the UUID as plain lowercase hex
+    pub fn id_key(&self) -> String {
+        self.id.simple().to_string()
     }
 }
 
+/// Minimum length of abbreviated file IDs shown to users
+pub const MIN_ID_PREFIX_LEN: usize = 8;
+
 #[derive(Debug, thiserror::Error)]
 pub enum FileRegistryError {
     #[error("IO error: {0}")]
@@ -61,36 +168,335 @@ pub enum FileRegistryError {
     #[error("File not found: {0}")]
     FileNotFound(FileId),
     
+    #[error("No file matches '{0}'")]
+    NoMatch(String),
+    
+    #[error("Ambiguous file ID '{prefix}' matches: {}", .candidates.join(", "))]
+    AmbiguousPrefix { prefix: String, candidates: Vec<String> },
+    
+    #[error("Database error: {0}")]
+    Database(Box<redb::Error>),
+    
+    #[error("A file named '{0}' already exists")]
+    NameCollision(String),
+    
+    #[error("Invalid file name '{0}'")]
+    InvalidName(String),
+    
+    #[error("'{0}' is pinned; unpin it first")]
+    Pinned(String),
+    
+    #[error("{} imported IDs are already in use: {}", .0.len(), .0.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", "))]
+    IdConflict(Vec<FileId>),
+    
     #[error("Registry file is corrupted")]
     CorruptedRegistry,
+    
+    #[error("Legacy registry {} could not be read; repair or move it aside to continue", .0.display())]
+    CorruptedLegacyRegistry(PathBuf),
+    
+    #[error("Registry at {0} is in use by another process")]
+    Locked(PathBuf),
+    
+    #[error("Registry was changed by someone else while this change was prepared; try again")]
+    ConcurrentChange,
+}
+
+impl_from_redb_error!(FileRegistryError);
+
+impl FileRegistryError {
+    /// Whether the database itself is damaged, as opposed to unavailable
+    /// (for example because another process holds it). A legacy JSON
+    /// registry that fails to migrate says nothing about the database.
+    pub fn is_corruption(&self) -> bool {
+        match self {
+            FileRegistryError::CorruptedRegistry => true,
+            FileRegistryError::Database(error) => match &**error {
+                redb::Error::Corrupted(_) => true,
+                // What redb reports for a bad header or a truncated file
+                redb::Error::Io(e) => matches!(e.kind(), std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
 }
 
 pub type FileRegistryResult<T> = Result<T, FileRegistryError>;
 
-/// Registry for tracking file-level metadata
+/// Number of rotating JSON backups kept of the registry
+pub const REGISTRY_BACKUP_GENERATIONS: usize = 3;
+
+/// How long opening the registry waits for another process to release it
+pub const REGISTRY_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What was restored when the registry database could not be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryRecovery {
+    pub backup: PathBuf,         // Backup the entries were restored from
+    pub files: usize,            // Number of entries recovered
+    pub reason: String,          // Why the primary registry was unusable
+    pub moved_to: PathBuf,       // Where the unreadable database was set aside
+}
+
+/// Outcome of importing entries exported from another registry
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    pub imported: Vec<FileMetadata>,
+    pub unchanged: usize, // Entries already present with identical metadata
+    pub rekeyed: usize,   // Entries given a new ID because theirs was taken
+}
+
+/// File metadata keyed by the UUID as an integer
+const FILES: TableDefinition<u128, &[u8]> = TableDefinition::new("files");
+/// Original file name -> IDs of files with that name
+const NAME_INDEX: MultimapTableDefinition<&str, u128> = MultimapTableDefinition::new("files_by_name");
+/// Creation timestamp -> IDs of files created at that second
+const CREATED_INDEX: MultimapTableDefinition<u64, u128> = MultimapTableDefinition::new("files_by_created");
+/// Whole-file digest -> IDs of files with exactly that content
+const DIGEST_INDEX: MultimapTableDefinition<&str, u128> = MultimapTableDefinition::new("files_by_digest");
+/// Chunk address -> IDs of files made partly of that chunk
+const CHUNK_INDEX: MultimapTableDefinition<&str, u128> = MultimapTableDefinition::new("files_by_chunk");
+/// Soft-deleted entries keyed by file ID
+const TRASH: TableDefinition<u128, &[u8]> = TableDefinition::new("trash");
+/// Registry-wide counters
+const META: TableDefinition<&str, u64> = TableDefinition::new("meta");
+/// Number of committed changes, used to tell when a cache is out of date
+const GENERATION: &str = "generation";
+
+/// Registry databases this process has open. redb allows one handle per
+/// file, so registries opened on the same directory share it.
+static OPEN_DATABASES: Mutex<BTreeMap<PathBuf, Weak<Database>>> = Mutex::new(BTreeMap::new());
+
+/// Full contents of the registry, as written to backups
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegistryContents {
+    pub files: HashMap<FileId, FileMetadata>,
+    #[serde(default)]
+    pub trash: HashMap<FileId, TrashEntry>,
+}
+
+/// Registry for tracking file-level metadata.
+///
+/// Entries live in an embedded redb database so every change is a small
+/// transaction rather than a rewrite of the whole registry. All entries are
+/// cached in memory for lookups.
+///
+/// A registry belongs to one process at a time: the database stays open,
+/// and locked, for as long as any registry on it exists, and another
+/// process opening it waits up to `REGISTRY_LOCK_TIMEOUT` before failing
+/// with `Locked`. Registries within the process share the database. Each
+/// reloads its cache before a change if another has committed since, and a
+/// change prepared from an outdated cache fails with `ConcurrentChange`.
 #[derive(Debug)]
 pub struct FileRegistry {
-    registry_path: PathBuf,
+    database: Arc<Database>,
+    generation: u64, // Changes committed when the cache was loaded
+    backups_dir: PathBuf,
+    outboards_dir: PathBuf,
     files: HashMap<FileId, FileMetadata>,
+    trash: HashMap<FileId, TrashEntry>,
+    recovery: Option<RegistryRecovery>,
 }
 
 impl FileRegistry {
     pub fn new<P: AsRef<Path>>(storage_dir: P) -> FileRegistryResult<Self> {
-        let registry_path = storage_dir.as_ref().join("file_registry.json");
+        let storage_dir = storage_dir.as_ref();
+        fs::create_dir_all(storage_dir)?;
         
-        let files = if registry_path.exists() {
-            Self::load_registry(&registry_path)?
-        } else {
-            HashMap::new()
+        let legacy_path = storage_dir.join("file_registry.json");
+        let registry = match Self::open_database(storage_dir, Some(&legacy_path)) {
+            Ok(registry) => registry,
+            // Only damaged data is worth recovering from; a database held by
+            // another process or IO errors such as permissions are reported
+            // as they are
+            Err(e) if e.is_corruption() => Self::recover_from_backup(storage_dir, e)?,
+            Err(e) => return Err(e),
         };
         
-        Ok(Self {
-            registry_path,
-            files,
-        })
+        // Make sure there is a backup to fall back on from the very start
+        let mut registry = registry;
+        if !registry.files.is_empty() && Self::newest_valid_backup(&registry.backups_dir).is_none() {
+            registry.write_backup()?;
+        }
+        Ok(registry)
+    }
+    
+    /// Create or open the database, migrate the legacy JSON registry at
+    /// `legacy_path` into it if there is one, and load every entry
+    fn open_database(storage_dir: &Path, legacy_path: Option<&Path>) -> FileRegistryResult<Self> {
+        let mut registry = Self {
+            database: Self::open(&storage_dir.join("file_registry.redb"))?,
+            generation: 0,
+            backups_dir: storage_dir.join("registry_backups"),
+            outboards_dir: storage_dir.join("outboards"),
+            files: HashMap::new(),
+            trash: HashMap::new(),
+            recovery: None,
+        };
+        
+        // Make sure every table exists so read transactions can open them
+        let txn = registry.database.begin_write()?;
+        txn.open_table(FILES)?;
+        txn.open_multimap_table(NAME_INDEX)?;
+        txn.open_multimap_table(CREATED_INDEX)?;
+        txn.open_multimap_table(DIGEST_INDEX)?;
+        let chunks_indexed = !txn.open_multimap_table(CHUNK_INDEX)?.is_empty()?;
+        txn.open_table(TRASH)?;
+        txn.open_table(META)?;
+        txn.commit()?;
+        
+        // Upserts are keyed by ID, so an interrupted migration can simply rerun
+        registry.load_registry()?;
+        if let Some(legacy_path) = legacy_path.filter(|path| path.exists()) {
+            registry.migrate_from_json(legacy_path)?;
+            registry.load_registry()?;
+        }
+        if !chunks_indexed {
+            registry.index_chunks()?;
+        }
+        Ok(registry)
+    }
+    
+    /// Fill the chunk index for entries stored before it existed. Only the
+    /// index changes, so the cache stays current.
+    fn index_chunks(&self) -> FileRegistryResult<()> {
+        if self.files.values().all(|metadata| metadata.chunk_addresses.is_empty()) {
+            return Ok(());
+        }
+        let txn = self.database.begin_write()?;
+        {
+            let mut index = txn.open_multimap_table(CHUNK_INDEX)?;
+            for metadata in self.files.values() {
+                for address in &metadata.chunk_addresses {
+                    index.insert(address.to_hex().as_str(), metadata.id.as_u128())?;
+                }
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+    
+    /// Set the unreadable database aside and rebuild it from the newest
+    /// backup that still parses
+    fn recover_from_backup(storage_dir: &Path, error: FileRegistryError) -> FileRegistryResult<Self> {
+        let Some((backup, contents)) = Self::newest_valid_backup(&storage_dir.join("registry_backups")) else {
+            return Err(error);
+        };
+        
+        let database_path = storage_dir.join("file_registry.redb");
+        let moved_to = database_path.with_extension(format!("redb.corrupted-{}", now_secs()));
+        if database_path.exists() {
+            fs::rename(&database_path, &moved_to)?;
+        }
+        
+        let mut registry = Self::open_database(storage_dir, None)?;
+        let entries: Vec<FileMetadata> = contents.files.into_values().collect();
+        registry.persist(&entries, &[])?;
+        registry.transact(|txn| {
+            for entry in contents.trash.values() {
+                Self::insert_trash_entry(txn, entry)?;
+            }
+            Ok(())
+        })?;
+        registry.load_registry()?;
+        
+        registry.recovery = Some(RegistryRecovery {
+            backup,
+            files: registry.files.len(),
+            reason: error.to_string(),
+            moved_to,
+        });
+        Ok(registry)
+    }
+    
+    /// Details of the recovery performed on open, if the database was unreadable
+    pub fn recovery(&self) -> Option<&RegistryRecovery> {
+        self.recovery.as_ref()
+    }
+    
+    fn backup_path(backups_dir: &Path, generation: usize) -> PathBuf {
+        backups_dir.join(format!("file_registry.json.{}", generation))
+    }
+    
+    /// Write the committed entries as the newest backup generation, shifting
+    /// older generations down and dropping the oldest
+    pub fn write_backup(&mut self) -> FileRegistryResult<()> {
+        let contents = self.read_contents()?;
+        fs::create_dir_all(&self.backups_dir)?;
+        for generation in (1..REGISTRY_BACKUP_GENERATIONS).rev() {
+            let from = Self::backup_path(&self.backups_dir, generation);
+            if from.exists() {
+                fs::rename(&from, Self::backup_path(&self.backups_dir, generation + 1))?;
+            }
+        }
+        
+        let json = serde_json::to_vec_pretty(&contents)?;
+        write_atomic(Self::backup_path(&self.backups_dir, 1), &json)?;
+        Ok(())
+    }
+    
+    /// Newest backup generation that parses, with its entries
+    fn newest_valid_backup(backups_dir: &Path) -> Option<(PathBuf, RegistryContents)> {
+        (1..=REGISTRY_BACKUP_GENERATIONS)
+            .map(|generation| Self::backup_path(backups_dir, generation))
+            .find_map(|path| {
+                let contents = Self::parse_json_registry(&fs::read_to_string(&path).ok()?).ok()?;
+                Some((path, contents))
+            })
+    }
+    
+    /// Copy of every live and trashed entry
+    pub fn contents(&self) -> RegistryContents {
+        RegistryContents {
+            files: self.files.clone(),
+            trash: self.trash.clone(),
+        }
     }
     
-    /// Register a new file and return its metadata
+    /// Open the database, or share the handle this process already has,
+    /// waiting for another process to let go of it
+    fn open(path: &Path) -> FileRegistryResult<Arc<Database>> {
+        let mut open = OPEN_DATABASES.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(database) = open.get(path).and_then(Weak::upgrade) {
+            return Ok(database);
+        }
+        
+        let deadline = Instant::now() + REGISTRY_LOCK_TIMEOUT;
+        let database = loop {
+            match Database::create(path) {
+                Err(redb::DatabaseError::DatabaseAlreadyOpen) if Instant::now() < deadline => {
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                Err(redb::DatabaseError::DatabaseAlreadyOpen) => {
+                    return Err(FileRegistryError::Locked(path.to_path_buf()));
+                }
+                result => break Arc::new(result?),
+            }
+        };
+        open.retain(|_, database| database.strong_count() > 0);
+        open.insert(path.to_path_buf(), Arc::downgrade(&database));
+        Ok(database)
+    }
+    
+    /// Reload the cache if another registry has committed changes since it
+    /// was loaded
+    pub fn refresh(&mut self) -> FileRegistryResult<()> {
+        let txn = self.database.begin_read()?;
+        let generation = Self::read_generation(&txn.open_table(META)?)?;
+        drop(txn);
+        if generation != self.generation {
+            self.load_registry()?;
+        }
+        Ok(())
+    }
+    
+    fn read_generation(meta: &impl ReadableTable<&'static str, u64>) -> FileRegistryResult<u64> {
+        Ok(meta.get(GENERATION)?.map_or(0, |generation| generation.value()))
+    }
+    
+    /// Register a new file and return its metadata.
+    /// The logical path defaults to the original name.
     pub fn register_file(
         &mut self,
         original_name: String,
@@ -98,129 +504,990 @@ impl FileRegistry {
         total_size: u64,
     ) -> FileRegistryResult<FileMetadata> {
         let metadata = FileMetadata::new(original_name, chunk_addresses, total_size);
+        self.insert_version(metadata)
+    }
+    
+    /// Register a new file under a logical path, adding a version if the path exists
+    pub fn register_file_at(
+        &mut self,
+        path: &str,
+        original_name: String,
+        chunk_addresses: Vec<ContentAddress>,
+        total_size: u64,
+    ) -> FileRegistryResult<FileMetadata> {
+        let mut metadata = FileMetadata::new(original_name, chunk_addresses, total_size);
+        metadata.path = path.to_string();
+        self.insert_version(metadata)
+    }
+    
+    /// Register an entry prepared in full by the caller, as the next version
+    /// of its path. The entry and everything on it is committed together.
+    pub fn register(&mut self, metadata: FileMetadata) -> FileRegistryResult<FileMetadata> {
+        self.insert_version(metadata)
+    }
+    
+    /// Link a new entry after the latest version of its path and persist it
+    fn insert_version(&mut self, mut metadata: FileMetadata) -> FileRegistryResult<FileMetadata> {
+        self.refresh()?;
+        if let Some(latest) = self.latest_version(&metadata.path) {
+            metadata.version = latest.version + 1;
+            metadata.parent = Some(latest.id);
+        }
+        self.persist(std::slice::from_ref(&metadata), &[])?;
         self.files.insert(metadata.id, metadata.clone());
-        self.save_registry()?;
         Ok(metadata)
     }
     
+    /// All versions stored under a logical path, oldest first
+    pub fn versions(&self, path: &str) -> Vec<&FileMetadata> {
+        let mut versions: Vec<&FileMetadata> = self.files
+            .values()
+            .filter(|metadata| metadata.path == path)
+            .collect();
+        versions.sort_by_key(|metadata| metadata.version);
+        versions
+    }
+    
+    /// Most recent version of a logical path
+    pub fn latest_version(&self, path: &str) -> Option<&FileMetadata> {
+        self.files
+            .values()
+            .filter(|metadata| metadata.path == path)
+            .max_by_key(|metadata| metadata.version)
+    }
+    
+    /// A specific version number of a logical path
+    pub fn get_version(&self, path: &str, version: u32) -> Option<&FileMetadata> {
+        self.files
+            .values()
+            .find(|metadata| metadata.path == path && metadata.version == version)
+    }
+    
+    /// The version of a logical path that was current at `timestamp`
+    pub fn version_at(&self, path: &str, timestamp: u64) -> Option<&FileMetadata> {
+        self.files
+            .values()
+            .filter(|metadata| metadata.path == path && metadata.created_at <= timestamp)
+            .max_by_key(|metadata| metadata.version)
+    }
+    
+    /// Remove old versions of a path according to a retention policy,
+    /// returning the removed entries. Pinned versions are always kept.
+    pub fn prune_versions(&mut self, path: &str, policy: &RetentionPolicy) -> FileRegistryResult<Vec<FileMetadata>> {
+        self.refresh()?;
+        let holders = self.pin_holders();
+        let to_prune: Vec<FileId> = policy.versions_to_prune(&self.versions(path), now_secs())
+            .into_iter()
+            .filter(|id| !holders.contains_key(id))
+            .collect();
+        self.remove_files(&to_prune)
+    }
+    
     /// Get file metadata by ID
     pub fn get_file(&self, file_id: &FileId) -> Option<&FileMetadata> {
         self.files.get(file_id)
     }
     
-    /// Find a file by its short ID (first 8 characters of UUID without dashes)
-    pub fn get_file_by_short_id(&self, short_id: &str) -> Option<&FileMetadata> {
-        self.files.values().find(|metadata| metadata.short_id() == short_id)
+    /// Find all files whose ID starts with `prefix` (dashes and case are ignored)
+    pub fn files_with_prefix(&self, prefix: &str) -> Vec<&FileMetadata> {
+        let prefix = prefix.replace('-', "").to_ascii_lowercase();
+        if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Vec::new();
+        }
+        
+        let mut matches: Vec<&FileMetadata> = self.files
+            .values()
+            .filter(|metadata| metadata.id_key().starts_with(&prefix))
+            .collect();
+        matches.sort_by_key(|metadata| metadata.id);
+        matches
+    }
+    
+    /// Resolve an abbreviated file ID of any length to exactly one file
+    pub fn resolve_prefix(&self, prefix: &str) -> FileRegistryResult<&FileMetadata> {
+        match self.files_with_prefix(prefix).as_slice() {
+            [] => Err(FileRegistryError::NoMatch(prefix.to_string())),
+            [metadata] => Ok(metadata),
+            candidates => Err(FileRegistryError::AmbiguousPrefix {
+                prefix: prefix.to_string(),
+                candidates: candidates
+                    .iter()
+                    .map(|metadata| format!("{} ({})", metadata.id, metadata.original_name))
+                    .collect(),
+            }),
+        }
+    }
+    
+    /// Shortest unique abbreviation of every registered file ID
+    pub fn short_ids(&self) -> HashMap<FileId, String> {
+        let index = PrefixIndex::new(self.files.values().map(FileMetadata::id_key));
+        self.files
+            .values()
+            .map(|metadata| {
+                let key = metadata.id_key();
+                (metadata.id, index.shortest_unique(&key, MIN_ID_PREFIX_LEN).to_string())
+            })
+            .collect()
+    }
+    
+    /// Shortest unique abbreviation of a single file ID
+    pub fn short_id(&self, file_id: &FileId) -> String {
+        let index = PrefixIndex::new(self.files.values().map(FileMetadata::id_key));
+        let key = file_id.simple().to_string();
+        index.shortest_unique(&key, MIN_ID_PREFIX_LEN).to_string()
+    }
+    
+    /// Apply a change to a registered file and persist it
+    pub fn update_file<F>(&mut self, file_id: &FileId, update: F) -> FileRegistryResult<&FileMetadata>
+    where
+        F: FnOnce(&mut FileMetadata),
+    {
+        self.refresh()?;
+        let mut metadata = self.files.get(file_id)
+            .ok_or(FileRegistryError::FileNotFound(*file_id))?
+            .clone();
+        update(&mut metadata);
+        self.persist(std::slice::from_ref(&metadata), &[])?;
+        self.files.insert(*file_id, metadata);
+        Ok(&self.files[file_id])
+    }
+    
+    /// Move a file to a new logical path, keeping its ID.
+    ///
+    /// Every version of the file's path moves with it so its history stays
+    /// intact, and each records the rename. If versions already exist under
+    /// `new_path`, the moved versions are appended after them, unless
+    /// `unique` is set, in which case any other entry with the same path or
+    /// name is an error. Returns the moved entries, oldest first.
+    pub fn rename(&mut self, file_id: &FileId, new_path: &str, unique: bool) -> FileRegistryResult<Vec<FileMetadata>> {
+        self.refresh()?;
+        let new_path = new_path.trim_matches('/');
+        let new_name = new_path.rsplit('/').next().unwrap_or_default();
+        if new_name.is_empty() || new_path.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+            return Err(FileRegistryError::InvalidName(new_path.to_string()));
+        }
+        
+        let old_path = self.files.get(file_id)
+            .ok_or(FileRegistryError::FileNotFound(*file_id))?
+            .path
+            .clone();
+        if old_path == new_path {
+            return Ok(self.versions(&old_path).into_iter().cloned().collect());
+        }
+        
+        if unique {
+            let name_taken = self.files_named(new_name)?
+                .iter()
+                .any(|metadata| metadata.path != old_path);
+            if name_taken || self.latest_version(new_path).is_some() {
+                return Err(FileRegistryError::NameCollision(new_path.to_string()));
+            }
+        }
+        
+        // Existing versions at the destination stay first in line
+        let mut previous = self.latest_version(new_path).map(|latest| (latest.version, latest.id));
+        let renamed_at = now_secs();
+        let mut moved: Vec<FileMetadata> = self.versions(&old_path).into_iter().cloned().collect();
+        for metadata in &mut moved {
+            if let Some((version, parent)) = previous {
+                metadata.version = version + 1;
+                metadata.parent = Some(parent);
+                previous = Some((metadata.version, metadata.id));
+            }
+            metadata.path = new_path.to_string();
+            metadata.original_name = new_name.to_string();
+            metadata.renames.push(Rename {
+                from: old_path.clone(),
+                to: new_path.to_string(),
+                at: renamed_at,
+            });
+        }
+        
+        self.persist(&moved, &[])?;
+        for metadata in &moved {
+            self.files.insert(metadata.id, metadata.clone());
+        }
+        Ok(moved)
+    }
+    
+    /// Register entries exported from another registry, keeping their IDs.
+    ///
+    /// Entries already present unchanged are skipped. Any other entry whose
+    /// ID is taken is a conflict: the import fails without changes unless
+    /// `rekey` is set, in which case it gets a fresh ID and parent links
+    /// between imported entries follow. Imported versions of a path that
+    /// already has versions here are numbered after the existing ones.
+    pub fn import_entries(&mut self, entries: Vec<FileMetadata>, rekey: bool) -> FileRegistryResult<ImportReport> {
+        self.refresh()?;
+        let mut report = ImportReport::default();
+        let mut seen = HashSet::new();
+        let mut conflicts = Vec::new();
+        let mut incoming = Vec::new();
+        for metadata in entries {
+            if self.files.get(&metadata.id) == Some(&metadata) {
+                report.unchanged += 1;
+                continue;
+            }
+            let taken = self.files.contains_key(&metadata.id) || self.trash.contains_key(&metadata.id);
+            if !seen.insert(metadata.id) || taken {
+                conflicts.push(incoming.len());
+            }
+            incoming.push(metadata);
+        }
+        if !conflicts.is_empty() && !rekey {
+            return Err(FileRegistryError::IdConflict(conflicts.iter().map(|&index| incoming[index].id).collect()));
+        }
+        
+        // Give each conflicting entry a new ID, then point parents at it
+        let mut new_ids = HashMap::new();
+        report.rekeyed = conflicts.len();
+        for index in conflicts {
+            let new_id = Uuid::new_v4();
+            new_ids.insert(incoming[index].id, new_id);
+            incoming[index].id = new_id;
+        }
+        // Versions of paths that exist here follow the existing ones
+        incoming.sort_by(|a, b| a.path.cmp(&b.path).then(a.version.cmp(&b.version)));
+        let mut previous: Option<(String, u32, FileId)> = None;
+        for metadata in &mut incoming {
+            if let Some(parent) = metadata.parent.and_then(|parent| new_ids.get(&parent)) {
+                metadata.parent = Some(*parent);
+            }
+            let after = match previous.take() {
+                Some((path, version, id)) if path == metadata.path => Some((version, id)),
+                _ => self.latest_version(&metadata.path).map(|latest| (latest.version, latest.id)),
+            };
+            if let Some((version, parent)) = after {
+                metadata.version = version + 1;
+                metadata.parent = Some(parent);
+                previous = Some((metadata.path.clone(), metadata.version, metadata.id));
+            }
+            // Outboards stay behind on the source node
+            metadata.stream_root = None;
+        }
+        
+        self.persist(&incoming, &[])?;
+        for metadata in &incoming {
+            self.files.insert(metadata.id, metadata.clone());
+        }
+        report.imported = incoming;
+        Ok(report)
+    }
+    
+    /// Make `files` the complete set of registered entries in a single
+    /// transaction, returning the entries that were dropped. Trashed
+    /// entries that come back leave the trash. Fails without changes if a
+    /// pinned entry would be dropped.
+    pub fn replace_files(&mut self, mut files: Vec<FileMetadata>) -> FileRegistryResult<Vec<FileMetadata>> {
+        self.refresh()?;
+        // Outboards are removed with their entries and not restored
+        for metadata in &mut files {
+            if metadata.stream_root.is_some() && !self.outboard_path(&metadata.id).exists() {
+                metadata.stream_root = None;
+            }
+        }
+        let keep: HashSet<FileId> = files.iter().map(|metadata| metadata.id).collect();
+        let dropped: Vec<FileId> = self.files.keys().filter(|id| !keep.contains(id)).copied().collect();
+        if let Some(holder) = self.pin_holder_of(&dropped) {
+            return Err(FileRegistryError::Pinned(holder.path.clone()));
+        }
+        let untrashed: Vec<FileId> = keep.iter().filter(|id| self.trash.contains_key(id)).copied().collect();
+        
+        self.transact(|txn| {
+            for file_id in &dropped {
+                Self::remove_entry(txn, file_id)?;
+            }
+            for metadata in &files {
+                Self::remove_entry(txn, &metadata.id)?;
+                Self::insert_entry(txn, metadata)?;
+            }
+            let mut trash = txn.open_table(TRASH)?;
+            for file_id in &untrashed {
+                trash.remove(file_id.as_u128())?;
+            }
+            Ok(())
+        })?;
+        
+        for file_id in &untrashed {
+            self.trash.remove(file_id);
+        }
+        let mut removed = Vec::new();
+        for file_id in &dropped {
+            self.remove_outboard(file_id)?;
+            removed.extend(self.files.remove(file_id));
+        }
+        for metadata in files {
+            self.files.insert(metadata.id, metadata);
+        }
+        Ok(removed)
     }
     
     /// Remove a file from the registry
     pub fn remove_file(&mut self, file_id: &FileId) -> FileRegistryResult<Option<FileMetadata>> {
-        let removed = self.files.remove(file_id);
-        if removed.is_some() {
-            self.save_registry()?;
+        self.refresh()?;
+        if !self.files.contains_key(file_id) {
+            return Ok(None);
+        }
+        if let Some(holder) = self.pin_holder_of(std::slice::from_ref(file_id)) {
+            return Err(FileRegistryError::Pinned(holder.path.clone()));
+        }
+        
+        self.persist(&[], std::slice::from_ref(file_id))?;
+        self.remove_outboard(file_id)?;
+        Ok(self.files.remove(file_id))
+    }
+    
+    /// Remove several files in a single transaction, returning those that
+    /// were registered
+    pub fn remove_files(&mut self, file_ids: &[FileId]) -> FileRegistryResult<Vec<FileMetadata>> {
+        self.refresh()?;
+        if file_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        if let Some(holder) = self.pin_holder_of(file_ids) {
+            return Err(FileRegistryError::Pinned(holder.path.clone()));
+        }
+        self.persist(&[], file_ids)?;
+        
+        let mut removed = Vec::new();
+        for file_id in file_ids {
+            if let Some(metadata) = self.files.remove(file_id) {
+                self.remove_outboard(file_id)?;
+                removed.push(metadata);
+            }
         }
         Ok(removed)
     }
     
-    /// List all registered files
-    pub fn list_files(&self) -> Vec<&FileMetadata> {
-        self.files.values().collect()
+    /// Pin an entry so it cannot be removed, replacing any existing pin
+    pub fn pin(&mut self, file_id: &FileId, mode: PinMode) -> FileRegistryResult<FileMetadata> {
+        self.update_file(file_id, |metadata| metadata.pin = Some(mode)).cloned()
     }
     
-    /// Get files count
-    pub fn file_count(&self) -> usize {
-        self.files.len()
+    /// Remove an entry's pin, returning the mode it was pinned with. An
+    /// entry held only by a recursive pin above it cannot be unpinned on
+    /// its own.
+    pub fn unpin(&mut self, file_id: &FileId) -> FileRegistryResult<Option<PinMode>> {
+        self.refresh()?;
+        let previous = self.files.get(file_id)
+            .ok_or(FileRegistryError::FileNotFound(*file_id))?
+            .pin;
+        match previous {
+            Some(_) => {
+                self.update_file(file_id, |metadata| metadata.pin = None)?;
+            }
+            None => {
+                if let Some(holder) = self.pin_holder_of(std::slice::from_ref(file_id)) {
+                    return Err(FileRegistryError::Pinned(holder.path.clone()));
+                }
+            }
+        }
+        Ok(previous)
     }
     
-    /// Find files by original name (partial match)
-    pub fn find_files_by_name(&self, name_pattern: &str) -> Vec<&FileMetadata> {
+    /// Entries that cannot be removed, each with the pinned entry holding
+    /// it: pinned entries hold themselves, and a recursive pin holds every
+    /// entry below its logical path
+    pub fn pin_holders(&self) -> HashMap<FileId, &FileMetadata> {
+        let recursive: Vec<(String, &FileMetadata)> = self.files
+            .values()
+            .filter(|metadata| metadata.pin == Some(PinMode::Recursive))
+            .map(|metadata| (format!("{}/", metadata.path), metadata))
+            .collect();
         self.files
             .values()
-            .filter(|metadata| metadata.original_name.contains(name_pattern))
+            .filter_map(|metadata| {
+                let holder = if metadata.is_pinned() {
+                    metadata
+                } else {
+                    recursive.iter().find(|(prefix, _)| metadata.path.starts_with(prefix.as_str()))?.1
+                };
+                Some((metadata.id, holder))
+            })
             .collect()
     }
     
-    /// Get total size of all registered files
-    pub fn total_size(&self) -> u64 {
-        self.files.values().map(|f| f.total_size).sum()
+    /// The pinned entry holding the first of `file_ids` that is held
+    fn pin_holder_of(&self, file_ids: &[FileId]) -> Option<&FileMetadata> {
+        let holders = self.pin_holders();
+        file_ids.iter().find_map(|id| holders.get(id).copied())
+    }
+    
+    /// Registered entries that have not expired, as listings and reports see them
+    pub fn unexpired_files(&self) -> Vec<&FileMetadata> {
+        let expired = self.expired_ids(now_secs());
+        self.files.values().filter(|metadata| !expired.contains(&metadata.id)).collect()
+    }
+    
+    /// Entries past their expiry time that no pin holds, i.e. those
+    /// `expire` removes and listings leave out
+    pub fn expired_ids(&self, now: u64) -> HashSet<FileId> {
+        let holders = self.pin_holders();
+        self.files
+            .values()
+            .filter(|metadata| metadata.is_expired(now) && !holders.contains_key(&metadata.id))
+            .map(|metadata| metadata.id)
+            .collect()
+    }
+    
+    /// All pinned entries, ordered by path and version
+    pub fn pinned_files(&self) -> Vec<&FileMetadata> {
+        let mut pinned: Vec<&FileMetadata> = self.files.values().filter(|metadata| metadata.is_pinned()).collect();
+        pinned.sort_by(|a, b| a.path.cmp(&b.path).then(a.version.cmp(&b.version)));
+        pinned
     }
     
-    /// Save the registry to disk
-    fn save_registry(&self) -> FileRegistryResult<()> {
-        // Create parent directory if it doesn't exist
-        if let Some(parent) = self.registry_path.parent() {
-            fs::create_dir_all(parent)?;
+    /// Move a file to the trash, recording when it was deleted
+    pub fn move_to_trash(&mut self, file_id: &FileId, deleted_at: u64) -> FileRegistryResult<TrashEntry> {
+        self.refresh()?;
+        let metadata = self.files.get(file_id)
+            .ok_or(FileRegistryError::FileNotFound(*file_id))?
+            .clone();
+        if let Some(holder) = self.pin_holder_of(std::slice::from_ref(file_id)) {
+            return Err(FileRegistryError::Pinned(holder.path.clone()));
         }
+        let entry = TrashEntry { metadata, deleted_at };
         
-        let json = serde_json::to_string_pretty(&self.files)?;
-        fs::write(&self.registry_path, json)?;
-        Ok(())
+        self.transact(|txn| {
+            Self::remove_entry(txn, file_id)?;
+            Self::insert_trash_entry(txn, &entry)
+        })?;
+        self.files.remove(file_id);
+        self.trash.insert(*file_id, entry.clone());
+        Ok(entry)
     }
     
-    /// Load the registry from disk
-    fn load_registry<P: AsRef<Path>>(path: P) -> FileRegistryResult<HashMap<FileId, FileMetadata>> {
-        let content = fs::read_to_string(path)?;
-        if content.trim().is_empty() {
-            return Ok(HashMap::new());
+    /// Put a trashed file back. If its version number was reused while it
+    /// was in the trash, it comes back as the newest version of its path.
+    pub fn restore_from_trash(&mut self, file_id: &FileId) -> FileRegistryResult<FileMetadata> {
+        self.refresh()?;
+        let mut metadata = self.trash.get(file_id)
+            .ok_or(FileRegistryError::FileNotFound(*file_id))?
+            .metadata
+            .clone();
+        if self.get_version(&metadata.path, metadata.version).is_some() {
+            let latest = self.latest_version(&metadata.path).expect("path has a version");
+            metadata.parent = Some(latest.id);
+            metadata.version = latest.version + 1;
         }
         
-        let files: HashMap<FileId, FileMetadata> = serde_json::from_str(&content)
-            .map_err(|_| FileRegistryError::CorruptedRegistry)?;
-        
-        Ok(files)
+        self.transact(|txn| {
+            txn.open_table(TRASH)?.remove(file_id.as_u128())?;
+            Self::insert_entry(txn, &metadata)
+        })?;
+        self.trash.remove(file_id);
+        self.files.insert(*file_id, metadata.clone());
+        Ok(metadata)
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::TempDir;
     
-    #[test]
-    fn test_file_registry_creation() {
-        let temp_dir = TempDir::new().unwrap();
-        let registry = FileRegistry::new(temp_dir.path()).unwrap();
+    /// Permanently drop entries from the trash, returning them
+    pub fn purge_from_trash(&mut self, file_ids: &[FileId]) -> FileRegistryResult<Vec<TrashEntry>> {
+        self.refresh()?;
+        let file_ids: Vec<FileId> = file_ids.iter().filter(|id| self.trash.contains_key(id)).copied().collect();
+        if file_ids.is_empty() {
+            return Ok(Vec::new());
+        }
         
-        assert_eq!(registry.file_count(), 0);
-        assert!(registry.list_files().is_empty());
+        self.transact(|txn| {
+            let mut table = txn.open_table(TRASH)?;
+            for file_id in &file_ids {
+                table.remove(file_id.as_u128())?;
+            }
+            Ok(())
+        })?;
+        
+        let mut purged = Vec::new();
+        for file_id in file_ids {
+            self.remove_outboard(&file_id)?;
+            purged.extend(self.trash.remove(&file_id));
+        }
+        Ok(purged)
     }
     
-    #[test]
-    fn test_register_and_retrieve_file() {
-        let temp_dir = TempDir::new().unwrap();
-        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+    /// All trashed entries, most recently deleted first
+    pub fn trash_entries(&self) -> Vec<&TrashEntry> {
+        let mut entries: Vec<&TrashEntry> = self.trash.values().collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse((entry.deleted_at, entry.metadata.id)));
+        entries
+    }
+    
+    /// Get a trashed entry by ID
+    pub fn get_trashed(&self, file_id: &FileId) -> Option<&TrashEntry> {
+        self.trash.get(file_id)
+    }
+    
+    /// Resolve an abbreviated file ID, or failing that a logical path (the
+    /// most recently deleted entry with that path), to a trashed entry
+    pub fn resolve_trashed(&self, reference: &str) -> FileRegistryResult<&TrashEntry> {
+        let prefix = reference.replace('-', "").to_ascii_lowercase();
+        let is_hex = !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_hexdigit());
+        let mut matches: Vec<&TrashEntry> = self.trash
+            .values()
+            .filter(|entry| is_hex && entry.metadata.id_key().starts_with(&prefix))
+            .collect();
+        matches.sort_by_key(|entry| entry.metadata.id);
         
-        let addresses = vec![]; // Empty for test
-        let metadata = registry.register_file(
-            "test.txt".to_string(),
-            addresses,
-            1024
-        ).unwrap();
+        match matches.as_slice() {
+            [] => self.trash_entries()
+                .into_iter()
+                .find(|entry| entry.metadata.path == reference)
+                .ok_or_else(|| FileRegistryError::NoMatch(reference.to_string())),
+            [entry] => Ok(entry),
+            candidates => Err(FileRegistryError::AmbiguousPrefix {
+                prefix: reference.to_string(),
+                candidates: candidates
+                    .iter()
+                    .map(|entry| format!("{} ({})", entry.metadata.id, entry.metadata.original_name))
+                    .collect(),
+            }),
+        }
+    }
+    
+    /// Store the outboard tree for a file and record its root in the metadata
+    pub fn save_outboard(&mut self, file_id: &FileId, outboard: &Outboard) -> FileRegistryResult<()> {
+        self.refresh()?;
+        if !self.files.contains_key(file_id) {
+            return Err(FileRegistryError::FileNotFound(*file_id));
+        }
         
-        assert_eq!(registry.file_count(), 1);
+        fs::create_dir_all(&self.outboards_dir)?;
+        write_atomic(self.outboard_path(file_id), &outboard.data)?;
         
-        let retrieved = registry.get_file(&metadata.id).unwrap();
-        assert_eq!(retrieved.original_name, "test.txt");
-        assert_eq!(retrieved.total_size, 1024);
+        let root = outboard.root.clone();
+        self.update_file(file_id, |metadata| metadata.stream_root = Some(root))?;
+        Ok(())
     }
     
-    #[test]
-    fn test_registry_persistence() {
-        let temp_dir = TempDir::new().unwrap();
-        let file_id;
-        
-        // Create and register a file
-        {
-            let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
-            let addresses = vec![]; // Empty for test
-            let metadata = registry.register_file(
-                "persistent.txt".to_string(),
-                addresses,
-                2048
-            ).unwrap();
-            file_id = metadata.id;
+    /// Load the outboard tree stored for a file, if any
+    pub fn load_outboard(&self, file_id: &FileId) -> FileRegistryResult<Option<Vec<u8>>> {
+        let path = self.outboard_path(file_id);
+        if !path.exists() {
+            return Ok(None);
         }
-        
+        Ok(Some(fs::read(path)?))
+    }
+    
+    fn remove_outboard(&self, file_id: &FileId) -> FileRegistryResult<()> {
+        let path = self.outboard_path(file_id);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+    
+    fn outboard_path(&self, file_id: &FileId) -> PathBuf {
+        self.outboards_dir.join(format!("{}.obao", file_id))
+    }
+    
+    /// List all registered files
+    pub fn list_files(&self) -> Vec<&FileMetadata> {
+        self.files.values().collect()
+    }
+    
+    /// Get files count
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+    
+    /// Find files by original name (partial match)
+    pub fn find_files_by_name(&self, name_pattern: &str) -> Vec<&FileMetadata> {
+        self.files
+            .values()
+            .filter(|metadata| metadata.original_name.contains(name_pattern))
+            .collect()
+    }
+    
+    /// Find files matching all criteria of a filter, skipping expired ones
+    pub fn find_files(&self, filter: &FileFilter) -> Vec<&FileMetadata> {
+        let expired = self.expired_ids(now_secs());
+        self.files
+            .values()
+            .filter(|metadata| !expired.contains(&metadata.id) && filter.matches(metadata))
+            .collect()
+    }
+    
+    /// Find files matching a parsed query, in no particular order, skipping
+    /// expired ones. Queries bounding the creation time only look at files
+    /// the creation time index places in range.
+    pub fn search(&self, query: &Query) -> FileRegistryResult<Vec<&FileMetadata>> {
+        let expired = self.expired_ids(now_secs());
+        let candidates = match query.created_range() {
+            Some(range) => self.files_created_between(range.start, range.end)?,
+            None => self.files.values().collect(),
+        };
+        Ok(candidates
+            .into_iter()
+            .filter(|metadata| !expired.contains(&metadata.id) && query.matches(metadata))
+            .collect())
+    }
+    
+    /// Find files carrying a tag
+    pub fn find_files_by_tag(&self, tag: &str) -> Vec<&FileMetadata> {
+        self.files
+            .values()
+            .filter(|metadata| metadata.tags.contains(tag))
+            .collect()
+    }
+    
+    /// Find files with an attribute set to a specific value
+    pub fn find_files_by_attribute(&self, key: &str, value: &str) -> Vec<&FileMetadata> {
+        self.files
+            .values()
+            .filter(|metadata| metadata.attributes.get(key).map(String::as_str) == Some(value))
+            .collect()
+    }
+    
+    /// Add and remove tags on a file
+    pub fn update_tags(&mut self, file_id: &FileId, add: &[String], remove: &[String]) -> FileRegistryResult<&FileMetadata> {
+        self.update_file(file_id, |metadata| {
+            metadata.tags.extend(add.iter().cloned());
+            for tag in remove {
+                metadata.tags.remove(tag);
+            }
+        })
+    }
+    
+    /// Set and unset attributes on a file
+    pub fn update_attributes(
+        &mut self,
+        file_id: &FileId,
+        set: &[(String, String)],
+        unset: &[String],
+    ) -> FileRegistryResult<&FileMetadata> {
+        self.update_file(file_id, |metadata| {
+            metadata.attributes.extend(set.iter().cloned());
+            for key in unset {
+                metadata.attributes.remove(key);
+            }
+        })
+    }
+    
+    /// Get total size of all registered files
+    pub fn total_size(&self) -> u64 {
+        self.files.values().map(|f| f.total_size).sum()
+    }
+    
+    /// Files whose original name is exactly `name`, using the name index
+    pub fn files_named(&self, name: &str) -> FileRegistryResult<Vec<&FileMetadata>> {
+        let txn = self.database.begin_read()?;
+        let index = txn.open_multimap_table(NAME_INDEX)?;
+        
+        let mut files = Vec::new();
+        for id in index.get(name)? {
+            if let Some(metadata) = self.files.get(&Uuid::from_u128(id?.value())) {
+                files.push(metadata);
+            }
+        }
+        Ok(files)
+    }
+    
+    /// Files whose whole-file digest is `digest`, using the digest index
+    pub fn files_with_digest(&self, digest: &str) -> FileRegistryResult<Vec<&FileMetadata>> {
+        let txn = self.database.begin_read()?;
+        let index = txn.open_multimap_table(DIGEST_INDEX)?;
+        
+        let mut files = Vec::new();
+        for id in index.get(digest)? {
+            if let Some(metadata) = self.files.get(&Uuid::from_u128(id?.value())) {
+                files.push(metadata);
+            }
+        }
+        Ok(files)
+    }
+    
+    /// Files whose chunk list includes `address`, using the chunk index
+    pub fn files_with_chunk(&self, address: &ContentAddress) -> FileRegistryResult<Vec<&FileMetadata>> {
+        let txn = self.database.begin_read()?;
+        let index = txn.open_multimap_table(CHUNK_INDEX)?;
+        
+        let mut files = Vec::new();
+        for id in index.get(address.to_hex().as_str())? {
+            if let Some(metadata) = self.files.get(&Uuid::from_u128(id?.value())) {
+                files.push(metadata);
+            }
+        }
+        Ok(files)
+    }
+    
+    /// Non-empty files with identical content, grouped by digest. Groups are
+    /// ordered by the space their extra copies take up, largest first, and
+    /// each group is ordered oldest first.
+    pub fn duplicate_groups(&self) -> Vec<Vec<&FileMetadata>> {
+        let mut by_digest: HashMap<&str, Vec<&FileMetadata>> = HashMap::new();
+        for metadata in self.unexpired_files().into_iter().filter(|metadata| metadata.total_size > 0) {
+            if let Some(digest) = &metadata.digest {
+                by_digest.entry(digest.as_str()).or_default().push(metadata);
+            }
+        }
+        
+        let mut groups: Vec<Vec<&FileMetadata>> = by_digest
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .collect();
+        for group in &mut groups {
+            group.sort_by_key(|metadata| (metadata.created_at, metadata.path.clone(), metadata.version));
+        }
+        groups.sort_by_key(|group| {
+            let redundant = group[0].total_size * (group.len() as u64 - 1);
+            (std::cmp::Reverse(redundant), group[0].digest.clone())
+        });
+        groups
+    }
+    
+    /// Files created in `[start, end)`, oldest first, using the creation time index
+    pub fn files_created_between(&self, start: u64, end: u64) -> FileRegistryResult<Vec<&FileMetadata>> {
+        if start >= end {
+            return Ok(Vec::new());
+        }
+        let txn = self.database.begin_read()?;
+        let index = txn.open_multimap_table(CREATED_INDEX)?;
+        
+        let mut files = Vec::new();
+        for entry in index.range(start..end)? {
+            let (_, ids) = entry?;
+            for id in ids {
+                if let Some(metadata) = self.files.get(&Uuid::from_u128(id?.value())) {
+                    files.push(metadata);
+                }
+            }
+        }
+        Ok(files)
+    }
+    
+    /// Write `upserts` and delete `removals` in one transaction, keeping the
+    /// indexes in step with the stored entries
+    fn persist(&mut self, upserts: &[FileMetadata], removals: &[FileId]) -> FileRegistryResult<()> {
+        self.transact(|txn| {
+            for metadata in upserts {
+                Self::remove_entry(txn, &metadata.id)?;
+                Self::insert_entry(txn, metadata)?;
+            }
+            for file_id in removals {
+                Self::remove_entry(txn, file_id)?;
+            }
+            Ok(())
+        })
+    }
+    
+    /// Run `changes` in a single write transaction and commit it
+    fn transact<F>(&mut self, changes: F) -> FileRegistryResult<()>
+    where
+        F: FnOnce(&WriteTransaction) -> FileRegistryResult<()>,
+    {
+        let txn = self.database.begin_write()?;
+        let generation = {
+            let mut meta = txn.open_table(META)?;
+            let generation = Self::read_generation(&meta)?;
+            if generation != self.generation {
+                return Err(FileRegistryError::ConcurrentChange);
+            }
+            meta.insert(GENERATION, generation + 1)?;
+            generation + 1
+        };
+        changes(&txn)?;
+        txn.commit()?;
+        self.generation = generation;
+        
+        // Every commit is backed up so recovery loses nothing that was
+        // committed. The change stands either way, so a failed backup is
+        // only worth a warning; the next change tries again.
+        if let Err(e) = self.write_backup() {
+            eprintln!("Warning: failed to write registry backup: {}", e);
+        }
+        Ok(())
+    }
+    
+    fn insert_trash_entry(txn: &WriteTransaction, entry: &TrashEntry) -> FileRegistryResult<()> {
+        let bytes = serde_json::to_vec(entry)?;
+        txn.open_table(TRASH)?.insert(entry.metadata.id.as_u128(), bytes.as_slice())?;
+        Ok(())
+    }
+    
+    fn insert_entry(txn: &WriteTransaction, metadata: &FileMetadata) -> FileRegistryResult<()> {
+        let key = metadata.id.as_u128();
+        let bytes = serde_json::to_vec(metadata)?;
+        txn.open_table(FILES)?.insert(key, bytes.as_slice())?;
+        txn.open_multimap_table(NAME_INDEX)?.insert(metadata.original_name.as_str(), key)?;
+        txn.open_multimap_table(CREATED_INDEX)?.insert(metadata.created_at, key)?;
+        if let Some(digest) = &metadata.digest {
+            txn.open_multimap_table(DIGEST_INDEX)?.insert(digest.as_str(), key)?;
+        }
+        let mut chunks = txn.open_multimap_table(CHUNK_INDEX)?;
+        for address in &metadata.chunk_addresses {
+            chunks.insert(address.to_hex().as_str(), key)?;
+        }
+        Ok(())
+    }
+    
+    fn remove_entry(txn: &WriteTransaction, file_id: &FileId) -> FileRegistryResult<()> {
+        let key = file_id.as_u128();
+        let previous = match txn.open_table(FILES)?.remove(key)? {
+            Some(previous) => serde_json::from_slice::<FileMetadata>(previous.value())
+                .map_err(|_| FileRegistryError::CorruptedRegistry)?,
+            None => return Ok(()),
+        };
+        txn.open_multimap_table(NAME_INDEX)?.remove(previous.original_name.as_str(), key)?;
+        txn.open_multimap_table(CREATED_INDEX)?.remove(previous.created_at, key)?;
+        if let Some(digest) = &previous.digest {
+            txn.open_multimap_table(DIGEST_INDEX)?.remove(digest.as_str(), key)?;
+        }
+        let mut chunks = txn.open_multimap_table(CHUNK_INDEX)?;
+        for address in &previous.chunk_addresses {
+            chunks.remove(address.to_hex().as_str(), key)?;
+        }
+        Ok(())
+    }
+    
+    /// Load every live and trashed entry from the database
+    fn load_registry(&mut self) -> FileRegistryResult<()> {
+        let (contents, generation) = self.read_database()?;
+        self.files = contents.files;
+        self.trash = contents.trash;
+        self.generation = generation;
+        Ok(())
+    }
+    
+    /// Every committed live and trashed entry, read from the database
+    /// rather than the cache
+    fn read_contents(&self) -> FileRegistryResult<RegistryContents> {
+        Ok(self.read_database()?.0)
+    }
+    
+    fn read_database(&self) -> FileRegistryResult<(RegistryContents, u64)> {
+        let txn = self.database.begin_read()?;
+        let generation = Self::read_generation(&txn.open_table(META)?)?;
+        
+        let table = txn.open_table(FILES)?;
+        let mut files = HashMap::with_capacity(table.len()? as usize);
+        for entry in table.iter()? {
+            let (_, value) = entry?;
+            let metadata: FileMetadata = serde_json::from_slice(value.value())
+                .map_err(|_| FileRegistryError::CorruptedRegistry)?;
+            files.insert(metadata.id, metadata);
+        }
+        
+        let mut trash = HashMap::new();
+        for entry in txn.open_table(TRASH)?.iter()? {
+            let (_, value) = entry?;
+            let entry: TrashEntry = serde_json::from_slice(value.value())
+                .map_err(|_| FileRegistryError::CorruptedRegistry)?;
+            trash.insert(entry.metadata.id, entry);
+        }
+        
+        Ok((RegistryContents { files, trash }, generation))
+    }
+    
+    /// Import a registry written by earlier versions as a single JSON file.
+    /// The JSON file is kept, renamed, in case the migration needs redoing.
+    /// One that does not parse is left in place and reported, rather than
+    /// mistaken for a damaged database.
+    fn migrate_from_json(&mut self, path: &Path) -> FileRegistryResult<()> {
+        let contents = Self::parse_json_registry(&fs::read_to_string(path)?)
+            .map_err(|_| FileRegistryError::CorruptedLegacyRegistry(path.to_path_buf()))?;
+        let entries: Vec<FileMetadata> = contents.files.into_values().collect();
+        self.persist(&entries, &[])?;
+        
+        fs::rename(path, path.with_extension("json.migrated"))?;
+        Ok(())
+    }
+    
+    /// Parse a whole-registry JSON document: a backup, or a legacy
+    /// registry that is a bare map of file entries
+    fn parse_json_registry(content: &str) -> FileRegistryResult<RegistryContents> {
+        if content.trim().is_empty() {
+            return Ok(RegistryContents::default());
+        }
+        
+        let mut contents = match serde_json::from_str::<RegistryContents>(content) {
+            Ok(contents) => contents,
+            Err(_) => RegistryContents {
+                files: serde_json::from_str(content).map_err(|_| FileRegistryError::CorruptedRegistry)?,
+                trash: HashMap::new(),
+            },
+        };
+        Self::upgrade_legacy_versions(&mut contents.files);
+        Ok(contents)
+    }
+    
+    /// Entries written before versioning have no path or version number.
+    /// Give them their original name as path and chain them by creation time.
+    fn upgrade_legacy_versions(files: &mut HashMap<FileId, FileMetadata>) {
+        let mut legacy: Vec<FileId> = files
+            .values()
+            .filter(|metadata| metadata.version == 0)
+            .map(|metadata| metadata.id)
+            .collect();
+        legacy.sort_by_key(|id| (files[id].created_at, *id));
+        
+        for file_id in legacy {
+            let path = match files[&file_id].path.as_str() {
+                "" => files[&file_id].original_name.clone(),
+                path => path.to_string(),
+            };
+            let latest = files
+                .values()
+                .filter(|metadata| metadata.path == path && metadata.version > 0)
+                .max_by_key(|metadata| metadata.version)
+                .map(|metadata| (metadata.id, metadata.version));
+            
+            let metadata = files.get_mut(&file_id).unwrap();
+            metadata.path = path;
+            metadata.version = latest.map_or(1, |(_, version)| version + 1);
+            metadata.parent = latest.map(|(id, _)| id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_file_registry_creation() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = FileRegistry::new(temp_dir.path()).unwrap();
+        
+        assert_eq!(registry.file_count(), 0);
+        assert!(registry.list_files().is_empty());
+    }
+    
+    #[test]
+    fn test_register_and_retrieve_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        
+        let addresses = vec![]; // Empty for test
+        let metadata = registry.register_file(
+            "test.txt".to_string(),
+            addresses,
+            1024
+        ).unwrap();
+        
+        assert_eq!(registry.file_count(), 1);
+        
+        let retrieved = registry.get_file(&metadata.id).unwrap();
+        assert_eq!(retrieved.original_name, "test.txt");
+        assert_eq!(retrieved.total_size, 1024);
+    }
+    
+    #[test]
+    fn test_registry_persistence() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_id;
+        
+        // Create and register a file
+        {
+            let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+            let addresses = vec![]; // Empty for test
+            let metadata = registry.register_file(
+                "persistent.txt".to_string(),
+                addresses,
+                2048
+            ).unwrap();
+            file_id = metadata.id;
+        }
+        
         // Create a new registry instance and verify the file persisted
         {
             let registry = FileRegistry::new(temp_dir.path()).unwrap();
@@ -231,4 +1498,545 @@ mod tests {
             assert_eq!(retrieved.total_size, 2048);
         }
     }
+    
+    #[test]
+    fn test_outboard_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        let metadata = registry.register_file("stream.bin".to_string(), vec![], 4096).unwrap();
+        
+        let outboard = Outboard::build(&[7u8; 4096][..]).unwrap();
+        registry.save_outboard(&metadata.id, &outboard).unwrap();
+        
+        assert_eq!(registry.get_file(&metadata.id).unwrap().stream_root, Some(outboard.root.clone()));
+        assert_eq!(registry.load_outboard(&metadata.id).unwrap(), Some(outboard.data.clone()));
+        
+        registry.remove_file(&metadata.id).unwrap();
+        assert_eq!(registry.load_outboard(&metadata.id).unwrap(), None);
+    }
+    
+    #[test]
+    fn test_prefix_resolution() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        
+        let first = registry.register_file("a.txt".to_string(), vec![], 1).unwrap();
+        let second = registry.register_file("b.txt".to_string(), vec![], 2).unwrap();
+        
+        // Full IDs, with or without dashes, resolve exactly
+        assert_eq!(registry.resolve_prefix(&first.id.to_string()).unwrap().id, first.id);
+        assert_eq!(registry.resolve_prefix(&second.id_key()).unwrap().id, second.id);
+        
+        // The shortest unique prefix resolves back to the same file
+        let short = registry.short_id(&first.id);
+        assert!(short.len() >= MIN_ID_PREFIX_LEN);
+        assert_eq!(registry.resolve_prefix(&short).unwrap().id, first.id);
+        
+        assert!(matches!(registry.resolve_prefix("zz"), Err(FileRegistryError::NoMatch(_))));
+    }
+    
+    #[test]
+    fn test_ambiguous_prefix_lists_candidates() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        
+        // With 17 files, at least two IDs must share their first hex digit
+        for i in 0..17 {
+            registry.register_file(format!("file{}.txt", i), vec![], i).unwrap();
+        }
+        let shared = (0..16)
+            .map(|digit| format!("{:x}", digit))
+            .find(|digit| registry.files_with_prefix(digit).len() > 1)
+            .unwrap();
+        
+        match registry.resolve_prefix(&shared) {
+            Err(FileRegistryError::AmbiguousPrefix { candidates, .. }) => assert!(candidates.len() > 1),
+            other => panic!("Expected ambiguity error, got {:?}", other.map(|m| m.id)),
+        }
+    }
+    
+    #[test]
+    fn test_versions_link_to_parent() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        
+        let v1 = registry.register_file("report.pdf".to_string(), vec![], 10).unwrap();
+        let v2 = registry.register_file("report.pdf".to_string(), vec![], 20).unwrap();
+        let other = registry.register_file_at("docs/report.pdf", "report.pdf".to_string(), vec![], 30).unwrap();
+        
+        assert_eq!((v1.version, v1.parent), (1, None));
+        assert_eq!((v2.version, v2.parent), (2, Some(v1.id)));
+        assert_eq!((other.version, other.parent), (1, None));
+        
+        assert_eq!(registry.versions("report.pdf").len(), 2);
+        assert_eq!(registry.latest_version("report.pdf").unwrap().id, v2.id);
+        assert_eq!(registry.get_version("report.pdf", 1).unwrap().id, v1.id);
+        assert_eq!(registry.version_at("report.pdf", v1.created_at).unwrap().id, v2.id);
+        assert!(registry.version_at("report.pdf", v1.created_at - 1).is_none());
+    }
+    
+    #[test]
+    fn test_prune_versions() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        
+        for size in 0..5 {
+            registry.register_file("log.txt".to_string(), vec![], size).unwrap();
+        }
+        
+        let policy = RetentionPolicy { keep_last: Some(2), keep_within: None };
+        let removed = registry.prune_versions("log.txt", &policy).unwrap();
+        assert_eq!(removed.len(), 3);
+        
+        let remaining: Vec<u32> = registry.versions("log.txt").iter().map(|m| m.version).collect();
+        assert_eq!(remaining, vec![4, 5]);
+    }
+    
+    #[test]
+    fn test_legacy_entries_get_versions() {
+        let temp_dir = TempDir::new().unwrap();
+        let legacy = r#"{
+            "6f1c2a8e-9d1b-4d3a-8a51-1f6a4f2e9b01": {
+                "id": "6f1c2a8e-9d1b-4d3a-8a51-1f6a4f2e9b01", "original_name": "a.txt",
+                "chunk_addresses": [], "total_size": 1, "created_at": 100, "chunk_count": 0
+            },
+            "0b7d3c4e-5f6a-4b8c-9d0e-1f2a3b4c5d6e": {
+                "id": "0b7d3c4e-5f6a-4b8c-9d0e-1f2a3b4c5d6e", "original_name": "a.txt",
+                "chunk_addresses": [], "total_size": 2, "created_at": 200, "chunk_count": 0
+            }
+        }"#;
+        fs::write(temp_dir.path().join("file_registry.json"), legacy).unwrap();
+        
+        let registry = FileRegistry::new(temp_dir.path()).unwrap();
+        let versions = registry.versions("a.txt");
+        assert_eq!(versions.len(), 2);
+        assert_eq!((versions[0].total_size, versions[0].version), (1, 1));
+        assert_eq!((versions[1].total_size, versions[1].version), (2, 2));
+        assert_eq!(versions[1].parent, Some(versions[0].id));
+    }
+    
+    #[test]
+    fn test_tags_and_attributes() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        
+        let a = registry.register_file("a.log".to_string(), vec![], 1).unwrap();
+        let b = registry.register_file("b.log".to_string(), vec![], 2).unwrap();
+        
+        registry.update_tags(&a.id, &["prod".to_string(), "ci".to_string()], &[]).unwrap();
+        registry.update_tags(&b.id, &["prod".to_string()], &[]).unwrap();
+        registry.update_tags(&a.id, &[], &["ci".to_string()]).unwrap();
+        registry.update_attributes(&b.id, &[("owner".to_string(), "ops".to_string())], &[]).unwrap();
+        
+        assert_eq!(registry.find_files_by_tag("prod").len(), 2);
+        assert!(registry.find_files_by_tag("ci").is_empty());
+        assert_eq!(registry.find_files_by_attribute("owner", "ops")[0].id, b.id);
+        
+        let filter = FileFilter {
+            tags: vec!["prod".to_string()],
+            attributes: vec![("owner".to_string(), "ops".to_string())],
+            ..Default::default()
+        };
+        let found = registry.find_files(&filter);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, b.id);
+        
+        // Edits persist
+        let reloaded = FileRegistry::new(temp_dir.path()).unwrap();
+        assert_eq!(reloaded.get_file(&b.id).unwrap().attributes["owner"], "ops");
+    }
+    
+    #[test]
+    fn test_migration_from_json() {
+        let temp_dir = TempDir::new().unwrap();
+        let legacy_path = temp_dir.path().join("file_registry.json");
+        let metadata = FileMetadata::new("old.txt".to_string(), vec![], 7);
+        let files = HashMap::from([(metadata.id, metadata.clone())]);
+        fs::write(&legacy_path, serde_json::to_string_pretty(&files).unwrap()).unwrap();
+        
+        let registry = FileRegistry::new(temp_dir.path()).unwrap();
+        assert_eq!(registry.get_file(&metadata.id).unwrap().total_size, 7);
+        assert!(!legacy_path.exists());
+        assert!(temp_dir.path().join("file_registry.json.migrated").exists());
+        
+        // The database is now the source of truth
+        drop(registry);
+        let reopened = FileRegistry::new(temp_dir.path()).unwrap();
+        assert_eq!(reopened.file_count(), 1);
+        assert_eq!(reopened.files_named("old.txt").unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn test_name_and_creation_indexes() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        
+        let a = registry.register_file("a.txt".to_string(), vec![], 1).unwrap();
+        let b = registry.register_file("b.txt".to_string(), vec![], 2).unwrap();
+        registry.update_file(&a.id, |metadata| metadata.created_at = 100).unwrap();
+        registry.update_file(&b.id, |metadata| metadata.created_at = 200).unwrap();
+        
+        assert_eq!(registry.files_named("a.txt").unwrap()[0].id, a.id);
+        assert!(registry.files_named("a").unwrap().is_empty());
+        
+        let created: Vec<FileId> = registry.files_created_between(0, 300).unwrap().iter().map(|f| f.id).collect();
+        assert_eq!(created, vec![a.id, b.id]);
+        assert_eq!(registry.files_created_between(150, 300).unwrap().len(), 1);
+        
+        // Stale index entries are dropped on update and removal
+        assert!(registry.files_created_between(300, u64::MAX).unwrap().is_empty());
+        registry.remove_file(&b.id).unwrap();
+        assert!(registry.files_named("b.txt").unwrap().is_empty());
+        assert!(registry.files_created_between(150, 300).unwrap().is_empty());
+        
+        // Queries bounding the creation time go through the index
+        let old: Vec<FileId> = registry.search(&"created<150".parse().unwrap()).unwrap().iter().map(|f| f.id).collect();
+        assert_eq!(old, vec![a.id]);
+        assert!(registry.search(&"created>150 created<100".parse().unwrap()).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_chunk_index_tracks_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        let shared = ContentAddress::from_data(b"shared");
+        let own = ContentAddress::from_data(b"own");
+        
+        let a = registry.register_file("a.bin".to_string(), vec![shared.clone(), own.clone()], 2).unwrap();
+        let b = registry.register_file("b.bin".to_string(), vec![shared.clone(), shared.clone()], 2).unwrap();
+        let users = |registry: &FileRegistry, address| -> BTreeSet<FileId> {
+            registry.files_with_chunk(address).unwrap().iter().map(|f| f.id).collect()
+        };
+        assert_eq!(users(&registry, &shared), BTreeSet::from([a.id, b.id]));
+        assert_eq!(users(&registry, &own), BTreeSet::from([a.id]));
+        
+        registry.remove_file(&b.id).unwrap();
+        assert_eq!(users(&registry, &shared), BTreeSet::from([a.id]));
+        
+        // A database from before the index existed is indexed on open
+        let txn = registry.database.begin_write().unwrap();
+        txn.delete_multimap_table(CHUNK_INDEX).unwrap();
+        txn.commit().unwrap();
+        drop(registry);
+        let registry = FileRegistry::new(temp_dir.path()).unwrap();
+        assert_eq!(users(&registry, &own), BTreeSet::from([a.id]));
+    }
+    
+    #[test]
+    fn test_registry_is_locked_by_other_handles() {
+        let temp_dir = TempDir::new().unwrap();
+        FileRegistry::new(temp_dir.path()).unwrap().register_file("held.txt".to_string(), vec![], 1).unwrap();
+        
+        // Stands in for another process holding the database
+        let other = Database::create(temp_dir.path().join("file_registry.redb")).unwrap();
+        assert!(matches!(FileRegistry::new(temp_dir.path()), Err(FileRegistryError::Locked(_))));
+        drop(other);
+        
+        // A held database is not a damaged one: nothing is set aside or recovered
+        let registry = FileRegistry::new(temp_dir.path()).unwrap();
+        assert!(registry.recovery().is_none());
+        assert_eq!(registry.file_count(), 1);
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+    }
+    
+    #[test]
+    fn test_backups_follow_committed_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        let newest = |dir: &Path| {
+            let json = fs::read_to_string(dir.join("registry_backups/file_registry.json.1")).unwrap();
+            FileRegistry::parse_json_registry(&json).unwrap().files.len()
+        };
+        
+        // Every committed change is backed up straight away, without
+        // waiting for the registry to be dropped
+        for i in 1..=5 {
+            registry.register_file(format!("{}.txt", i), vec![], 1).unwrap();
+            assert_eq!(newest(temp_dir.path()), i);
+        }
+    }
+    
+    #[test]
+    fn test_registries_in_one_process_see_each_others_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut first = FileRegistry::new(temp_dir.path()).unwrap();
+        let mut second = FileRegistry::new(temp_dir.path()).unwrap();
+        
+        let v1 = first.register_file("a.txt".to_string(), vec![], 1).unwrap();
+        let v2 = second.register_file("a.txt".to_string(), vec![], 2).unwrap();
+        assert_eq!((v2.version, v2.parent), (2, Some(v1.id)));
+        
+        first.update_tags(&v2.id, &["seen".to_string()], &[]).unwrap();
+        assert_eq!(first.file_count(), 2);
+        second.update_tags(&v1.id, &["also".to_string()], &[]).unwrap();
+        assert!(second.get_file(&v2.id).unwrap().tags.contains("seen"));
+    }
+    
+    #[test]
+    fn test_backup_generations_rotate() {
+        let temp_dir = TempDir::new().unwrap();
+        
+        for i in 0..REGISTRY_BACKUP_GENERATIONS + 2 {
+            let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+            registry.register_file(format!("{}.txt", i), vec![], 1).unwrap();
+        }
+        
+        let backups = temp_dir.path().join("registry_backups");
+        assert_eq!(fs::read_dir(&backups).unwrap().count(), REGISTRY_BACKUP_GENERATIONS);
+        
+        // The newest generation holds every entry, older ones one fewer each
+        let newest = FileRegistry::parse_json_registry(
+            &fs::read_to_string(backups.join("file_registry.json.1")).unwrap()
+        ).unwrap();
+        let older = FileRegistry::parse_json_registry(
+            &fs::read_to_string(backups.join("file_registry.json.2")).unwrap()
+        ).unwrap();
+        assert_eq!(newest.files.len(), REGISTRY_BACKUP_GENERATIONS + 2);
+        assert_eq!(older.files.len(), REGISTRY_BACKUP_GENERATIONS + 1);
+    }
+    
+    #[test]
+    fn test_recover_from_corrupted_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let kept = {
+            let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+            registry.register_file("kept.txt".to_string(), vec![], 5).unwrap()
+        };
+        
+        // Damage the newest backup too; recovery must skip to an older one
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        registry.register_file("later.txt".to_string(), vec![], 6).unwrap();
+        drop(registry);
+        fs::write(temp_dir.path().join("registry_backups/file_registry.json.1"), b"{ truncated").unwrap();
+        fs::write(temp_dir.path().join("file_registry.redb"), b"not a database").unwrap();
+        
+        let registry = FileRegistry::new(temp_dir.path()).unwrap();
+        let recovery = registry.recovery().expect("registry should report recovery");
+        assert_eq!(recovery.files, 1);
+        assert!(recovery.backup.ends_with("file_registry.json.2"));
+        assert!(recovery.moved_to.exists());
+        assert_eq!(registry.get_file(&kept.id).unwrap().original_name, "kept.txt");
+    }
+    
+    #[test]
+    fn test_corrupt_legacy_registry_leaves_database_alone() {
+        let temp_dir = TempDir::new().unwrap();
+        let kept = {
+            let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+            registry.register_file("kept.txt".to_string(), vec![], 5).unwrap()
+        };
+        let legacy_path = temp_dir.path().join("file_registry.json");
+        fs::write(&legacy_path, b"{ truncated").unwrap();
+        
+        // Every attempt fails the same way, and the healthy database stays put
+        for _ in 0..2 {
+            let result = FileRegistry::new(temp_dir.path());
+            assert!(matches!(result, Err(FileRegistryError::CorruptedLegacyRegistry(ref path)) if *path == legacy_path));
+        }
+        let names: Vec<_> = fs::read_dir(temp_dir.path()).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert!(!names.iter().any(|name| name.contains("corrupted")));
+        
+        fs::remove_file(&legacy_path).unwrap();
+        let registry = FileRegistry::new(temp_dir.path()).unwrap();
+        assert!(registry.recovery().is_none());
+        assert!(registry.get_file(&kept.id).is_some());
+    }
+    
+    #[test]
+    fn test_corruption_without_backup_is_an_error() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("file_registry.redb"), b"not a database").unwrap();
+        
+        assert!(FileRegistry::new(temp_dir.path()).is_err());
+    }
+    
+    #[test]
+    fn test_trash_and_restore() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        
+        let v1 = registry.register_file("doc.txt".to_string(), vec![], 1).unwrap();
+        let entry = registry.move_to_trash(&v1.id, 500).unwrap();
+        assert_eq!(entry.deleted_at, 500);
+        assert!(registry.get_file(&v1.id).is_none());
+        assert_eq!(registry.trash_entries().len(), 1);
+        
+        // Trash survives a reopen and is included in backups
+        drop(registry);
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        assert_eq!(registry.resolve_trashed("doc.txt").unwrap().metadata.id, v1.id);
+        assert_eq!(registry.contents().trash.len(), 1);
+        
+        // The version number was reused meanwhile, so the restored entry moves up
+        let replacement = registry.register_file("doc.txt".to_string(), vec![], 2).unwrap();
+        assert_eq!(replacement.version, 1);
+        let restored = registry.restore_from_trash(&v1.id).unwrap();
+        assert_eq!((restored.version, restored.parent), (2, Some(replacement.id)));
+        assert!(registry.trash_entries().is_empty());
+        assert_eq!(registry.file_count(), 2);
+    }
+    
+    #[test]
+    fn test_rename_moves_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        
+        let v1 = registry.register_file("draft.txt".to_string(), vec![], 1).unwrap();
+        let v2 = registry.register_file("draft.txt".to_string(), vec![], 2).unwrap();
+        let moved = registry.rename(&v1.id, "docs/final.txt", false).unwrap();
+        
+        assert_eq!(moved.len(), 2);
+        let renamed = registry.get_file(&v2.id).unwrap();
+        assert_eq!((renamed.path.as_str(), renamed.original_name.as_str()), ("docs/final.txt", "final.txt"));
+        assert_eq!((renamed.version, renamed.parent), (2, Some(v1.id)));
+        assert_eq!(renamed.renames[0].from, "draft.txt");
+        assert!(registry.versions("draft.txt").is_empty());
+        assert_eq!(registry.files_named("final.txt").unwrap().len(), 2);
+        assert!(registry.files_named("draft.txt").unwrap().is_empty());
+        
+        // The rename survives a reopen
+        drop(registry);
+        let registry = FileRegistry::new(temp_dir.path()).unwrap();
+        assert_eq!(registry.get_file(&v1.id).unwrap().path, "docs/final.txt");
+    }
+    
+    #[test]
+    fn test_rename_collisions() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        
+        let a = registry.register_file("a.txt".to_string(), vec![], 1).unwrap();
+        let b = registry.register_file("b.txt".to_string(), vec![], 2).unwrap();
+        let other = registry.register_file_at("other/c.txt", "c.txt".to_string(), vec![], 3).unwrap();
+        
+        assert!(matches!(
+            registry.rename(&a.id, "b.txt", true),
+            Err(FileRegistryError::NameCollision(name)) if name == "b.txt"
+        ));
+        assert!(matches!(registry.rename(&a.id, "c.txt", true), Err(FileRegistryError::NameCollision(_))));
+        assert!(matches!(registry.rename(&a.id, "docs//a.txt", false), Err(FileRegistryError::InvalidName(_))));
+        assert!(matches!(registry.rename(&a.id, "../x", false), Err(FileRegistryError::InvalidName(_))));
+        assert_eq!(registry.get_file(&a.id).unwrap().path, "a.txt");
+        
+        // Without --unique the moved file becomes the newest version of the target
+        let moved = registry.rename(&a.id, "b.txt", false).unwrap();
+        assert_eq!((moved[0].version, moved[0].parent), (2, Some(b.id)));
+        assert_eq!(registry.versions("b.txt").len(), 2);
+        assert_eq!(registry.get_file(&other.id).unwrap().path, "other/c.txt");
+    }
+    
+    #[test]
+    fn test_pins_block_removal() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        
+        let v1 = registry.register_file("a.txt".to_string(), vec![], 1).unwrap();
+        let v2 = registry.register_file("a.txt".to_string(), vec![], 2).unwrap();
+        registry.register_file("a.txt".to_string(), vec![], 3).unwrap();
+        registry.pin(&v1.id, PinMode::Direct).unwrap();
+        registry.update_file(&v1.id, |metadata| metadata.expires_at = Some(0)).unwrap();
+        
+        assert!(matches!(registry.remove_file(&v1.id), Err(FileRegistryError::Pinned(_))));
+        assert!(matches!(registry.move_to_trash(&v1.id, 0), Err(FileRegistryError::Pinned(_))));
+        assert!(matches!(registry.remove_files(&[v2.id, v1.id]), Err(FileRegistryError::Pinned(_))));
+        assert!(registry.get_file(&v2.id).is_some());
+        // A pin outlives the time-to-live
+        assert!(!registry.get_file(&v1.id).unwrap().is_expired(now_secs()));
+        
+        // Pruning keeps pinned versions on top of what the policy keeps
+        let policy = RetentionPolicy { keep_last: Some(1), keep_within: None };
+        let pruned = registry.prune_versions("a.txt", &policy).unwrap();
+        assert_eq!(pruned.iter().map(|metadata| metadata.id).collect::<Vec<_>>(), vec![v2.id]);
+        assert_eq!(registry.pinned_files().len(), 1);
+        
+        // The pin survives a reload, and removal works again once it is gone
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        assert_eq!(registry.unpin(&v1.id).unwrap(), Some(PinMode::Direct));
+        assert_eq!(registry.unpin(&v1.id).unwrap(), None);
+        assert!(registry.remove_file(&v1.id).unwrap().is_some());
+    }
+    
+    #[test]
+    fn test_recursive_pins_hold_entries_below_their_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        
+        let photos = registry.register_file_at("photos", "photos".to_string(), vec![], 1).unwrap();
+        let inside = registry.register_file_at("photos/a.jpg", "a.jpg".to_string(), vec![], 2).unwrap();
+        let sibling = registry.register_file_at("photos-old/b.jpg", "b.jpg".to_string(), vec![], 3).unwrap();
+        
+        // A direct pin holds only the entry itself
+        registry.pin(&photos.id, PinMode::Direct).unwrap();
+        assert!(registry.remove_file(&inside.id).is_ok());
+        let inside = registry.register_file_at("photos/a.jpg", "a.jpg".to_string(), vec![], 2).unwrap();
+        
+        // A recursive one also holds what lies below, including later entries
+        registry.pin(&photos.id, PinMode::Recursive).unwrap();
+        let later = registry.register_file_at("photos/2024/c.jpg", "c.jpg".to_string(), vec![], 4).unwrap();
+        registry.update_file(&later.id, |metadata| metadata.expires_at = Some(0)).unwrap();
+        for file_id in [inside.id, later.id] {
+            assert!(matches!(registry.move_to_trash(&file_id, 0), Err(FileRegistryError::Pinned(ref path)) if path == "photos"));
+            assert!(matches!(registry.unpin(&file_id), Err(FileRegistryError::Pinned(_))));
+        }
+        assert!(registry.expired_ids(now_secs()).is_empty());
+        assert!(registry.remove_file(&sibling.id).unwrap().is_some());
+        
+        assert_eq!(registry.unpin(&photos.id).unwrap(), Some(PinMode::Recursive));
+        assert_eq!(registry.expired_ids(now_secs()), HashSet::from([later.id]));
+        assert_eq!(registry.remove_files(&[inside.id, later.id]).unwrap().len(), 2);
+    }
+    
+    #[test]
+    fn test_import_entries() {
+        let source_dir = TempDir::new().unwrap();
+        let mut source = FileRegistry::new(source_dir.path()).unwrap();
+        let v1 = source.register_file("a.txt".to_string(), vec![], 1).unwrap();
+        let v2 = source.register_file("a.txt".to_string(), vec![], 2).unwrap();
+        let b = source.register_file("b.txt".to_string(), vec![], 3).unwrap();
+        let exported = vec![v1.clone(), v2.clone(), b.clone()];
+        
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        let local = registry.register_file("a.txt".to_string(), vec![], 9).unwrap();
+        let report = registry.import_entries(exported.clone(), false).unwrap();
+        assert_eq!(report.imported.len(), 3);
+        
+        // Imported history of an existing path is appended to it
+        let versions: Vec<(FileId, u32)> = registry.versions("a.txt").iter().map(|m| (m.id, m.version)).collect();
+        assert_eq!(versions, vec![(local.id, 1), (v1.id, 2), (v2.id, 3)]);
+        assert_eq!(registry.get_file(&v1.id).unwrap().parent, Some(local.id));
+        assert_eq!(registry.get_file(&b.id).unwrap(), &b);
+        
+        // Importing again skips unchanged entries; changed ones conflict
+        let mut changed = b.clone();
+        changed.tags.insert("new".to_string());
+        let report = registry.import_entries(vec![b.clone(), changed.clone()], false);
+        assert!(matches!(report, Err(FileRegistryError::IdConflict(ids)) if ids == vec![b.id]));
+        assert!(registry.get_file(&b.id).unwrap().tags.is_empty());
+        
+        let report = registry.import_entries(vec![b.clone(), changed], true).unwrap();
+        assert_eq!((report.unchanged, report.rekeyed), (1, 1));
+        let rekeyed = &report.imported[0];
+        assert_ne!(rekeyed.id, b.id);
+        assert_eq!((rekeyed.version, rekeyed.parent), (2, Some(b.id)));
+        assert_eq!(FileRegistry::new(temp_dir.path()).unwrap().file_count(), 5);
+    }
+    
+    #[test]
+    fn test_purge_from_trash() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut registry = FileRegistry::new(temp_dir.path()).unwrap();
+        
+        let a = registry.register_file("a".to_string(), vec![], 1).unwrap();
+        let b = registry.register_file("b".to_string(), vec![], 1).unwrap();
+        registry.move_to_trash(&a.id, 1).unwrap();
+        registry.move_to_trash(&b.id, 2).unwrap();
+        
+        let purged = registry.purge_from_trash(&[a.id, FileId::new_v4()]).unwrap();
+        assert_eq!(purged.len(), 1);
+        assert_eq!(registry.trash_entries()[0].metadata.id, b.id);
+        assert!(matches!(registry.restore_from_trash(&a.id), Err(FileRegistryError::FileNotFound(_))));
+    }
 }
@@ -0,0 +1,208 @@
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use redb::{Database, ReadableTable, TableDefinition};
+use crate::content::ContentAddress;
+use super::registry::FileMetadata;
+
+/// Snapshot ID -> JSON encoded `SnapshotRecord`
+const SNAPSHOTS: TableDefinition<&str, &[u8]> = TableDefinition::new("snapshots");
+
+/// Length of the hex snapshot IDs derived from object addresses
+pub const SNAPSHOT_ID_LEN: usize = 12;
+
+/// The registered file set at one point in time. Stored as a single
+/// content-addressed object, so a snapshot cannot change once taken.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub created_at: u64, // Unix timestamp
+    pub files: Vec<FileMetadata>, // Ordered by path and version
+}
+
+impl Snapshot {
+    pub fn new<'a, I>(files: I, created_at: u64) -> Self
+    where
+        I: IntoIterator<Item = &'a FileMetadata>,
+    {
+        let mut files: Vec<FileMetadata> = files.into_iter().cloned().collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path).then(a.version.cmp(&b.version)));
+        Self { created_at, files }
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().map(|metadata| metadata.total_size).sum()
+    }
+}
+
+/// Index entry describing a stored snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotRecord {
+    pub id: String, // Leading hex digits of the object address
+    pub address: ContentAddress,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub created_at: u64,
+    pub files: usize,
+    pub total_size: u64,
+}
+
+impl SnapshotRecord {
+    pub fn new(snapshot: &Snapshot, address: ContentAddress, name: Option<String>) -> Self {
+        Self {
+            id: hex::encode(address.hash_bytes())[..SNAPSHOT_ID_LEN].to_string(),
+            address,
+            name,
+            created_at: snapshot.created_at,
+            files: snapshot.files.len(),
+            total_size: snapshot.total_size(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Database error: {0}")]
+    Database(Box<redb::Error>),
+
+    #[error("Snapshot not found: {0}")]
+    NotFound(String),
+
+    #[error("Ambiguous snapshot '{prefix}' matches: {}", .candidates.join(", "))]
+    Ambiguous { prefix: String, candidates: Vec<String> },
+
+    #[error("A snapshot named '{0}' already exists")]
+    Exists(String),
+}
+
+impl_from_redb_error!(SnapshotError);
+
+pub type SnapshotResult<T> = Result<T, SnapshotError>;
+
+/// Index of catalog snapshots.
+///
+/// The snapshots themselves are objects in the content store; this small
+/// redb database next to the file registry only records which exist.
+#[derive(Debug)]
+pub struct SnapshotStore {
+    database_path: PathBuf,
+}
+
+impl SnapshotStore {
+    /// Open or create the snapshot index in `storage_dir`
+    pub fn new<P: AsRef<Path>>(storage_dir: P) -> SnapshotResult<Self> {
+        std::fs::create_dir_all(storage_dir.as_ref())?;
+        let store = Self {
+            database_path: storage_dir.as_ref().join("snapshots.redb"),
+        };
+
+        // Make sure the table exists so read transactions can open it
+        let database = store.open()?;
+        let txn = database.begin_write()?;
+        txn.open_table(SNAPSHOTS)?;
+        txn.commit()?;
+        Ok(store)
+    }
+
+    fn open(&self) -> SnapshotResult<Database> {
+        Ok(Database::create(&self.database_path)?)
+    }
+
+    /// Record a snapshot. IDs and names must be unused.
+    pub fn add(&self, record: &SnapshotRecord) -> SnapshotResult<()> {
+        let existing = self.list()?;
+        if let Some(taken) = existing.iter().find(|other| {
+            other.id == record.id || (record.name.is_some() && other.name == record.name)
+        }) {
+            return Err(SnapshotError::Exists(record.name.clone().unwrap_or_else(|| taken.id.clone())));
+        }
+
+        let database = self.open()?;
+        let txn = database.begin_write()?;
+        txn.open_table(SNAPSHOTS)?.insert(record.id.as_str(), serde_json::to_vec(record)?.as_slice())?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Every snapshot, oldest first
+    pub fn list(&self) -> SnapshotResult<Vec<SnapshotRecord>> {
+        let database = self.open()?;
+        let txn = database.begin_read()?;
+        let table = txn.open_table(SNAPSHOTS)?;
+
+        let mut records = Vec::new();
+        for row in table.iter()? {
+            let (_, bytes) = row?;
+            records.push(serde_json::from_slice::<SnapshotRecord>(bytes.value())?);
+        }
+        records.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        Ok(records)
+    }
+
+    /// Find a snapshot by name or by a unique prefix of its ID
+    pub fn resolve(&self, reference: &str) -> SnapshotResult<SnapshotRecord> {
+        let records = self.list()?;
+        if let Some(named) = records.iter().find(|record| record.name.as_deref() == Some(reference)) {
+            return Ok(named.clone());
+        }
+
+        let prefix = reference.to_ascii_lowercase();
+        let matches: Vec<&SnapshotRecord> = records.iter().filter(|record| record.id.starts_with(&prefix)).collect();
+        match matches.as_slice() {
+            [record] if !prefix.is_empty() => Ok((*record).clone()),
+            [] | [_] => Err(SnapshotError::NotFound(reference.to_string())),
+            _ => Err(SnapshotError::Ambiguous {
+                prefix: reference.to_string(),
+                candidates: matches.iter().map(|record| record.id.clone()).collect(),
+            }),
+        }
+    }
+
+    /// Drop a snapshot from the index, returning its record
+    pub fn remove(&self, reference: &str) -> SnapshotResult<SnapshotRecord> {
+        let record = self.resolve(reference)?;
+        let database = self.open()?;
+        let txn = database.begin_write()?;
+        txn.open_table(SNAPSHOTS)?.remove(record.id.as_str())?;
+        txn.commit()?;
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn record(label: &str, created_at: u64, name: Option<&str>) -> SnapshotRecord {
+        let snapshot = Snapshot::new(&[], created_at);
+        SnapshotRecord::new(&snapshot, ContentAddress::from_data(label.as_bytes()), name.map(str::to_string))
+    }
+
+    #[test]
+    fn test_add_resolve_and_remove() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp_dir.path()).unwrap();
+        let first = record("first", 20, Some("before-cleanup"));
+        let second = record("second", 10, None);
+        store.add(&first).unwrap();
+        store.add(&second).unwrap();
+
+        assert!(matches!(store.add(&first), Err(SnapshotError::Exists(_))));
+        assert!(matches!(store.add(&record("third", 30, Some("before-cleanup"))), Err(SnapshotError::Exists(_))));
+        assert_eq!(store.list().unwrap(), vec![second.clone(), first.clone()]);
+
+        assert_eq!(store.resolve("before-cleanup").unwrap(), first);
+        assert_eq!(store.resolve(&second.id[..6]).unwrap(), second);
+        assert!(matches!(store.resolve("zz"), Err(SnapshotError::NotFound(_))));
+        assert!(matches!(store.resolve(""), Err(SnapshotError::Ambiguous { .. })));
+
+        let store = SnapshotStore::new(temp_dir.path()).unwrap();
+        assert_eq!(store.remove("before-cleanup").unwrap(), first);
+        assert_eq!(store.list().unwrap(), vec![second]);
+    }
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+use super::registry::FileMetadata;
+
+/// How long deleted files stay restorable by default (7 days)
+pub const DEFAULT_TRASH_RETENTION: u64 = 7 * 24 * 60 * 60;
+
+/// A soft-deleted registry entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub metadata: FileMetadata,
+    pub deleted_at: u64, // Unix timestamp
+}
+
+impl TrashEntry {
+    /// Whether the retention period has passed, after which the entry may be
+    /// purged and its chunks are no longer kept alive
+    pub fn is_expired(&self, now: u64, retention: u64) -> bool {
+        now >= self.deleted_at.saturating_add(retention)
+    }
+
+    /// Seconds until the entry expires (zero once expired)
+    pub fn remaining(&self, now: u64, retention: u64) -> u64 {
+        self.deleted_at.saturating_add(retention).saturating_sub(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry() {
+        let entry = TrashEntry {
+            metadata: FileMetadata::new("a.txt".to_string(), vec![], 1),
+            deleted_at: 1_000,
+        };
+
+        assert!(!entry.is_expired(1_000, 60));
+        assert_eq!(entry.remaining(1_030, 60), 30);
+        assert!(entry.is_expired(1_060, 60));
+        assert_eq!(entry.remaining(2_000, 60), 0);
+        assert!(!entry.is_expired(u64::MAX - 1, u64::MAX));
+    }
+}
@@ -0,0 +1,559 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::content::ContentAddress;
+use crate::file::posix::create_symlink;
+use crate::storage::ContentStore;
+use crate::storage::store::ContentStoreError;
+
+/// Kind of object a tree entry points at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+/// A single named child of a directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeEntry {
+    pub name: String,
+    pub kind: EntryKind,
+    pub mode: u32,
+    pub size: u64, // File size, or total size of all files below a directory
+    pub address: ContentAddress, // FileManifest for files, Tree for directories, target for symlinks
+}
+
+/// Directory listing stored as a content-addressed object
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tree {
+    pub entries: Vec<TreeEntry>, // Sorted by name so equal directories hash equally
+}
+
+/// Chunk list of a single file stored as a content-addressed object
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileManifest {
+    pub size: u64,
+    pub chunks: Vec<ContentAddress>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TreeError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Storage error: {0}")]
+    Storage(#[from] ContentStoreError),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Invalid entry name in tree: {0:?}")]
+    InvalidName(String),
+
+    #[error("Duplicate entry name in tree: {0:?}")]
+    DuplicateName(String),
+
+    #[error("Refusing to restore through a symlink: {}", .0.display())]
+    SymlinkInPath(PathBuf),
+
+    #[error("File name or symlink target is not valid UTF-8: {}", .0.display())]
+    NonUtf8(PathBuf),
+
+    #[error("Not a directory: {0}")]
+    NotADirectory(String),
+}
+
+pub type TreeResult<T> = Result<T, TreeError>;
+
+/// Counts gathered while storing or restoring a tree
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeSummary {
+    pub root: Option<ContentAddress>,
+    pub files: usize,
+    pub directories: usize,
+    pub symlinks: usize,
+    pub total_size: u64,
+    pub skipped: Vec<String>, // Paths that are not files, directories or symlinks
+    pub reused: usize, // Files taken from a stat cache without being read
+}
+
+/// What a file looked like when it was stored. A file whose size,
+/// modification time and inode still match is assumed unchanged, so its
+/// manifest is reused without reading or hashing it again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStat {
+    pub size: u64,
+    pub mtime_nanos: u128, // Modification time since the Unix epoch
+    pub inode: u64, // Always 0 where inodes are not available
+    pub manifest: ContentAddress,
+}
+
+/// Stat of every file below a stored directory, keyed by `/`-separated
+/// path relative to it
+pub type StatCache = BTreeMap<String, FileStat>;
+
+/// Store an object as a single content-addressed blob
+fn put_object<T: Serialize>(store: &ContentStore, object: &T) -> TreeResult<ContentAddress> {
+    let bytes = serde_json::to_vec(object)?;
+    Ok(store.put_chunk(&bytes)?)
+}
+
+fn get_object<T: for<'de> Deserialize<'de>>(store: &ContentStore, address: &ContentAddress) -> TreeResult<T> {
+    let chunk = store.get_chunk(address)?;
+    Ok(serde_json::from_slice(chunk.data())?)
+}
+
+/// Load a directory listing by address
+pub fn load_tree(store: &ContentStore, address: &ContentAddress) -> TreeResult<Tree> {
+    get_object(store, address)
+}
+
+/// Load a file manifest by address
+pub fn load_manifest(store: &ContentStore, address: &ContentAddress) -> TreeResult<FileManifest> {
+    get_object(store, address)
+}
+
+/// Collect every object reachable from the tree at `root`: the trees
+/// themselves, file manifests, file chunks and symlink targets. Subtrees already in
+/// `objects` are not walked again.
+pub fn collect_objects(store: &ContentStore, root: &ContentAddress, objects: &mut HashSet<ContentAddress>) -> TreeResult<()> {
+    if !objects.insert(root.clone()) {
+        return Ok(());
+    }
+
+    for entry in load_tree(store, root)?.entries {
+        match entry.kind {
+            EntryKind::Directory => collect_objects(store, &entry.address, objects)?,
+            EntryKind::File => {
+                let manifest = load_manifest(store, &entry.address)?;
+                objects.insert(entry.address);
+                objects.extend(manifest.chunks);
+            }
+            EntryKind::Symlink => {
+                objects.insert(entry.address);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    match (metadata.is_dir(), metadata.permissions().readonly()) {
+        (true, _) => 0o755,
+        (false, true) => 0o444,
+        (false, false) => 0o644,
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> u64 {
+    0
+}
+
+/// Recursively store a directory, returning the address of its root tree.
+/// Unchanged files and subtrees produce identical objects and are deduplicated.
+pub fn store_directory<P: AsRef<Path>>(store: &ContentStore, dir: P) -> TreeResult<TreeSummary> {
+    store_directory_incremental(store, dir, &StatCache::new()).map(|(summary, _)| summary)
+}
+
+/// Store a directory like `store_directory`, but take files whose stat
+/// matches `previous` from their earlier manifest instead of reading them.
+/// Returns the stat cache for the next run alongside the summary.
+pub fn store_directory_incremental<P: AsRef<Path>>(
+    store: &ContentStore,
+    dir: P,
+    previous: &StatCache,
+) -> TreeResult<(TreeSummary, StatCache)> {
+    let dir = dir.as_ref();
+    if !fs::metadata(dir)?.is_dir() {
+        return Err(TreeError::NotADirectory(dir.display().to_string()));
+    }
+
+    let mut walk = TreeWalk {
+        store,
+        previous,
+        current: StatCache::new(),
+        summary: TreeSummary::default(),
+    };
+    let (root, _) = walk.store_tree(dir, "")?;
+    walk.summary.root = Some(root);
+    Ok((walk.summary, walk.current))
+}
+
+/// State carried through one recursive directory store
+struct TreeWalk<'a> {
+    store: &'a ContentStore,
+    previous: &'a StatCache,
+    current: StatCache,
+    summary: TreeSummary,
+}
+
+impl TreeWalk<'_> {
+    fn store_tree(&mut self, dir: &Path, prefix: &str) -> TreeResult<(ContentAddress, u64)> {
+        let mut tree = Tree::default();
+        let mut tree_size = 0;
+
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry.file_name().into_string()
+                .map_err(|_| TreeError::NonUtf8(path.clone()))?;
+            let relative = format!("{}{}", prefix, name);
+            let metadata = fs::symlink_metadata(&path)?;
+
+            let (kind, size, address) = if metadata.is_dir() {
+                let (address, size) = self.store_tree(&path, &format!("{}/", relative))?;
+                self.summary.directories += 1;
+                (EntryKind::Directory, size, address)
+            } else if metadata.is_file() {
+                let address = self.store_file(&path, &metadata, relative)?;
+                self.summary.files += 1;
+                self.summary.total_size += metadata.len();
+                (EntryKind::File, metadata.len(), address)
+            } else if metadata.file_type().is_symlink() {
+                // Links are kept as links; their target is stored, never followed
+                let target = fs::read_link(&path)?.into_os_string().into_string()
+                    .map_err(|_| TreeError::NonUtf8(path.clone()))?;
+                self.summary.symlinks += 1;
+                (EntryKind::Symlink, 0, self.store.put_chunk(target.as_bytes())?)
+            } else {
+                self.summary.skipped.push(path.display().to_string());
+                continue;
+            };
+
+            tree_size += size;
+            tree.entries.push(TreeEntry { name, kind, mode: file_mode(&metadata), size, address });
+        }
+
+        tree.entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok((put_object(self.store, &tree)?, tree_size))
+    }
+
+    /// Store one file, or reuse its manifest if the stat cache says it is
+    /// unchanged and the manifest is still stored
+    fn store_file(&mut self, path: &Path, metadata: &fs::Metadata, relative: String) -> TreeResult<ContentAddress> {
+        let mtime_nanos = metadata.modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |since| since.as_nanos());
+        let (size, inode) = (metadata.len(), inode(metadata));
+
+        let cached = self.previous.get(&relative).filter(|stat| {
+            stat.size == size && stat.mtime_nanos == mtime_nanos && stat.inode == inode
+        });
+        let manifest = match cached {
+            Some(stat) if self.store.has_chunk(&stat.manifest)? => {
+                self.summary.reused += 1;
+                stat.manifest.clone()
+            }
+            _ => {
+                let chunks = self.store.put_file(path)?;
+                put_object(self.store, &FileManifest { size, chunks })?
+            }
+        };
+
+        self.current.insert(relative, FileStat { size, mtime_nanos, inode, manifest: manifest.clone() });
+        Ok(manifest)
+    }
+}
+
+/// Rebuild the directory rooted at `root` inside `dest`
+pub fn restore_directory<P: AsRef<Path>>(store: &ContentStore, root: &ContentAddress, dest: P) -> TreeResult<TreeSummary> {
+    let mut summary = TreeSummary {
+        root: Some(root.clone()),
+        ..TreeSummary::default()
+    };
+    restore_tree(store, root, dest.as_ref(), &mut summary)?;
+    Ok(summary)
+}
+
+fn restore_tree(store: &ContentStore, address: &ContentAddress, dest: &Path, summary: &mut TreeSummary) -> TreeResult<()> {
+    let tree = load_tree(store, address)?;
+    fs::create_dir_all(dest)?;
+
+    let mut names = HashSet::new();
+    for entry in &tree.entries {
+        // Never let a stored name escape the destination directory
+        if entry.name.is_empty() || entry.name == "." || entry.name == ".."
+            || entry.name.contains('/') || entry.name.contains('\\') {
+            return Err(TreeError::InvalidName(entry.name.clone()));
+        }
+        // A second entry with the same name could follow a link restored by the first
+        if !names.insert(entry.name.as_str()) {
+            return Err(TreeError::DuplicateName(entry.name.clone()));
+        }
+
+        let path = dest.join(&entry.name);
+        refuse_symlink(&path)?;
+        match entry.kind {
+            EntryKind::Directory => {
+                restore_tree(store, &entry.address, &path, summary)?;
+                summary.directories += 1;
+            }
+            EntryKind::File => {
+                let manifest = load_manifest(store, &entry.address)?;
+                store.get_file(&manifest.chunks, &path)?;
+                summary.files += 1;
+                summary.total_size += manifest.size;
+            }
+            EntryKind::Symlink => {
+                let chunk = store.get_chunk(&entry.address)?;
+                let target = std::str::from_utf8(chunk.data())
+                    .map_err(|_| TreeError::NonUtf8(path.clone()))?;
+                match create_symlink(target, &path) {
+                    Ok(()) => summary.symlinks += 1,
+                    Err(e) if e.kind() == std::io::ErrorKind::Unsupported => {
+                        summary.skipped.push(path.display().to_string());
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+                // Permissions would apply to the target, not the link
+                continue;
+            }
+        }
+        set_mode(&path, entry.mode)?;
+    }
+
+    Ok(())
+}
+
+/// Fail if `path` is a symlink, so nothing is created or written through it
+fn refuse_symlink(path: &Path) -> TreeResult<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => Err(TreeError::SymlinkInPath(path.to_path_buf())),
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::ContentStoreConfig;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (ContentStore, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let config = ContentStoreConfig {
+            storage_path: temp_dir.path().join("store"),
+            ..ContentStoreConfig::default()
+        };
+        (ContentStore::new(config).unwrap(), temp_dir)
+    }
+
+    fn create_sample_dir(root: &Path) {
+        fs::create_dir_all(root.join("docs/nested")).unwrap();
+        fs::create_dir_all(root.join("empty")).unwrap();
+        fs::write(root.join("readme.txt"), b"top level file").unwrap();
+        fs::write(root.join("docs/a.txt"), b"first document").unwrap();
+        fs::write(root.join("docs/nested/b.bin"), vec![7u8; 50_000]).unwrap();
+    }
+
+    #[test]
+    fn test_incremental_store_reuses_unchanged_files() {
+        let (store, temp) = create_test_store();
+        let source = temp.path().join("source");
+        create_sample_dir(&source);
+
+        let (first, cache) = store_directory_incremental(&store, &source, &StatCache::new()).unwrap();
+        assert_eq!((first.files, first.reused), (3, 0));
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec!["docs/a.txt", "docs/nested/b.bin", "readme.txt"]);
+
+        let (again, cache) = store_directory_incremental(&store, &source, &cache).unwrap();
+        assert_eq!(again.reused, 3);
+        assert_eq!(again.root, first.root);
+
+        // A file whose size changed is read again; the rest are reused
+        fs::write(source.join("readme.txt"), b"top level file, edited").unwrap();
+        let (changed, _) = store_directory_incremental(&store, &source, &cache).unwrap();
+        assert_eq!(changed.reused, 2);
+        assert_ne!(changed.root, first.root);
+        let dest = temp.path().join("restored");
+        restore_directory(&store, changed.root.as_ref().unwrap(), &dest).unwrap();
+        assert_eq!(fs::read(dest.join("readme.txt")).unwrap(), b"top level file, edited");
+    }
+
+    #[test]
+    fn test_store_and_restore_directory() {
+        let (store, temp) = create_test_store();
+        let source = temp.path().join("source");
+        create_sample_dir(&source);
+
+        let stored = store_directory(&store, &source).unwrap();
+        assert_eq!(stored.files, 3);
+        assert_eq!(stored.directories, 3);
+        assert_eq!(stored.total_size, 14 + 14 + 50_000);
+
+        let dest = temp.path().join("restored");
+        let restored = restore_directory(&store, stored.root.as_ref().unwrap(), &dest).unwrap();
+        assert_eq!(restored.files, 3);
+        assert_eq!(fs::read(dest.join("docs/nested/b.bin")).unwrap(), vec![7u8; 50_000]);
+        assert_eq!(fs::read(dest.join("readme.txt")).unwrap(), b"top level file");
+        assert!(dest.join("empty").is_dir());
+    }
+
+    #[test]
+    fn test_unchanged_subtrees_deduplicate() {
+        let (store, temp) = create_test_store();
+        let source = temp.path().join("source");
+        create_sample_dir(&source);
+
+        let first = store_directory(&store, &source).unwrap();
+        let objects_before = store.stats().unwrap().total_chunks;
+
+        // Touch only the top-level file; the docs subtree must be reused as-is
+        fs::write(source.join("readme.txt"), b"changed top level file").unwrap();
+        let second = store_directory(&store, &source).unwrap();
+        assert_ne!(first.root, second.root);
+
+        let first_tree = load_tree(&store, first.root.as_ref().unwrap()).unwrap();
+        let second_tree = load_tree(&store, second.root.as_ref().unwrap()).unwrap();
+        let docs = |tree: &Tree| tree.entries.iter().find(|e| e.name == "docs").unwrap().address.clone();
+        assert_eq!(docs(&first_tree), docs(&second_tree));
+
+        // New objects: one chunk, one manifest, one root tree
+        assert_eq!(store.stats().unwrap().total_chunks, objects_before + 3);
+    }
+
+    #[test]
+    fn test_collect_objects_covers_whole_tree() {
+        let (store, temp) = create_test_store();
+        let source = temp.path().join("source");
+        create_sample_dir(&source);
+
+        let stored = store_directory(&store, &source).unwrap();
+        let mut objects = HashSet::new();
+        collect_objects(&store, stored.root.as_ref().unwrap(), &mut objects).unwrap();
+
+        // Nothing else was written, so every stored object is reachable
+        assert_eq!(objects.len(), store.stats().unwrap().total_chunks);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_are_stored_as_links() {
+        let (store, temp) = create_test_store();
+        let source = temp.path().join("source");
+        create_sample_dir(&source);
+        std::os::unix::fs::symlink("docs/a.txt", source.join("link")).unwrap();
+        std::os::unix::fs::symlink("/nowhere/at/all", source.join("dangling")).unwrap();
+
+        let stored = store_directory(&store, &source).unwrap();
+        assert_eq!((stored.files, stored.symlinks), (3, 2));
+        assert!(stored.skipped.is_empty());
+
+        let dest = temp.path().join("restored");
+        let restored = restore_directory(&store, stored.root.as_ref().unwrap(), &dest).unwrap();
+        assert_eq!(restored.symlinks, 2);
+        assert_eq!(fs::read_link(dest.join("link")).unwrap(), Path::new("docs/a.txt"));
+        assert_eq!(fs::read_link(dest.join("dangling")).unwrap(), Path::new("/nowhere/at/all"));
+
+        let mut objects = HashSet::new();
+        collect_objects(&store, stored.root.as_ref().unwrap(), &mut objects).unwrap();
+        assert_eq!(objects.len(), store.stats().unwrap().total_chunks);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_store_rejects_non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let (store, temp) = create_test_store();
+        let source = temp.path().join("source");
+        fs::create_dir_all(&source).unwrap();
+        let name = std::ffi::OsStr::from_bytes(b"bad\xffname");
+        if fs::write(source.join(name), b"data").is_err() {
+            return; // The filesystem itself insists on UTF-8 names
+        }
+
+        assert!(matches!(store_directory(&store, &source), Err(TreeError::NonUtf8(_))));
+    }
+
+    #[test]
+    fn test_restore_rejects_escaping_names() {
+        let (store, temp) = create_test_store();
+        let manifest = put_object(&store, &FileManifest { size: 0, chunks: vec![] }).unwrap();
+        let tree = Tree {
+            entries: vec![TreeEntry {
+                name: "../evil".to_string(),
+                kind: EntryKind::File,
+                mode: 0o644,
+                size: 0,
+                address: manifest,
+            }],
+        };
+        let root = put_object(&store, &tree).unwrap();
+
+        let result = restore_directory(&store, &root, temp.path().join("out"));
+        assert!(matches!(result, Err(TreeError::InvalidName(_))));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_restore_rejects_malicious_trees() {
+        let (store, temp) = create_test_store();
+        let outside = temp.path().join("outside");
+        fs::create_dir_all(&outside).unwrap();
+
+        let content = store.put_data(b"owned").unwrap();
+        let manifest = put_object(&store, &FileManifest { size: 5, chunks: content }).unwrap();
+        let target = store.put_chunk(outside.to_str().unwrap().as_bytes()).unwrap();
+        let inner = put_object(&store, &Tree {
+            entries: vec![TreeEntry { name: "payload".to_string(), kind: EntryKind::File, mode: 0o644, size: 5, address: manifest.clone() }],
+        }).unwrap();
+        let link = TreeEntry { name: "x".to_string(), kind: EntryKind::Symlink, mode: 0o777, size: 0, address: target };
+
+        // A link followed by a directory of the same name would write into the link target
+        let duplicate = put_object(&store, &Tree {
+            entries: vec![link.clone(), TreeEntry { name: "x".to_string(), kind: EntryKind::Directory, mode: 0o755, size: 5, address: inner.clone() }],
+        }).unwrap();
+        let result = restore_directory(&store, &duplicate, temp.path().join("dup"));
+        assert!(matches!(result, Err(TreeError::DuplicateName(_))));
+
+        // Restoring into a directory that already holds a link of that name
+        let dest = temp.path().join("existing");
+        fs::create_dir_all(&dest).unwrap();
+        std::os::unix::fs::symlink(&outside, dest.join("x")).unwrap();
+        let single = put_object(&store, &Tree {
+            entries: vec![TreeEntry { name: "x".to_string(), kind: EntryKind::Directory, mode: 0o755, size: 5, address: inner }],
+        }).unwrap();
+        let result = restore_directory(&store, &single, &dest);
+        assert!(matches!(result, Err(TreeError::SymlinkInPath(_))));
+
+        let file = put_object(&store, &Tree {
+            entries: vec![TreeEntry { name: "x".to_string(), kind: EntryKind::File, mode: 0o644, size: 5, address: manifest }],
+        }).unwrap();
+        let result = restore_directory(&store, &file, &dest);
+        assert!(matches!(result, Err(TreeError::SymlinkInPath(_))));
+
+        assert!(!outside.join("payload").exists());
+        assert_eq!(fs::read_dir(&outside).unwrap().count(), 0);
+    }
+}
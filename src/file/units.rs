@@ -0,0 +1,214 @@
+/// Parse a human-friendly duration such as `90s`, `15m`, `12h`, `7d` or `2w`
+/// into seconds. A bare number is taken as seconds.
+pub fn parse_duration(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    let split = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+
+    let value: u64 = number
+        .parse()
+        .map_err(|_| format!("Invalid duration: {}", input))?;
+    let multiplier = match unit.to_ascii_lowercase().as_str() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("Invalid duration unit '{}' (use s, m, h, d or w)", unit)),
+    };
+
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("Duration too large: {}", input))
+}
+
+/// Format seconds as the two most significant units, e.g. `6d 23h` or `45s`
+pub fn format_duration(seconds: u64) -> String {
+    const UNITS: [(&str, u64); 4] = [("d", 86_400), ("h", 3_600), ("m", 60), ("s", 1)];
+
+    let parts: Vec<String> = UNITS
+        .iter()
+        .scan(seconds, |remaining, &(suffix, size)| {
+            let value = *remaining / size;
+            *remaining %= size;
+            Some((value, suffix))
+        })
+        .skip_while(|&(value, _)| value == 0)
+        .take(2)
+        .filter(|&(value, _)| value > 0)
+        .map(|(value, suffix)| format!("{}{}", value, suffix))
+        .collect();
+
+    if parts.is_empty() { "0s".to_string() } else { parts.join(" ") }
+}
+
+/// Parse a size such as `512`, `64KB`, `1.5GiB` or `10M` into bytes.
+/// `KB`/`MB`/`GB`/`TB` are decimal; `KiB`/`MiB`/`GiB`/`TiB` and the bare
+/// `K`/`M`/`G`/`T` forms are binary.
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid size: {}", input))?;
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        _ => return Err(format!("Invalid size unit '{}' (use B, KB, MB, GB, TB or KiB, MiB, GiB, TiB)", unit)),
+    };
+
+    let bytes = value * multiplier as f64;
+    if !bytes.is_finite() || bytes > u64::MAX as f64 {
+        return Err(format!("Size too large: {}", input));
+    }
+    Ok(bytes.round() as u64)
+}
+
+/// Parse a UTC date `YYYY-MM-DD`, optionally followed by `THH:MM` or
+/// `THH:MM:SS`. Returns the Unix timestamp of its start together with the
+/// length in seconds of the period it names (a day, minute or second).
+pub fn parse_date(input: &str) -> Result<(u64, u64), String> {
+    let invalid = || format!("Invalid date '{}': expected YYYY-MM-DD[THH:MM[:SS]]", input);
+
+    let (date, time) = match input.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (input, None),
+    };
+
+    let parts: Vec<&str> = date.split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        return Err(invalid());
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return Err(invalid());
+    }
+    let year: i64 = year.parse().map_err(|_| invalid())?;
+    let month: u32 = month.parse().map_err(|_| invalid())?;
+    let day: u32 = day.parse().map_err(|_| invalid())?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return Err(invalid());
+    }
+
+    let (seconds, span) = match time {
+        None => (0, 24 * 60 * 60),
+        Some(time) => {
+            let fields = time
+                .split(':')
+                .map(|field| if field.len() == 2 { field.parse::<u64>().ok() } else { None })
+                .collect::<Option<Vec<u64>>>()
+                .ok_or_else(invalid)?;
+            match *fields.as_slice() {
+                [h, m] if h < 24 && m < 60 => (h * 3600 + m * 60, 60),
+                [h, m, s] if h < 24 && m < 60 && s < 60 => (h * 3600 + m * 60 + s, 1),
+                _ => return Err(invalid()),
+            }
+        }
+    };
+
+    let days = days_from_civil(year, month, day);
+    if days < 0 {
+        return Err(format!("Date before 1970 is not supported: {}", input));
+    }
+    Ok((days as u64 * 24 * 60 * 60 + seconds, span))
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Current time as a Unix timestamp in seconds
+pub fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("45").unwrap(), 45);
+        assert_eq!(parse_duration("90s").unwrap(), 90);
+        assert_eq!(parse_duration("15m").unwrap(), 900);
+        assert_eq!(parse_duration("12h").unwrap(), 43_200);
+        assert_eq!(parse_duration("7d").unwrap(), 604_800);
+        assert_eq!(parse_duration("2W").unwrap(), 1_209_600);
+
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3y").is_err());
+        assert!(parse_duration("-1d").is_err());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(45), "45s");
+        assert_eq!(format_duration(3_600), "1h");
+        assert_eq!(format_duration(3_661), "1h 1m");
+        assert_eq!(format_duration(7 * 86_400 - 1), "6d 23h");
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("10MB").unwrap(), 10_000_000);
+        assert_eq!(parse_size("10M").unwrap(), 10 << 20);
+        assert_eq!(parse_size("1.5KiB").unwrap(), 1536);
+        assert_eq!(parse_size("2gb").unwrap(), 2_000_000_000);
+
+        assert!(parse_size("").is_err());
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("10XB").is_err());
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("1970-01-01").unwrap(), (0, 86_400));
+        assert_eq!(parse_date("2000-03-01").unwrap(), (951_868_800, 86_400));
+        assert_eq!(parse_date("2026-01-01").unwrap(), (1_767_225_600, 86_400));
+        assert_eq!(parse_date("2026-01-01T12:30").unwrap(), (1_767_270_600, 60));
+        assert_eq!(parse_date("2026-01-01T12:30:15").unwrap(), (1_767_270_615, 1));
+        assert_eq!(parse_date("2024-02-29").unwrap().1, 86_400);
+
+        assert!(parse_date("2023-02-29").is_err());
+        assert!(parse_date("2026-13-01").is_err());
+        assert!(parse_date("2026-1-1").is_err());
+        assert!(parse_date("2026-01-01T25:00").is_err());
+        assert!(parse_date("1969-12-31").is_err());
+    }
+}
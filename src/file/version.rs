@@ -0,0 +1,83 @@
+use super::registry::{FileId, FileMetadata};
+
+/// Rules deciding which old versions of a path survive a prune.
+/// A version is kept if any rule keeps it; the newest version is always kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Keep the N most recent versions
+    pub keep_last: Option<usize>,
+    /// Keep every version created within this many seconds
+    pub keep_within: Option<u64>,
+}
+
+impl RetentionPolicy {
+    /// Whether the policy has at least one rule
+    pub fn is_empty(&self) -> bool {
+        self.keep_last.is_none() && self.keep_within.is_none()
+    }
+
+    /// Select the versions to remove. `versions` must be sorted oldest first.
+    pub fn versions_to_prune(&self, versions: &[&FileMetadata], now: u64) -> Vec<FileId> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        let newest_first = versions.iter().rev().enumerate();
+        newest_first
+            .filter(|(position, metadata)| {
+                let recent = self.keep_last.is_some_and(|n| *position < n);
+                let young = self.keep_within.is_some_and(|secs| metadata.created_at.saturating_add(secs) >= now);
+                *position != 0 && !recent && !young
+            })
+            .map(|(_, metadata)| metadata.id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(created: &[u64]) -> Vec<FileMetadata> {
+        created
+            .iter()
+            .enumerate()
+            .map(|(i, &created_at)| {
+                let mut metadata = FileMetadata::new("report.pdf".to_string(), vec![], 1);
+                metadata.version = i as u32 + 1;
+                metadata.created_at = created_at;
+                metadata
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_keep_last() {
+        let all = versions(&[100, 200, 300, 400, 500]);
+        let refs: Vec<&FileMetadata> = all.iter().collect();
+        let policy = RetentionPolicy { keep_last: Some(2), keep_within: None };
+
+        let pruned = policy.versions_to_prune(&refs, 1000);
+        assert_eq!(pruned, vec![all[2].id, all[1].id, all[0].id]);
+    }
+
+    #[test]
+    fn test_keep_within() {
+        let all = versions(&[100, 200, 300, 400, 500]);
+        let refs: Vec<&FileMetadata> = all.iter().collect();
+        let policy = RetentionPolicy { keep_last: None, keep_within: Some(300) };
+
+        let pruned = policy.versions_to_prune(&refs, 600);
+        assert_eq!(pruned, vec![all[1].id, all[0].id]);
+    }
+
+    #[test]
+    fn test_latest_version_always_kept() {
+        let all = versions(&[100, 200]);
+        let refs: Vec<&FileMetadata> = all.iter().collect();
+        let policy = RetentionPolicy { keep_last: Some(0), keep_within: None };
+
+        assert_eq!(policy.versions_to_prune(&refs, 10_000), vec![all[0].id]);
+        assert!(RetentionPolicy::default().versions_to_prune(&refs, 10_000).is_empty());
+    }
+}
@@ -1,16 +1,16 @@
 // Entry point for the Nebula application
-mod args;
-mod config;
-mod node;
-mod content;
-mod storage;
-mod file;
-
-use node::Node;
-use config::Config;
+use nebula::{config, content};
+use nebula::node::{Node, PutOptions, Reference};
+use nebula::file::{export, BackupPolicy, ExportFormat, FileFilter, Query, QueryOptions, RefCondition, RestoreOptions, RetentionPolicy};
+use nebula::file::units::{format_duration, now_secs, parse_duration};
+use nebula::config::Config;
 use clap::Parser;
-use args::{NebulaArgs, Commands};
-use uuid;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use nebula::args::{NebulaArgs, Commands, PinCommands, RefCommands, SnapshotCommands, TrashCommands};
+
+/// How often a daemon node sweeps expired files
+const EXPIRY_SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(600);
 
 fn main() {
     let args = NebulaArgs::parse();
@@ -30,20 +30,121 @@ fn main() {
 
 fn handle_cli(args: &NebulaArgs, config: &Config) {
     let result = match &args.command {
-        Commands::Start { port, storage, address, daemon } => {
-            handle_start_command(*port, storage.as_ref(), address, *daemon, config)
+        Commands::Start { port, storage, address, daemon, once } => {
+            handle_start_command(*port, storage.as_ref(), address, *daemon, *once, config)
+        }
+        Commands::Put { file, recursive, path, tags, attributes, no_dereference, on_duplicate, ttl, storage, format, verified } => {
+            match ttl.as_deref().map(parse_duration).transpose() {
+                Ok(ttl) => {
+                    let options = PutOptions {
+                        path: path.clone(),
+                        tags: tags.clone(),
+                        attributes: attributes.clone(),
+                        keep_symlinks: *no_dereference,
+                        duplicates: on_duplicate.unwrap_or(config.duplicate_policy),
+                        ttl,
+                    };
+                    handle_put_command(file, *recursive, &options, storage.as_ref(), format, *verified, config)
+                }
+                Err(e) => Err(e.into()),
+            }
+        }
+        Commands::Get { file_id, output, recursive, version, at, preserve, storage } => {
+            let restore = if *preserve { RestoreOptions::all() } else { RestoreOptions::default() };
+            handle_get_command(file_id, output, *recursive, *version, *at, restore, storage.as_ref(), config)
+        }
+        Commands::Rm { file_id, permanent, keep_chunks, storage } => {
+            handle_rm_command(file_id, *permanent, *keep_chunks, storage.as_ref(), config)
+        }
+        Commands::Trash { action, storage } => {
+            handle_trash_command(action, storage.as_ref(), config)
+        }
+        Commands::Ref { action, storage } => {
+            handle_ref_command(action, storage.as_ref(), config)
+        }
+        Commands::ExportRegistry { output, format, storage } => {
+            handle_export_registry_command(output, *format, storage.as_ref(), config)
+        }
+        Commands::ImportRegistry { input, rekey, chunks_from, allow_missing, storage } => {
+            handle_import_registry_command(input, *rekey, chunks_from.as_deref(), *allow_missing, storage.as_ref(), config)
+        }
+        Commands::Backup { dir, name, storage } => {
+            handle_backup_command(dir, name.as_deref(), storage.as_ref(), config)
+        }
+        Commands::Backups { name, storage } => {
+            handle_backups_command(name.as_deref(), storage.as_ref(), config)
+        }
+        Commands::Forget { name, keep_last, keep_daily, keep_weekly, dry_run, storage } => {
+            let policy = BackupPolicy {
+                keep_last: *keep_last,
+                keep_daily: *keep_daily,
+                keep_weekly: *keep_weekly,
+            };
+            handle_forget_command(name.as_deref(), &policy, *dry_run, storage.as_ref(), config)
+        }
+        Commands::Snapshot { action, storage } => {
+            handle_snapshot_command(action, storage.as_ref(), config)
+        }
+        Commands::Pin { action, storage } => {
+            handle_pin_command(action, storage.as_ref(), config)
+        }
+        Commands::Gc { dry_run, storage } => {
+            handle_gc_command(*dry_run, storage.as_ref(), config)
         }
-        Commands::Put { file, storage, format } => {
-            handle_put_command(file, storage.as_ref(), format, config)
+        Commands::Expire { dry_run, storage } => {
+            handle_expire_command(*dry_run, storage.as_ref(), config)
         }
-        Commands::Get { file_id, output, storage } => {
-            handle_get_command(file_id, output, storage.as_ref(), config)
+        Commands::Diff { old, new, storage } => {
+            handle_diff_command(old, new, storage.as_ref(), config)
         }
+        Commands::Du { limit, storage } => {
+            handle_du_command(*limit, storage.as_ref(), config)
+        }
+        Commands::Similar { file_id, limit, storage } => {
+            handle_similar_command(file_id, *limit, storage.as_ref(), config)
+        }
+        Commands::Dupes { storage } => {
+            handle_dupes_command(storage.as_ref(), config)
+        }
+        Commands::Log { path, storage } => {
+            handle_log_command(path, storage.as_ref(), config)
+        }
+        Commands::Prune { path, keep_last, keep_within, storage } => {
+            handle_prune_command(path, *keep_last, keep_within.as_deref(), storage.as_ref(), config)
+        }
+        Commands::GetRange { file_id, offset, length, output, storage } => {
+            handle_get_range_command(file_id, *offset, *length, output, storage.as_ref(), config)
+        }
+        Commands::Prove { file_id, chunk_index, storage } => {
+            handle_prove_command(file_id, *chunk_index, storage.as_ref(), config)
+        }
+        Commands::Verify { file_id, all, proof, root, chunk, storage } => match (proof, root) {
+            (Some(proof), Some(root)) => handle_verify_proof_command(proof, root, chunk.as_ref()),
+            _ => handle_verify_command(file_id.as_deref(), *all, storage.as_ref(), config),
+        },
         Commands::List { storage, verbose } => {
             handle_list_command(storage.as_ref(), *verbose, config)
         }
-        Commands::ListFiles { storage, verbose } => {
-            handle_list_files_command(storage.as_ref(), *verbose, config)
+        Commands::ListFiles { storage, verbose, name, tags, attributes, mime } => {
+            let filter = FileFilter {
+                name: name.clone(),
+                tags: tags.clone(),
+                attributes: attributes.clone(),
+                mime: mime.clone(),
+            };
+            handle_list_files_command(storage.as_ref(), *verbose, &filter, config)
+        }
+        Commands::Find { query, sort, desc, limit, offset, storage } => {
+            handle_find_command(&query.join(" "), sort, *desc, *limit, *offset, storage.as_ref(), config)
+        }
+        Commands::Tag { file_id, add, remove, storage } => {
+            handle_tag_command(file_id, add, remove, storage.as_ref(), config)
+        }
+        Commands::Attr { file_id, set, unset, storage } => {
+            handle_attr_command(file_id, set, unset, storage.as_ref(), config)
+        }
+        Commands::Mv { file_id, new_name, unique, storage } => {
+            handle_mv_command(file_id, new_name, *unique, storage.as_ref(), config)
         }
         Commands::Stats { storage } => {
             handle_stats_command(storage.as_ref(), config)
@@ -70,30 +171,36 @@ fn handle_start_command(
     _storage: Option<&std::path::PathBuf>, 
     address: &str, 
     daemon: bool,
+    once: bool,
     _config: &Config
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut node = Node::new(
         address.to_string(),
         port,
-        crate::config::LogLevel::Info,
+        config::LogLevel::Info,
         daemon
     )?;
     
     if daemon {
         println!("Starting node in daemon mode...");
         node.start()?;
+        let stop = stop_signal()?;
+        if !once {
+            println!("Node running in daemon mode. Press Ctrl+C to stop.");
+        }
         
-        // In a real implementation, this would run indefinitely
-        // For now, we'll simulate daemon mode
-        println!("Node running in daemon mode. Press Ctrl+C to stop.");
-        
-        // Set up signal handler for graceful shutdown
-        match std::thread::park_timeout(std::time::Duration::from_secs(3600)) {
-            () => {
-                println!("Shutting down daemon...");
-                node.stop()?;
+        // The registry belongs to one process at a time, so leave it to CLI
+        // commands between sweeps and open it afresh for each one
+        drop(node);
+        loop {
+            if let Err(e) = sweep_expired_files(address, port) {
+                eprintln!("Expiry sweep failed: {}", e);
+            }
+            if once || !wait_for_next_sweep(&stop) {
+                break;
             }
         }
+        println!("Shutting down daemon...");
     } else {
         println!("Starting node in interactive mode...");
         node.start()?;
@@ -108,28 +215,91 @@ fn handle_start_command(
     Ok(())
 }
 
+/// Flag raised when the process is asked to stop with SIGINT or SIGTERM
+#[cfg(unix)]
+fn stop_signal() -> std::io::Result<Arc<AtomicBool>> {
+    let stop = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(&stop))?;
+    }
+    Ok(stop)
+}
+
+/// Ctrl+C ends the process directly where signals cannot be caught
+#[cfg(not(unix))]
+fn stop_signal() -> std::io::Result<Arc<AtomicBool>> {
+    Ok(Arc::new(AtomicBool::new(false)))
+}
+
+/// Sleep until the next expiry sweep is due, returning false if asked to
+/// stop in the meantime
+fn wait_for_next_sweep(stop: &AtomicBool) -> bool {
+    let due = std::time::Instant::now() + EXPIRY_SWEEP_INTERVAL;
+    while std::time::Instant::now() < due {
+        if stop.load(Ordering::Relaxed) {
+            return false;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+    !stop.load(Ordering::Relaxed)
+}
+
+/// Remove expired files using a freshly loaded registry. The node is
+/// opened quietly so each sweep does not repeat the startup banner.
+fn sweep_expired_files(address: &str, port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        address.to_string(),
+        port,
+        config::LogLevel::Warn,
+        true
+    )?;
+    
+    let report = node.run_command(|node| node.expire_files(false))?;
+    if !report.expired.is_empty() {
+        println!("Expired {} files, released {} chunks ({} bytes)",
+                 report.expired.len(), report.released_chunks, report.reclaimed_bytes);
+    }
+    print_unreadable_roots(&report.unreadable_roots);
+    Ok(())
+}
+
 fn handle_put_command(
     file: &std::path::PathBuf,
+    recursive: bool,
+    options: &PutOptions,
     _storage: Option<&std::path::PathBuf>,
     format: &str,
+    verified: bool,
     _config: &Config
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut node = Node::new(
         "127.0.0.1".to_string(),
         4001,
-        crate::config::LogLevel::Info,
+        config::LogLevel::Info,
         false
     )?;
     
     node.run_command(|node| {
-        let metadata = node.put_file_with_registry(file)?;
+        let mut metadata = if recursive {
+            let (metadata, summary) = node.put_directory_with_registry(file, options)?;
+            print_skipped(&summary.skipped);
+            metadata
+        } else {
+            node.put_file_with_options(file, options)?
+        };
+        
+        if verified {
+            let root = node.build_outboard(&metadata.id)?;
+            println!("Outboard tree built with root: {}", root);
+            metadata.stream_root = Some(root);
+        }
         
         match format {
             "id" => {
                 println!("{}", metadata.id);
             }
             "short" => {
-                println!("{}", metadata.short_id());
+                println!("{}", node.file_registry.short_id(&metadata.id));
             }
             "json" => {
                 println!("{}", serde_json::to_string_pretty(&metadata)?);
@@ -152,39 +322,991 @@ fn handle_put_command(
     Ok(())
 }
 
+/// Explain why a removal released no chunks: something that might still
+/// reference them could not be read
+fn print_unreadable_roots(roots: &[String]) {
+    if roots.is_empty() {
+        return;
+    }
+    println!("Warning: kept every chunk because {} roots could not be read; gc can release them once these are fixed:", roots.len());
+    for root in roots {
+        println!("  {}", root);
+    }
+}
+
+/// Report entries a directory walk left out: sockets, FIFOs and devices,
+/// or symlinks on platforms that cannot create them
+fn print_skipped(skipped: &[String]) {
+    if skipped.is_empty() {
+        return;
+    }
+    println!("Skipped {} entries:", skipped.len());
+    for path in skipped {
+        println!("  {}", path);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn handle_get_command(
     file_id: &str,
     output: &std::path::PathBuf,
+    recursive: bool,
+    version: Option<u32>,
+    at: Option<u64>,
+    restore: RestoreOptions,
     _storage: Option<&std::path::PathBuf>,
     _config: &Config
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut node = Node::new(
         "127.0.0.1".to_string(),
         4001,
-        crate::config::LogLevel::Info,
+        config::LogLevel::Info,
         false
     )?;
     
     node.run_command(|node| {
-        // Try to parse as full UUID first (file ID)
-        if let Ok(parsed_id) = uuid::Uuid::parse_str(file_id) {
-            node.get_file_by_id(&parsed_id, output)?;
-            println!("File retrieved to: {}", output.display());
-        } else if file_id.len() == 8 && file_id.chars().all(|c| c.is_ascii_hexdigit()) {
-            // Try as short ID (8 hex characters)
-            node.get_file_by_short_id(file_id, output)?;
-            println!("File retrieved to: {}", output.display());
+        let reference = if version.is_some() || at.is_some() {
+            Reference::File(node.resolve_version(file_id, version, at)?)
         } else {
-            // Fall back to treating it as a content address (legacy support)
-            let parsed_address = crate::content::ContentAddress::from_hex(file_id)
-                .map_err(|e| format!("Invalid file ID, short ID, or content address format: {}", e))?;
-            
-            println!("Retrieving chunk: {} (legacy mode)", parsed_address);
-            let addresses = vec![parsed_address];
-            node.get_file(&addresses, output)?;
-            println!("Content retrieved to: {}", output.display());
+            node.resolve(file_id)?
+        };
+        
+        if recursive {
+            let root = match reference {
+                Reference::File(parsed_id) => node.file_registry.get_file(&parsed_id)
+                    .and_then(|metadata| metadata.tree_root.clone())
+                    .ok_or_else(|| format!("{} is not a directory stored with put -r", file_id))?,
+                Reference::Content(address) => address,
+            };
+            print_skipped(&node.get_directory(&root, output)?.skipped);
+            println!("Directory restored to: {}", output.display());
+            return Ok(());
+        }
+        
+        match reference {
+            Reference::File(parsed_id) => {
+                node.get_file_with_options(&parsed_id, output, restore)?;
+                println!("File retrieved to: {}", output.display());
+            }
+            Reference::Content(parsed_address) => {
+                // Raw chunk retrieval (legacy support)
+                println!("Retrieving chunk: {} (legacy mode)", parsed_address);
+                let addresses = vec![parsed_address];
+                node.get_file(&addresses, output)?;
+                println!("Content retrieved to: {}", output.display());
+            }
+        }
+        
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_find_command(
+    query: &str,
+    sort: &str,
+    descending: bool,
+    limit: Option<usize>,
+    offset: usize,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Parse up front so syntax errors are reported without starting the node
+    let query: Query = query.parse()?;
+    let options = QueryOptions {
+        sort: sort.parse()?,
+        descending,
+        offset,
+        limit,
+    };
+    
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        for line in node.find_files(&query, &options)? {
+            println!("{}", line);
+        }
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_tag_command(
+    file_id: &str,
+    add: &[String],
+    remove: &[String],
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let parsed_id = node.resolve_file(file_id)?;
+        let metadata = node.update_tags(&parsed_id, add, remove)?;
+        let tags: Vec<&str> = metadata.tags.iter().map(String::as_str).collect();
+        println!("Tags for {}: {}", metadata.path, if tags.is_empty() { "(none)".to_string() } else { tags.join(", ") });
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_attr_command(
+    file_id: &str,
+    set: &[(String, String)],
+    unset: &[String],
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let parsed_id = node.resolve_file(file_id)?;
+        let metadata = node.update_attributes(&parsed_id, set, unset)?;
+        println!("Attributes for {}:", metadata.path);
+        if metadata.attributes.is_empty() {
+            println!("  (none)");
+        }
+        for (key, value) in &metadata.attributes {
+            println!("  {}={}", key, value);
+        }
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_mv_command(
+    file_id: &str,
+    new_name: &str,
+    unique: bool,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let parsed_id = node.resolve_file(file_id)?;
+        let moved = node.rename_file(&parsed_id, new_name, unique)?;
+        if let Some(rename) = moved.first().and_then(|metadata| metadata.renames.last()) {
+            println!("Renamed {} -> {} ({} versions)", rename.from, rename.to, moved.len());
+        }
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_diff_command(
+    old: &str,
+    new: &str,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let old_id = node.resolve_file(old)?;
+        let new_id = node.resolve_file(new)?;
+        let diff = node.diff_files(&old_id, &new_id)?;
+        let describe = |file_id| {
+            let metadata = node.file_registry.get_file(file_id).expect("resolved file is registered");
+            format!("{} v{} ({} bytes, {} chunks)", metadata.path, metadata.version, metadata.total_size, metadata.chunk_count)
+        };
+        
+        println!("--- {}", describe(&old_id));
+        println!("+++ {}", describe(&new_id));
+        if diff.is_identical() {
+            println!("Files consist of the same chunks in the same order.");
+            return Ok(());
+        }
+        println!("Shared:  {} chunks, {} bytes", diff.shared.chunks, diff.shared.bytes);
+        println!("Removed: {} chunks, {} bytes", diff.removed.chunks, diff.removed.bytes);
+        println!("Added:   {} chunks, {} bytes", diff.added.chunks, diff.added.bytes);
+        for range in &diff.removed_ranges {
+            println!("- bytes {}..{} ({} bytes)", range.start, range.end, range.len());
+        }
+        for range in &diff.changed_ranges {
+            println!("+ bytes {}..{} ({} bytes)", range.start, range.end, range.len());
+        }
+        println!("Estimated transfer to sync old into new: {} bytes ({} chunks)", diff.transfer_bytes(), diff.added.chunks);
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_du_command(
+    limit: Option<usize>,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let report = node.disk_usage()?;
+        let short_ids = node.file_registry.short_ids();
+        
+        println!("{:>14} {:>14} {:>14}  FILE", "LOGICAL", "SHARED", "UNIQUE");
+        for usage in report.files.iter().take(limit.unwrap_or(usize::MAX)) {
+            println!("{:>14} {:>14} {:>14}  {} {} v{}",
+                     usage.metadata.total_size, usage.shared_bytes, usage.unique_bytes,
+                     short_ids[&usage.metadata.id], usage.metadata.path, usage.metadata.version);
+        }
+        println!("Total: {} files, {} logical bytes stored in {} bytes",
+                 report.files.len(), report.logical_bytes, report.stored_bytes);
+        println!("Dedup ratio: {:.2}x", report.dedup_ratio());
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_similar_command(
+    file_id: &str,
+    limit: usize,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let parsed_id = node.resolve_file(file_id)?;
+        let similar = node.similar_files(&parsed_id, limit)?;
+        let target = node.file_registry.get_file(&parsed_id).expect("resolved file is registered");
+        
+        if similar.is_empty() {
+            println!("No files share chunks with {} v{}.", target.path, target.version);
+            return Ok(());
+        }
+        println!("Files similar to {} v{} ({} bytes):", target.path, target.version, target.total_size);
+        for file in similar {
+            let percent = if target.total_size == 0 {
+                0.0
+            } else {
+                file.shared_bytes as f64 * 100.0 / target.total_size as f64
+            };
+            println!("  {} - {} v{} - {:.1}% of bytes shared ({} bytes), similarity {:.2}",
+                     node.file_registry.short_id(&file.metadata.id), file.metadata.path, file.metadata.version,
+                     percent, file.shared_bytes, file.similarity);
+        }
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_dupes_command(
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        for line in node.duplicate_report()? {
+            println!("{}", line);
+        }
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_log_command(
+    path: &str,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        for line in node.file_log(path)? {
+            println!("{}", line);
+        }
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_rm_command(
+    file_id: &str,
+    permanent: bool,
+    keep_chunks: bool,
+    _storage: Option<&std::path::PathBuf>,
+    config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    node.trash_retention = config.trash_retention;
+    
+    node.run_command(|node| {
+        let parsed_id = node.resolve_file(file_id)?;
+        if !permanent {
+            let entry = node.trash_file(&parsed_id)?;
+            println!("Moved {} v{} ({}) to the trash", entry.metadata.path, entry.metadata.version, entry.metadata.id);
+            println!("  Restorable for {} with: nebula trash restore {}",
+                     format_duration(node.trash_retention), node.file_registry.short_id(&parsed_id));
+            return Ok(());
         }
         
+        let report = node.remove_file(&parsed_id, keep_chunks)?;
+        println!("Removed {} v{} ({})", report.metadata.path, report.metadata.version, report.metadata.id);
+        println!("  Logical size: {} bytes", report.logical_bytes);
+        if keep_chunks {
+            println!("  Kept {} unreferenced chunks for a later gc run", report.unreferenced_chunks);
+        } else {
+            println!("  Released {} chunks, reclaimed {} bytes", report.released_chunks, report.reclaimed_bytes);
+        }
+        print_unreadable_roots(&report.unreadable_roots);
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_trash_command(
+    action: &TrashCommands,
+    _storage: Option<&std::path::PathBuf>,
+    config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let older_than = match action {
+        TrashCommands::Empty { older_than } => older_than.as_deref().map(parse_duration).transpose()?,
+        _ => None,
+    };
+    
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    node.trash_retention = config.trash_retention;
+    
+    node.run_command(|node| {
+        match action {
+            TrashCommands::List => {
+                let entries = node.file_registry.trash_entries();
+                if entries.is_empty() {
+                    println!("Trash is empty.");
+                    return Ok(());
+                }
+                let now = now_secs();
+                println!("Trash ({} files):", entries.len());
+                for entry in entries {
+                    let remaining = entry.remaining(now, node.trash_retention);
+                    let status = if remaining == 0 {
+                        "expired, will be purged by gc".to_string()
+                    } else {
+                        format!("restorable for {}", format_duration(remaining))
+                    };
+                    println!("  {} - {} v{} ({} bytes) - deleted {} seconds since epoch, {}",
+                             entry.metadata.id, entry.metadata.path, entry.metadata.version,
+                             entry.metadata.total_size, entry.deleted_at, status);
+                }
+            }
+            TrashCommands::Restore { file_id } => {
+                let metadata = node.restore_from_trash(file_id)?;
+                println!("Restored {} v{} ({})", metadata.path, metadata.version, metadata.id);
+            }
+            TrashCommands::Empty { .. } => {
+                let report = node.empty_trash(older_than)?;
+                for entry in &report.purged {
+                    println!("Deleted {} v{} ({})", entry.metadata.path, entry.metadata.version, entry.metadata.id);
+                }
+                println!("Emptied {} files from the trash: released {} chunks, reclaimed {} bytes",
+                         report.purged.len(), report.released_chunks, report.reclaimed_bytes);
+                print_unreadable_roots(&report.unreadable_roots);
+            }
+        }
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_export_registry_command(
+    output: &std::path::Path,
+    format: Option<ExportFormat>,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let format = format.unwrap_or_else(|| {
+        let is_csv = output.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        if is_csv { ExportFormat::Csv } else { ExportFormat::JsonLines }
+    });
+    
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let writer = std::io::BufWriter::new(std::fs::File::create(output)?);
+        let count = node.export_registry(format, writer)?;
+        println!("Exported {} files to {} ({})", count, output.display(), format);
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_import_registry_command(
+    input: &std::path::Path,
+    rekey: bool,
+    chunks_from: Option<&std::path::Path>,
+    allow_missing: bool,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let entries = export::read_entries(std::io::BufReader::new(std::fs::File::open(input)?))?;
+    
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let report = node.import_registry(entries, rekey, chunks_from, allow_missing)?;
+        println!("Imported {} files ({} already present, {} given new IDs)",
+                 report.registry.imported.len(), report.registry.unchanged, report.registry.rekeyed);
+        if chunks_from.is_some() {
+            println!("Copied {} chunks ({} bytes)", report.copied_chunks, report.copied_bytes);
+        }
+        if !report.missing.is_empty() {
+            println!("Warning: {} objects the imported files need are not stored here:", report.missing.len());
+            for address in &report.missing {
+                println!("  {}", address);
+            }
+        }
+        if !report.skipped.is_empty() {
+            println!("Skipped {} files with missing objects (pass --allow-missing to import them anyway):",
+                     report.skipped.len());
+            for metadata in &report.skipped {
+                println!("  {} v{} ({})", metadata.path, metadata.version, metadata.id);
+            }
+        }
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_backup_command(
+    dir: &std::path::Path,
+    name: Option<&str>,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let (backup, summary) = node.backup_directory(dir, name)?;
+        print_skipped(&summary.skipped);
+        println!("Backed up {} as '{}' ({})", backup.source.display(), backup.name, backup.short_id());
+        println!("  {} files ({} unchanged), {} directories, {} bytes",
+                 backup.files, backup.reused, backup.directories, backup.total_size);
+        println!("  Root: {}", backup.root);
+        println!("  Restore with: nebula get {} --recursive --output <dir>", backup.root);
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_backups_command(
+    name: Option<&str>,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let backups = node.backups.list(name)?;
+        if backups.is_empty() {
+            println!("No backups.");
+        }
+        let now = now_secs();
+        for backup in backups {
+            println!("{}  {}  {} ago  {} files, {} bytes  {}",
+                     backup.short_id(), backup.name, format_duration(now.saturating_sub(backup.created_at)),
+                     backup.files, backup.total_size, backup.root);
+        }
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_forget_command(
+    name: Option<&str>,
+    policy: &BackupPolicy,
+    dry_run: bool,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let report = node.forget_backups(name, policy, dry_run)?;
+        let verb = if dry_run { "Would forget" } else { "Forgot" };
+        let now = now_secs();
+        for backup in &report.forgotten {
+            println!("{} {} '{}' taken {} ago", verb, backup.short_id(), backup.name,
+                     format_duration(now.saturating_sub(backup.created_at)));
+        }
+        println!("{} {} backups, kept {}", verb, report.forgotten.len(), report.kept);
+        if !dry_run {
+            println!("Released {} chunks ({} bytes)", report.released_chunks, report.reclaimed_bytes);
+            print_unreadable_roots(&report.unreadable_roots);
+        }
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_snapshot_command(
+    action: &SnapshotCommands,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        match action {
+            SnapshotCommands::Create { name } => {
+                let record = node.create_snapshot(name.clone())?;
+                println!("Created snapshot {} ({} files, {} bytes)", record.id, record.files, record.total_size);
+                println!("  Object: {}", record.address);
+            }
+            SnapshotCommands::List => {
+                let records = node.snapshots.list()?;
+                if records.is_empty() {
+                    println!("No snapshots.");
+                }
+                let now = now_secs();
+                for record in records {
+                    let name = record.name.as_deref().map(|name| format!("  {}", name)).unwrap_or_default();
+                    println!("{}  {} ago  {} files, {} bytes{}",
+                             record.id, format_duration(now.saturating_sub(record.created_at)),
+                             record.files, record.total_size, name);
+                }
+            }
+            SnapshotCommands::Restore { snapshot } => {
+                let restore = node.restore_snapshot(snapshot)?;
+                println!("Restored snapshot {} ({} files)", restore.snapshot.id, restore.snapshot.files);
+                for metadata in &restore.removed {
+                    println!("  Dropped {} v{} ({})", metadata.path, metadata.version, metadata.id);
+                }
+                if !restore.removed.is_empty() {
+                    println!("  Chunks of dropped files are released by the next gc");
+                }
+            }
+            SnapshotCommands::Delete { snapshot } => {
+                let deletion = node.delete_snapshot(snapshot)?;
+                println!("Deleted snapshot {}", deletion.snapshot.id);
+                println!("  Released {} chunks, reclaimed {} bytes", deletion.released_chunks, deletion.reclaimed_bytes);
+                print_unreadable_roots(&deletion.unreadable_roots);
+            }
+        }
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_pin_command(
+    action: &PinCommands,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        match action {
+            PinCommands::Add { file_id, recursive } => {
+                let parsed_id = node.resolve_file(file_id)?;
+                let metadata = node.pin_file(&parsed_id, *recursive)?;
+                println!("Pinned {} v{} ({}, {})", metadata.path, metadata.version, metadata.id, metadata.pin.expect("just pinned"));
+            }
+            PinCommands::Rm { file_id } => {
+                let parsed_id = node.resolve_file(file_id)?;
+                match node.unpin_file(&parsed_id)? {
+                    Some(mode) => println!("Unpinned {} ({})", parsed_id, mode),
+                    None => println!("{} was not pinned", parsed_id),
+                }
+            }
+            PinCommands::Ls => {
+                for line in node.pin_listing()? {
+                    println!("{}", line);
+                }
+            }
+        }
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_ref_command(
+    action: &RefCommands,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        match action {
+            RefCommands::Set { name, target, expect, create } => {
+                let target = node.ref_target(target)?;
+                let condition = match expect {
+                    Some(expected) => RefCondition::Target(node.ref_target(expected)?),
+                    None if *create => RefCondition::Absent,
+                    None => RefCondition::Any,
+                };
+                node.refs.set(name, target.clone(), &condition)?;
+                println!("{} -> {}", name, target);
+            }
+            RefCommands::Show { name } => {
+                let target = node.refs.get(name)?
+                    .ok_or_else(|| format!("Ref not found: {}", name))?;
+                println!("{}", target);
+            }
+            RefCommands::List { prefix } => {
+                let entries: Vec<_> = node.refs.list()?
+                    .into_iter()
+                    .filter(|entry| prefix.as_ref().is_none_or(|prefix| entry.name.starts_with(prefix.as_str())))
+                    .collect();
+                if entries.is_empty() {
+                    println!("No refs.");
+                }
+                for entry in entries {
+                    println!("{} -> {} (updated {} seconds since epoch)", entry.name, entry.target, entry.updated_at);
+                }
+            }
+            RefCommands::Log { name } => {
+                println!("History of ref {}:", name);
+                for update in node.refs.history(name)?.iter().rev() {
+                    match &update.target {
+                        Some(target) => println!("  {} -> {}", update.at, target),
+                        None => println!("  {} deleted", update.at),
+                    }
+                }
+            }
+            RefCommands::Rm { name, expect } => {
+                let condition = match expect {
+                    Some(expected) => RefCondition::Target(node.ref_target(expected)?),
+                    None => RefCondition::Any,
+                };
+                let previous = node.refs.delete(name, &condition)?;
+                println!("Deleted ref {} (was {})", name, previous);
+            }
+        }
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_expire_command(
+    dry_run: bool,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let report = node.expire_files(dry_run)?;
+        if report.expired.is_empty() {
+            println!("No expired files.");
+            return Ok(());
+        }
+        let verb = if dry_run { "Would remove" } else { "Removed" };
+        for metadata in &report.expired {
+            println!("{} {} v{} ({})", verb, metadata.path, metadata.version, metadata.id);
+        }
+        if !dry_run {
+            println!("Released {} chunks ({} bytes)", report.released_chunks, report.reclaimed_bytes);
+            print_unreadable_roots(&report.unreadable_roots);
+        }
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_gc_command(
+    dry_run: bool,
+    _storage: Option<&std::path::PathBuf>,
+    config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.trash_retention = config.trash_retention;
+    
+    node.run_command(|node| {
+        let report = node.collect_garbage(dry_run)?;
+        let verb = if dry_run { "Would delete" } else { "Deleted" };
+        if report.expired_trash > 0 {
+            println!("{} {} expired files from the trash", if dry_run { "Would purge" } else { "Purged" }, report.expired_trash);
+        }
+        println!("Scanned {} chunks", report.scanned_chunks);
+        println!("{} {} unreferenced chunks ({} bytes)", verb, report.unreferenced_chunks, report.reclaimed_bytes);
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_prune_command(
+    path: &str,
+    keep_last: Option<usize>,
+    keep_within: Option<&str>,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let policy = RetentionPolicy {
+        keep_last,
+        keep_within: keep_within.map(parse_duration).transpose()?,
+    };
+    if policy.is_empty() {
+        return Err("Specify at least one of --keep-last or --keep-within".into());
+    }
+    
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let removed = node.prune_versions(path, &policy)?;
+        for metadata in &removed {
+            println!("Pruned {} v{} ({})", metadata.path, metadata.version, metadata.id);
+        }
+        println!("Pruned {} versions of {}", removed.len(), path);
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_get_range_command(
+    file_id: &str,
+    offset: u64,
+    length: Option<u64>,
+    output: &std::path::PathBuf,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let parsed_id = node.resolve_file(file_id)?;
+        
+        let length = length.unwrap_or(u64::MAX - offset);
+        let data = node.read_verified_range(&parsed_id, offset, length)?;
+        std::fs::write(output, &data)?;
+        println!("Verified {} bytes from offset {} written to: {}", data.len(), offset, output.display());
+        
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_prove_command(
+    file_id: &str,
+    chunk_index: usize,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let parsed_id = node.resolve_file(file_id)?;
+        
+        let proof = node.prove_chunk(&parsed_id, chunk_index)?;
+        println!("Chunk {} of {}: {}", proof.leaf_index, proof.leaf_count, proof.leaf);
+        println!("Merkle root: {}", proof.root);
+        println!("Proof: {}", proof);
+        
+        Ok(())
+    })?;
+    
+    Ok(())
+}
+
+fn handle_verify_proof_command(
+    proof: &str,
+    root: &str,
+    chunk: Option<&std::path::PathBuf>
+) -> Result<(), Box<dyn std::error::Error>> {
+    let proof: content::MerkleProof = proof.parse()?;
+    let root: content::ContentAddress = root.parse()
+        .map_err(|e| format!("Invalid Merkle root '{}': {}", root, e))?;
+    
+    let valid = match chunk {
+        Some(chunk_path) => proof.verify_chunk(&std::fs::read(chunk_path)?, &root),
+        None => proof.verify(&root),
+    };
+    
+    if !valid {
+        return Err(format!("Proof is invalid for chunk {} under root {}", proof.leaf_index, root).into());
+    }
+    
+    println!("Proof is valid: chunk {} of {} ({}) is included under root {}",
+             proof.leaf_index, proof.leaf_count, proof.leaf, proof.root);
+    Ok(())
+}
+
+fn handle_verify_command(
+    file_id: Option<&str>,
+    all: bool,
+    _storage: Option<&std::path::PathBuf>,
+    _config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut node = Node::new(
+        "127.0.0.1".to_string(),
+        4001,
+        config::LogLevel::Info,
+        false
+    )?;
+    
+    node.run_command(|node| {
+        let checks = match file_id {
+            Some(file_id) if !all => vec![node.verify_file(&node.resolve_file(file_id)?)?],
+            _ => node.verify_all()?,
+        };
+        
+        let broken = checks.iter().filter(|check| !check.is_ok()).count();
+        for check in &checks {
+            let metadata = &check.metadata;
+            if check.is_ok() {
+                if !all {
+                    println!("OK {} v{} ({})", metadata.path, metadata.version, metadata.id);
+                }
+                continue;
+            }
+            println!("BROKEN {} v{} ({})", metadata.path, metadata.version, metadata.id);
+            for problem in &check.problems {
+                println!("  - {}", problem);
+            }
+        }
+        if all {
+            println!("Checked {} files: {} broken", checks.len(), broken);
+        }
+        
+        if broken > 0 {
+            return Err(format!("{} of {} files are broken", broken, checks.len()).into());
+        }
         Ok(())
     })?;
     
@@ -199,7 +1321,7 @@ fn handle_list_command(
     let mut node = Node::new(
         "127.0.0.1".to_string(),
         4001,
-        crate::config::LogLevel::Info,
+        config::LogLevel::Info,
         false
     )?;
     
@@ -231,7 +1353,7 @@ fn handle_stats_command(
     let mut node = Node::new(
         "127.0.0.1".to_string(),
         4001,
-        crate::config::LogLevel::Info,
+        config::LogLevel::Info,
         false
     )?;
     
@@ -254,7 +1376,7 @@ fn handle_status_command(
     let mut node = Node::new(
         "127.0.0.1".to_string(),
         4001,
-        crate::config::LogLevel::Info,
+        config::LogLevel::Info,
         false
     )?;
     
@@ -295,24 +1417,25 @@ fn handle_stop_command(
 fn handle_list_files_command(
     _storage: Option<&std::path::PathBuf>,
     verbose: bool,
+    filter: &FileFilter,
     _config: &Config
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut node = Node::new(
         "127.0.0.1".to_string(),
         4001,
-        crate::config::LogLevel::Info,
+        config::LogLevel::Info,
         false
     )?;
     
     node.run_command(|node| {
         if verbose {
-            let files = node.list_files_verbose()?;
+            let files = node.list_files_verbose(filter)?;
             println!("Registered Files (detailed):");
             for line in files {
                 println!("{}", line);
             }
         } else {
-            let files = node.list_files()?;
+            let files = node.list_files(filter)?;
             for line in files {
                 println!("{}", line);
             }
@@ -1,16 +1,27 @@
 use uuid::Uuid;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
-use crate::config::{NodeState, LogLevel};
-use crate::storage::{ContentStore, ContentStoreConfig, ChunkConfig};
-use crate::content::ContentAddress;
-use crate::file::{FileRegistry, FileMetadata, FileId};
+use crate::config::{DuplicatePolicy, NodeState, LogLevel};
+use crate::storage::{ContentStore, ContentStoreConfig, ChunkConfig, ChunkReader};
+use crate::storage::store::ContentStoreError;
+use crate::content::{ContentAddress, MerkleProof, MerkleTree, Outboard};
+use crate::content::stream::read_verified_range;
+use crate::file::{FileRegistry, FileRegistryError, FileFilter, FileMetadata, FileId, ImportReport, PinMode, PosixMetadata, Query, QueryOptions, Rename, RestoreOptions, RetentionPolicy, TreeSummary};
+use crate::file::{BackupPolicy, BackupRecord, BackupStore, RefStore, RefTarget, Snapshot, SnapshotRecord, SnapshotStore, StatCache};
+use crate::file::diff::ChunkDiff;
+use crate::file::export::{self, ExportFormat};
+use crate::file::digest::digest_reader;
+use crate::file::mime;
+use crate::file::tree;
+use crate::file::trash::{TrashEntry, DEFAULT_TRASH_RETENTION};
+use crate::file::units::{format_duration, now_secs};
 
 #[derive(Debug, thiserror::Error)]
 pub enum NodeError {
     #[error("Storage error: {0}")]
-    Storage(#[from] crate::storage::store::ContentStoreError),
+    Storage(#[from] ContentStoreError),
     
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -21,12 +32,36 @@ pub enum NodeError {
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
     
+    #[error("Tree error: {0}")]
+    Tree(#[from] crate::file::tree::TreeError),
+    
+    #[error("Query error: {0}")]
+    Query(#[from] crate::file::query::QueryError),
+    
+    #[error("Ref error: {0}")]
+    Ref(#[from] crate::file::RefError),
+    
+    #[error("Export error: {0}")]
+    Export(#[from] crate::file::ExportError),
+    
+    #[error("Snapshot error: {0}")]
+    Snapshot(#[from] crate::file::SnapshotError),
+    
+    #[error("Backup error: {0}")]
+    Backup(#[from] crate::file::BackupError),
+    
     #[error("Content not found")]
     ContentNotFound,
     
     #[error("Node is not running")]
     NotRunning,
     
+    #[error("No file or content matches '{0}'")]
+    ReferenceNotFound(String),
+    
+    #[error("Ambiguous reference '{reference}' matches: {}", .candidates.join(", "))]
+    AmbiguousReference { reference: String, candidates: Vec<String> },
+    
     #[error("General error: {0}")]
     General(String),
 }
@@ -45,6 +80,230 @@ impl From<String> for NodeError {
 
 pub type NodeResult<T> = Result<T, NodeError>;
 
+/// Options controlling how a file is registered on put
+#[derive(Debug, Clone, Default)]
+pub struct PutOptions {
+    /// Logical path to store under (defaults to the file name)
+    pub path: Option<String>,
+    /// Tags to attach to the new entry
+    pub tags: Vec<String>,
+    /// Key/value attributes to attach to the new entry
+    pub attributes: Vec<(String, String)>,
+    /// Store a symlink as a link instead of the file it points to
+    pub keep_symlinks: bool,
+    /// What to do when the same content is already registered
+    pub duplicates: DuplicatePolicy,
+    /// Seconds until the entry expires and `expire` removes it
+    pub ttl: Option<u64>,
+}
+
+/// Outcome of removing a file from the node
+#[derive(Debug, Clone)]
+pub struct RemovalReport {
+    /// The registry entry that was removed
+    pub metadata: FileMetadata,
+    /// Size of the file as seen by users
+    pub logical_bytes: u64,
+    /// Stored objects that no other file references any more
+    pub unreferenced_chunks: usize,
+    /// Unreferenced objects actually deleted (zero with `keep_chunks`)
+    pub released_chunks: usize,
+    /// Disk space freed by deleting them
+    pub reclaimed_bytes: u64,
+    /// Snapshots, backups and the like that could not be read, so nothing
+    /// was released in case they still reference it
+    pub unreadable_roots: Vec<String>,
+}
+
+/// Outcome of permanently deleting entries from the trash
+#[derive(Debug, Clone, Default)]
+pub struct TrashPurgeReport {
+    pub purged: Vec<TrashEntry>,
+    pub released_chunks: usize,
+    pub reclaimed_bytes: u64,
+    pub unreadable_roots: Vec<String>, // Why nothing was released, if anything
+}
+
+/// An inconsistency between a registry entry and the content store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileProblem {
+    /// A chunk or tree object the entry needs is not stored
+    MissingChunk(ContentAddress),
+    /// A stored chunk no longer hashes to its address
+    CorruptChunk(ContentAddress),
+    /// The directory tree could not be walked
+    BrokenTree(String),
+    ChunkCountMismatch { recorded: usize, actual: usize },
+    SizeMismatch { recorded: u64, actual: u64 },
+    MerkleRootMismatch,
+}
+
+impl std::fmt::Display for FileProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileProblem::MissingChunk(address) => write!(f, "missing chunk {}", address),
+            FileProblem::CorruptChunk(address) => write!(f, "chunk {} does not match its address", address),
+            FileProblem::BrokenTree(error) => write!(f, "directory tree is unreadable: {}", error),
+            FileProblem::ChunkCountMismatch { recorded, actual } => {
+                write!(f, "chunk count is {} but {} chunks are listed", recorded, actual)
+            }
+            FileProblem::SizeMismatch { recorded, actual } => {
+                write!(f, "total size is {} bytes but the chunks add up to {}", recorded, actual)
+            }
+            FileProblem::MerkleRootMismatch => write!(f, "Merkle root does not match the chunk list"),
+        }
+    }
+}
+
+/// Result of checking one registry entry against the content store
+#[derive(Debug, Clone)]
+pub struct FileCheck {
+    pub metadata: FileMetadata,
+    pub problems: Vec<FileProblem>,
+}
+
+impl FileCheck {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Outcome of importing a registry export into this node
+#[derive(Debug, Clone, Default)]
+pub struct RegistryImport {
+    pub registry: ImportReport,
+    pub copied_chunks: usize,
+    pub copied_bytes: u64,
+    /// Objects imported entries need that are in neither store
+    pub missing: Vec<ContentAddress>,
+    /// Entries left out because some of their objects are missing
+    pub skipped: Vec<FileMetadata>,
+}
+
+/// Outcome of restoring a catalog snapshot
+#[derive(Debug, Clone)]
+pub struct SnapshotRestore {
+    pub snapshot: SnapshotRecord,
+    /// Entries registered now that the snapshot does not contain
+    pub removed: Vec<FileMetadata>,
+}
+
+/// Outcome of deleting a catalog snapshot
+#[derive(Debug, Clone)]
+pub struct SnapshotDeletion {
+    pub snapshot: SnapshotRecord,
+    pub released_chunks: usize,
+    pub reclaimed_bytes: u64,
+    pub unreadable_roots: Vec<String>, // Why nothing was released, if anything
+}
+
+/// Outcome of applying a retention policy to backups
+#[derive(Debug, Clone, Default)]
+pub struct ForgetReport {
+    pub forgotten: Vec<BackupRecord>,
+    pub kept: usize,
+    pub released_chunks: usize,
+    pub reclaimed_bytes: u64,
+    pub unreadable_roots: Vec<String>, // Why nothing was released, if anything
+}
+
+/// Outcome of removing files whose time-to-live has run out
+#[derive(Debug, Clone, Default)]
+pub struct ExpiryReport {
+    pub expired: Vec<FileMetadata>,
+    pub released_chunks: usize,
+    pub reclaimed_bytes: u64,
+    pub unreadable_roots: Vec<String>, // Why nothing was released, if anything
+}
+
+/// Outcome of a garbage collection pass over the content store
+#[derive(Debug, Clone, Default)]
+pub struct GcReport {
+    /// Trash entries past their retention period that were purged first
+    pub expired_trash: usize,
+    pub scanned_chunks: usize,
+    pub unreferenced_chunks: usize,
+    pub reclaimed_bytes: u64,
+}
+
+/// How much of the store one registered file accounts for
+#[derive(Debug, Clone)]
+pub struct FileUsage {
+    pub metadata: FileMetadata,
+    /// Stored bytes also referenced by other files, trashed entries or refs
+    pub shared_bytes: u64,
+    /// Stored bytes only this file references: what deleting it would free
+    pub unique_bytes: u64,
+}
+
+/// Per-file storage accounting for the whole node
+#[derive(Debug, Clone, Default)]
+pub struct UsageReport {
+    /// Files ordered by unique bytes, largest first
+    pub files: Vec<FileUsage>,
+    /// Combined size of all registered files as users see them
+    pub logical_bytes: u64,
+    /// Size of every object in the content store
+    pub stored_bytes: u64,
+}
+
+impl UsageReport {
+    /// Logical bytes per stored byte (1.0 when nothing is stored)
+    pub fn dedup_ratio(&self) -> f64 {
+        if self.stored_bytes == 0 {
+            1.0
+        } else {
+            self.logical_bytes as f64 / self.stored_bytes as f64
+        }
+    }
+}
+
+/// A registered file that shares chunks with another
+#[derive(Debug, Clone)]
+pub struct SimilarFile {
+    pub metadata: FileMetadata,
+    /// Jaccard similarity of the two sets of distinct chunks, computed
+    /// exactly rather than estimated from MinHash signatures
+    pub similarity: f64,
+    /// Bytes of the queried file that are also stored in this one
+    pub shared_bytes: u64,
+}
+
+/// What a user-supplied ID or prefix refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reference {
+    File(FileId),
+    Content(ContentAddress),
+}
+
+/// What releasing a set of candidate objects did
+#[derive(Debug, Default)]
+struct Release {
+    unreferenced: usize,
+    released: usize,
+    reclaimed: u64,
+    unreadable_roots: Vec<String>,
+}
+
+/// Something that keeps stored objects alive
+enum Root {
+    File(Box<FileMetadata>), // A registered directory or a trashed entry
+    Snapshot(SnapshotRecord),
+    Backup(BackupRecord),
+    Content(String, ContentAddress), // Named by a ref
+}
+
+impl std::fmt::Display for Root {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Root::File(metadata) => write!(f, "file {} v{}", metadata.path, metadata.version),
+            Root::Snapshot(record) => write!(f, "snapshot {}", record.id),
+            Root::Backup(backup) => write!(f, "backup '{}' {}", backup.name, backup.short_id()),
+            Root::Content(name, address) => write!(f, "ref {} ({})", name, address),
+        }
+    }
+}
+
 /// Metadata that persists between node restarts
 #[derive(Debug, Serialize, Deserialize)]
 struct NodeMetadata {
@@ -86,6 +345,10 @@ pub struct Node {
     pub daemon_mode: bool,      // Whether to run as a daemon
     pub content_store: ContentStore, // Content-addressable storage
     pub file_registry: FileRegistry, // File-level metadata registry
+    pub refs: RefStore,         // Mutable names pointing at files or content
+    pub snapshots: SnapshotStore, // Index of catalog snapshots kept in the content store
+    pub backups: BackupStore,   // Index of directory backups kept in the content store
+    pub trash_retention: u64,   // Seconds trashed files keep their chunks alive
 }
 
 impl Node {
@@ -99,12 +362,15 @@ impl Node {
         
         // Create .nebula directory if it doesn't exist
         fs::create_dir_all(&nebula_dir)?;
+        let announce = log_level >= LogLevel::Info;
         
         // Load or create node metadata
         let metadata = if metadata_file.exists() {
             match NodeMetadata::load_from_file(&metadata_file) {
                 Ok(metadata) => {
-                    println!("Loaded existing node ID: {}", metadata.id);
+                    if announce {
+                        println!("Loaded existing node ID: {}", metadata.id);
+                    }
                     metadata
                 }
                 Err(e) => {
@@ -138,6 +404,15 @@ impl Node {
         // Create file registry
         let file_registry = FileRegistry::new(&storage_path)
             .map_err(|e| NodeError::General(format!("Failed to create file registry: {}", e)))?;
+        if let Some(recovery) = file_registry.recovery() {
+            println!("Warning: file registry was unreadable ({}); recovered {} files from {}",
+                     recovery.reason, recovery.files, recovery.backup.display());
+            println!("The damaged registry was moved to {}", recovery.moved_to.display());
+        }
+        
+        let refs = RefStore::new(&storage_path)?;
+        let snapshots = SnapshotStore::new(&storage_path)?;
+        let backups = BackupStore::new(&storage_path)?;
         
         Ok(Node {
             id: metadata.id,
@@ -149,27 +424,39 @@ impl Node {
             daemon_mode,
             content_store,
             file_registry,
+            refs,
+            snapshots,
+            backups,
+            trash_retention: DEFAULT_TRASH_RETENTION,
         })
     }
 
     pub fn start(&mut self) -> NodeResult<()> {
         self.state = NodeState::Starting;
-        println!("Starting node {} on {}:{}", self.id, self.address, self.port);
-        println!("Storage directory: {}", self.storage_dir.display());
+        if self.log_level >= LogLevel::Info {
+            println!("Starting node {} on {}:{}", self.id, self.address, self.port);
+            println!("Storage directory: {}", self.storage_dir.display());
+        }
         
         // TODO: Actual startup logic here (network initialization, etc.)
         self.state = NodeState::Running;
-        println!("Node started successfully");
+        if self.log_level >= LogLevel::Info {
+            println!("Node started successfully");
+        }
         Ok(())
     }
 
     pub fn stop(&mut self) -> NodeResult<()> {
         self.state = NodeState::Stopping;
-        println!("Stopping node {}", self.id);
+        if self.log_level >= LogLevel::Info {
+            println!("Stopping node {}", self.id);
+        }
         
         // TODO: Actual shutdown logic here
         self.state = NodeState::Stopped;
-        println!("Node stopped successfully");
+        if self.log_level >= LogLevel::Info {
+            println!("Node stopped successfully");
+        }
         Ok(())
     }
     
@@ -206,6 +493,11 @@ impl Node {
     
     /// Store a file and register it in the file registry, returning file metadata  
     pub fn put_file_with_registry<P: AsRef<std::path::Path>>(&mut self, file_path: P) -> NodeResult<FileMetadata> {
+        self.put_file_with_options(file_path, &PutOptions::default())
+    }
+    
+    /// Store a file and register it with the given options
+    pub fn put_file_with_options<P: AsRef<std::path::Path>>(&mut self, file_path: P, options: &PutOptions) -> NodeResult<FileMetadata> {
         if !self.is_running() {
             return Err(NodeError::NotRunning);
         }
@@ -213,28 +505,196 @@ impl Node {
         let path = file_path.as_ref();
         println!("Storing file with registry: {}", path.display());
         
-        // Get file size
-        let file_size = fs::metadata(path)?.len();
-        
-        // Store the file and get chunk addresses
-        let addresses = self.content_store.put_file(path)?;
+        // Capture mode, times, ownership and xattrs; symlinks are followed
+        // and their target stored unless asked to keep them as links
+        let posix = if options.keep_symlinks {
+            PosixMetadata::capture(path)?
+        } else {
+            PosixMetadata::capture(path.canonicalize()?)?
+        };
         
         // Get the original filename
         let original_name = path.file_name()
             .and_then(|name| name.to_str())
             .unwrap_or("unknown")
             .to_string();
+        let logical_path = options.path.clone().unwrap_or_else(|| original_name.clone());
+        
+        // Read the file once, so the digest describes exactly the bytes that get chunked
+        let data = if posix.is_symlink() { None } else { Some(fs::read(path)?) };
+        let digest = data.as_deref().map(digest_reader).transpose()?;
+        if let Some(digest) = &digest {
+            if let Some(existing) = self.find_duplicate(digest, &logical_path)? {
+                let short_id = self.file_registry.short_id(&existing.id);
+                match options.duplicates {
+                    // The existing entry stands in for this put, wherever it
+                    // is registered; it takes on the requested tags, attributes and TTL
+                    DuplicatePolicy::Reuse => {
+                        println!("Identical content is already {} v{} ({}); reusing it",
+                                 existing.path, existing.version, short_id);
+                        return self.apply_reuse_options(existing, options);
+                    }
+                    DuplicatePolicy::Warn => {
+                        println!("Warning: identical content is already registered as {} ({} v{})",
+                                 short_id, existing.path, existing.version);
+                    }
+                    DuplicatePolicy::New => {}
+                }
+            }
+        }
+        
+        // Store the content and get chunk addresses; symlinks have none
+        let (addresses, file_size) = match &data {
+            Some(data) => (self.content_store.put_data(data)?, data.len() as u64),
+            None => (Vec::new(), 0),
+        };
         
-        // Register the file in the registry
-        let metadata = self.file_registry.register_file(original_name, addresses, file_size)
+        // Register the complete entry in one go, as a new version if the path exists
+        let mut metadata = FileMetadata::new(original_name, addresses, file_size);
+        metadata.path = logical_path;
+        let metadata = self.prepare_entry(metadata, options, Some(posix), digest)?;
+        let metadata = self.file_registry.register(metadata)
             .map_err(|e| NodeError::General(format!("Failed to register file: {}", e)))?;
         
-        println!("File stored and registered with ID: {} ({} chunks)", 
-                 metadata.short_id(), metadata.chunk_count);
+        println!("File stored and registered with ID: {} ({} chunks, {} v{})", 
+                 self.file_registry.short_id(&metadata.id), metadata.chunk_count,
+                 metadata.path, metadata.version);
+        
+        Ok(metadata)
+    }
+    
+    /// The registered file with content `digest` that a put to
+    /// `logical_path` should be compared against: the latest version at
+    /// that path if it matches, otherwise the newest match anywhere
+    fn find_duplicate(&self, digest: &str, logical_path: &str) -> NodeResult<Option<FileMetadata>> {
+        // Entries about to be expired are not worth reusing
+        let expired = self.file_registry.expired_ids(now_secs());
+        let candidates = self.file_registry.files_with_digest(digest)
+            .map_err(|e| NodeError::General(format!("Failed to look up duplicates: {}", e)))?;
+        let at_path = self.file_registry.latest_version(logical_path)
+            .filter(|latest| latest.digest.as_deref() == Some(digest) && !expired.contains(&latest.id));
         
+        Ok(at_path
+            .or_else(|| candidates.into_iter()
+                .filter(|metadata| !expired.contains(&metadata.id))
+                .max_by_key(|metadata| (metadata.created_at, metadata.id)))
+            .cloned())
+    }
+    
+    /// Apply the user metadata requested on put to an existing entry that
+    /// is being reused in place of a new version; a TTL counts from now
+    fn apply_reuse_options(&mut self, existing: FileMetadata, options: &PutOptions) -> NodeResult<FileMetadata> {
+        let now = now_secs();
+        self.file_registry.update_file(&existing.id, |metadata| {
+            metadata.tags.extend(options.tags.iter().cloned());
+            metadata.attributes.extend(options.attributes.iter().cloned());
+            if let Some(ttl) = options.ttl {
+                metadata.expires_at = Some(now.saturating_add(ttl));
+            }
+        })
+        .cloned()
+        .map_err(|e| NodeError::General(format!("Failed to set file metadata: {}", e)))
+    }
+    
+    /// Attach the user metadata requested on put, and what was learned
+    /// about the content, to an entry before it is registered
+    fn prepare_entry(&self, mut metadata: FileMetadata, options: &PutOptions, posix: Option<PosixMetadata>, digest: Option<String>) -> NodeResult<FileMetadata> {
+        metadata.mime_type = Some(self.detect_mime_type(&metadata, posix.as_ref())?);
+        metadata.tags.extend(options.tags.iter().cloned());
+        metadata.attributes.extend(options.attributes.iter().cloned());
+        metadata.posix = posix;
+        metadata.digest = digest;
+        metadata.expires_at = options.ttl.map(|ttl| metadata.created_at.saturating_add(ttl));
         Ok(metadata)
     }
     
+    /// Sniff the MIME type of a stored entry from the start of its first chunk
+    fn detect_mime_type(&self, metadata: &FileMetadata, posix: Option<&PosixMetadata>) -> NodeResult<String> {
+        if metadata.is_directory() {
+            return Ok(mime::DIRECTORY.to_string());
+        }
+        if posix.is_some_and(PosixMetadata::is_symlink) {
+            return Ok(mime::SYMLINK.to_string());
+        }
+        
+        let head = match metadata.chunk_addresses.first() {
+            Some(address) => {
+                let chunk = self.content_store.get_chunk(address)?;
+                chunk.data()[..chunk.data().len().min(mime::SNIFF_LEN)].to_vec()
+            }
+            None => Vec::new(),
+        };
+        Ok(mime::sniff(&head, &metadata.original_name))
+    }
+    
+    /// Add and remove tags on a registered file
+    pub fn update_tags(&mut self, file_id: &FileId, add: &[String], remove: &[String]) -> NodeResult<FileMetadata> {
+        self.file_registry.update_tags(file_id, add, remove)
+            .cloned()
+            .map_err(|e| NodeError::General(format!("Failed to update tags: {}", e)))
+    }
+    
+    /// Set and unset attributes on a registered file
+    pub fn update_attributes(&mut self, file_id: &FileId, set: &[(String, String)], unset: &[String]) -> NodeResult<FileMetadata> {
+        self.file_registry.update_attributes(file_id, set, unset)
+            .cloned()
+            .map_err(|e| NodeError::General(format!("Failed to update attributes: {}", e)))
+    }
+    
+    /// Move a file (with all versions of its path) to a new logical path
+    pub fn rename_file(&mut self, file_id: &FileId, new_path: &str, unique: bool) -> NodeResult<Vec<FileMetadata>> {
+        self.file_registry.rename(file_id, new_path, unique)
+            .map_err(|e| NodeError::General(format!("Failed to rename file: {}", e)))
+    }
+    
+    /// Recursively store a directory as tree objects and register its root
+    pub fn put_directory_with_registry<P: AsRef<std::path::Path>>(&mut self, dir_path: P, options: &PutOptions) -> NodeResult<(FileMetadata, TreeSummary)> {
+        if !self.is_running() {
+            return Err(NodeError::NotRunning);
+        }
+        
+        let path = dir_path.as_ref();
+        println!("Storing directory with registry: {}", path.display());
+        
+        let summary = tree::store_directory(&self.content_store, path)?;
+        let root = summary.root.clone().ok_or("Directory produced no root tree")?;
+        
+        let original_name = path.canonicalize()?
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+        
+        let logical_path = options.path.clone().unwrap_or_else(|| original_name.clone());
+        let mut metadata = FileMetadata::new(original_name, Vec::new(), summary.total_size);
+        metadata.path = logical_path;
+        metadata.tree_root = Some(root.clone());
+        let metadata = self.prepare_entry(metadata, options, None, None)?;
+        let metadata = self.file_registry.register(metadata)
+            .map_err(|e| NodeError::General(format!("Failed to register directory: {}", e)))?;
+        
+        println!("Directory stored with root {} ({} files, {} directories, {} symlinks)",
+                 root, summary.files, summary.directories, summary.symlinks);
+        
+        Ok((metadata, summary))
+    }
+    
+    /// Rebuild a directory tree from its root address
+    pub fn get_directory<P: AsRef<std::path::Path>>(
+        &self,
+        root: &ContentAddress,
+        output_path: P
+    ) -> NodeResult<TreeSummary> {
+        if !self.is_running() {
+            return Err(NodeError::NotRunning);
+        }
+        
+        println!("Restoring tree {} to: {}", root, output_path.as_ref().display());
+        let summary = tree::restore_directory(&self.content_store, root, output_path)?;
+        println!("Restored {} files in {} directories, {} symlinks", summary.files, summary.directories, summary.symlinks);
+        Ok(summary)
+    }
+    
     /// Retrieve a file by its content addresses
     pub fn get_file<P: AsRef<std::path::Path>>(
         &self, 
@@ -256,6 +716,17 @@ impl Node {
         &self,
         file_id: &FileId,
         output_path: P
+    ) -> NodeResult<()> {
+        self.get_file_with_options(file_id, output_path, RestoreOptions::default())
+    }
+    
+    /// Retrieve a file by its file ID, restoring the selected POSIX metadata.
+    /// Symlinks are always recreated as links.
+    pub fn get_file_with_options<P: AsRef<std::path::Path>>(
+        &self,
+        file_id: &FileId,
+        output_path: P,
+        restore: RestoreOptions
     ) -> NodeResult<()> {
         if !self.is_running() {
             return Err(NodeError::NotRunning);
@@ -265,142 +736,1279 @@ impl Node {
         let metadata = self.file_registry.get_file(file_id)
             .ok_or_else(|| NodeError::General(format!("File not found: {}", file_id)))?;
         
+        if metadata.is_directory() {
+            return Err(NodeError::General(format!(
+                "'{}' is a directory; use get -r to restore it", metadata.original_name
+            )));
+        }
+        
         println!("Retrieving file '{}' ({} chunks) to: {}", 
                  metadata.original_name, 
                  metadata.chunk_count, 
                  output_path.as_ref().display());
         
-        // Use the existing get_file method with the chunk addresses
-        self.content_store.get_file(&metadata.chunk_addresses, output_path)?;
+        let output_path = output_path.as_ref();
+        match metadata.posix.as_ref().and_then(|posix| posix.symlink_target.as_deref()) {
+            Some(target) => crate::file::posix::create_symlink(target, output_path)?,
+            // Use the existing get_file method with the chunk addresses
+            None => self.content_store.get_file(&metadata.chunk_addresses, output_path)?,
+        }
+        
+        if let Some(posix) = &metadata.posix {
+            for warning in posix.apply(output_path, restore)? {
+                println!("Warning: {}", warning);
+            }
+        }
         println!("File '{}' retrieved successfully", metadata.original_name);
         Ok(())
     }
     
-    /// Retrieve a file by its short ID
-    pub fn get_file_by_short_id<P: AsRef<std::path::Path>>(
-        &self,
-        short_id: &str,
-        output_path: P
-    ) -> NodeResult<()> {
-        if !self.is_running() {
-            return Err(NodeError::NotRunning);
+    /// Resolve a full or abbreviated file ID or content address, a logical
+    /// path or a ref name
+    pub fn resolve(&self, reference: &str) -> NodeResult<Reference> {
+        let files = self.file_registry.files_with_prefix(reference);
+        let addresses = self.content_store.addresses_with_prefix(reference)?;
+        
+        match (files.as_slice(), addresses.as_slice()) {
+            ([], []) => match self.file_registry.latest_version(reference) {
+                Some(metadata) => Ok(Reference::File(metadata.id)),
+                None => self.resolve_ref(reference)?
+                    .ok_or_else(|| NodeError::ReferenceNotFound(reference.to_string())),
+            },
+            ([file], []) => Ok(Reference::File(file.id)),
+            ([], [address]) => Ok(Reference::Content(address.clone())),
+            _ => Err(NodeError::AmbiguousReference {
+                reference: reference.to_string(),
+                candidates: files
+                    .iter()
+                    .map(|file| format!("file {} ({})", file.id, file.original_name))
+                    .chain(addresses.iter().map(|address| format!("chunk {}", address)))
+                    .collect(),
+            }),
+        }
+    }
+    
+    /// Resolve a full or abbreviated file ID, or a logical path, to a registered file
+    pub fn resolve_file(&self, reference: &str) -> NodeResult<FileId> {
+        self.file_registry.resolve_prefix(reference)
+            .map(|metadata| metadata.id)
+            .or_else(|e| match e {
+                FileRegistryError::NoMatch(_) => self.file_registry.latest_version(reference)
+                    .map(|metadata| metadata.id)
+                    .ok_or(e),
+                other => Err(other),
+            })
+            .or_else(|e| match (e, self.resolve_ref(reference)?) {
+                (_, Some(Reference::File(file_id))) => Ok(file_id),
+                (_, Some(Reference::Content(address))) => Err(NodeError::General(format!(
+                    "Ref '{}' points at content {}, not a registered file", reference, address
+                ))),
+                (FileRegistryError::AmbiguousPrefix { prefix, candidates }, None) => Err(NodeError::AmbiguousReference {
+                    reference: prefix,
+                    candidates,
+                }),
+                (FileRegistryError::NoMatch(prefix), None) => Err(NodeError::ReferenceNotFound(prefix)),
+                (other, None) => Err(NodeError::General(other.to_string())),
+            })
+    }
+    
+    /// Resolve a ref name to what it currently points at
+    pub fn resolve_ref(&self, name: &str) -> NodeResult<Option<Reference>> {
+        match self.refs.get(name)? {
+            Some(RefTarget::File(file_id)) if self.file_registry.get_file(&file_id).is_none() => Err(NodeError::General(
+                format!("Ref '{}' points at file {}, which is no longer registered", name, file_id)
+            )),
+            Some(RefTarget::File(file_id)) => Ok(Some(Reference::File(file_id))),
+            Some(RefTarget::Content(address)) => Ok(Some(Reference::Content(address))),
+            None => Ok(None),
         }
+    }
+    
+    /// Resolve anything `get` accepts to a target a ref can point at
+    pub fn ref_target(&self, reference: &str) -> NodeResult<RefTarget> {
+        Ok(match self.resolve(reference)? {
+            Reference::File(file_id) => RefTarget::File(file_id),
+            Reference::Content(address) => RefTarget::Content(address),
+        })
+    }
+    
+    /// Resolve a specific version of a logical path, by number or by point in time
+    pub fn resolve_version(&self, path: &str, version: Option<u32>, at: Option<u64>) -> NodeResult<FileId> {
+        let metadata = match (version, at) {
+            (Some(version), _) => self.file_registry.get_version(path, version),
+            (None, Some(timestamp)) => self.file_registry.version_at(path, timestamp),
+            (None, None) => self.file_registry.latest_version(path),
+        };
         
-        // Get file metadata from registry using short ID
-        let metadata = self.file_registry.get_file_by_short_id(short_id)
-            .ok_or_else(|| NodeError::General(format!("File not found with short ID: {}", short_id)))?;
+        metadata
+            .map(|metadata| metadata.id)
+            .ok_or_else(|| NodeError::General(format!("No matching version of '{}'", path)))
+    }
+    
+    /// Show the version history of a logical path, newest first
+    pub fn file_log(&self, path: &str) -> NodeResult<Vec<String>> {
+        let versions = self.file_registry.versions(path);
+        if versions.is_empty() {
+            return Err(NodeError::General(format!("No versions stored under '{}'", path)));
+        }
         
-        println!("Retrieving file '{}' (ID: {}, {} chunks) to: {}", 
-                 metadata.original_name,
-                 metadata.short_id(), 
-                 metadata.chunk_count, 
-                 output_path.as_ref().display());
+        let short_ids = self.file_registry.short_ids();
+        let mut result = vec![format!("History of {} ({} versions):", path, versions.len())];
+        // Versions move together and each records the same renames
+        let mut renames: Vec<&Rename> = Vec::new();
+        for rename in versions.iter().flat_map(|metadata| &metadata.renames) {
+            if !renames.contains(&rename) {
+                renames.push(rename);
+            }
+        }
+        renames.sort_by_key(|rename| rename.at);
+        for rename in renames.iter().rev() {
+            result.push(format!("  renamed from {} ({} seconds since epoch)", rename.from, rename.at));
+        }
+        for metadata in versions.iter().rev() {
+            let parent = metadata.parent
+                .map(|parent| short_ids.get(&parent).cloned().unwrap_or_else(|| format!("{} (pruned)", parent)))
+                .unwrap_or_else(|| "none".to_string());
+            result.push(format!(
+                "  v{} {} - {} ({} bytes) - {} - parent: {}",
+                metadata.version,
+                short_ids[&metadata.id],
+                metadata.original_name,
+                metadata.total_size,
+                metadata.created_time_string(),
+                parent
+            ));
+        }
         
-        // Use the existing get_file method with the chunk addresses
-        self.content_store.get_file(&metadata.chunk_addresses, output_path)?;
-        println!("File '{}' retrieved successfully", metadata.original_name);
+        Ok(result)
+    }
+    
+    /// Drop old versions of a logical path according to a retention policy
+    pub fn prune_versions(&mut self, path: &str, policy: &RetentionPolicy) -> NodeResult<Vec<FileMetadata>> {
+        self.file_registry.prune_versions(path, policy)
+            .map_err(|e| NodeError::General(format!("Failed to prune versions: {}", e)))
+    }
+    
+    /// Add every stored object a registry entry depends on to `objects`
+    fn collect_file_objects(&self, metadata: &FileMetadata, objects: &mut HashSet<ContentAddress>) -> NodeResult<()> {
+        objects.extend(metadata.chunk_addresses.iter().cloned());
+        if let Some(root) = &metadata.tree_root {
+            tree::collect_objects(&self.content_store, root, objects)?;
+        }
         Ok(())
     }
     
-    /// Get storage statistics
-    pub fn get_stats(&self) -> NodeResult<Vec<String>> {
-        let stats = self.content_store.stats()?;
-        let listing = self.content_store.list_content()?;
-        let file_count = self.file_registry.file_count();
-        let file_total_size = self.file_registry.total_size();
-        
-        let mut result = Vec::new();
-        result.push("Storage Statistics:".to_string());
-        result.push(format!("  Total chunks: {}", stats.total_chunks));
-        result.push(format!("  Total chunk size: {} bytes", stats.total_size));
-        result.push(format!("  Registered files: {}", file_count));
-        result.push(format!("  Total file size: {} bytes", file_total_size));
-        result.push(format!("  Storage path: {}", stats.storage_path.display()));
-        
-        if !listing.chunks.is_empty() {
-            let avg_chunk_size = stats.total_size as f64 / stats.total_chunks as f64;
-            result.push(format!("  Average chunk size: {:.1} bytes", avg_chunk_size));
-            
-            if file_count > 0 {
-                let avg_file_size = file_total_size as f64 / file_count as f64;
-                result.push(format!("  Average file size: {:.1} bytes", avg_file_size));
+    /// Every stored object that must be kept: anything referenced by a
+    /// registered file or directory, or retained for another reason
+    pub fn live_objects(&self) -> NodeResult<HashSet<ContentAddress>> {
+        let mut live = self.retained_objects()?;
+        for metadata in self.file_registry.list_files() {
+            self.collect_file_objects(metadata, &mut live)?;
+        }
+        Ok(live)
+    }
+    
+    /// Objects kept alive by something other than a registered file
+    fn retained_objects(&self) -> NodeResult<HashSet<ContentAddress>> {
+        let mut live = HashSet::new();
+        for root in self.retained_roots()? {
+            self.collect_root_objects(&root, &mut live)?;
+        }
+        Ok(live)
+    }
+    
+    /// What keeps objects alive besides registered files: trashed entries
+    /// still within the retention period, refs pointing at content, catalog
+    /// snapshots and directory backups
+    fn retained_roots(&self) -> NodeResult<Vec<Root>> {
+        let now = now_secs();
+        let mut roots: Vec<Root> = self.file_registry.trash_entries()
+            .into_iter()
+            .filter(|entry| !entry.is_expired(now, self.trash_retention))
+            .map(|entry| Root::File(Box::new(entry.metadata.clone())))
+            .collect();
+        roots.extend(self.snapshots.list()?.into_iter().map(Root::Snapshot));
+        roots.extend(self.backups.list(None)?.into_iter().map(Root::Backup));
+        for entry in self.refs.list()? {
+            if let RefTarget::Content(address) = entry.target {
+                roots.push(Root::Content(entry.name, address));
             }
-            
-            // Find largest and smallest chunks
-            let largest = listing.chunks.iter().max_by_key(|c| c.size);
-            let smallest = listing.chunks.iter().min_by_key(|c| c.size);
-            
-            if let Some(largest) = largest {
-                result.push(format!("  Largest chunk: {} bytes", largest.size));
+        }
+        Ok(roots)
+    }
+    
+    fn collect_root_objects(&self, root: &Root, objects: &mut HashSet<ContentAddress>) -> NodeResult<()> {
+        match root {
+            Root::File(metadata) => self.collect_file_objects(metadata, objects)?,
+            Root::Snapshot(record) => self.collect_snapshot_objects(record, objects)?,
+            Root::Backup(backup) => {
+                tree::collect_objects(&self.content_store, &backup.root, objects)?;
+                objects.insert(backup.stats.clone());
             }
-            if let Some(smallest) = smallest {
-                result.push(format!("  Smallest chunk: {} bytes", smallest.size));
+            // Walked as a tree when it is one
+            Root::Content(_, address) => {
+                if tree::load_tree(&self.content_store, address).is_ok() {
+                    tree::collect_objects(&self.content_store, address, objects)?;
+                } else {
+                    objects.insert(address.clone());
+                }
             }
         }
-        
-        Ok(result)
+        Ok(())
     }
     
-    /// List all registered files
-    pub fn list_files(&self) -> NodeResult<Vec<String>> {
-        let files = self.file_registry.list_files();
-        let mut result = Vec::new();
-        
-        if files.is_empty() {
-            result.push("No files registered.".to_string());
-        } else {
-            result.push(format!("Registered Files ({}):", files.len()));
-            for file in files {
-                result.push(format!(
-                    "  {} - {} ({} bytes, {} chunks) - {}",
-                    file.short_id(),
-                    file.original_name,
-                    file.total_size,
-                    file.chunk_count,
-                    file.created_time_string()
-                ));
+    /// Those of `candidates` nothing references any more. Chunks of
+    /// registered files are looked up in the chunk index; directories and
+    /// retained roots are walked one at a time, stopping as soon as every
+    /// candidate is accounted for. Roots that cannot be read are returned
+    /// instead, with no candidates, as they might reference any of them.
+    fn unreferenced_objects(&self, candidates: &HashSet<ContentAddress>) -> NodeResult<(Vec<ContentAddress>, Vec<String>)> {
+        let mut remaining = HashSet::new();
+        for address in candidates {
+            let files = self.file_registry.files_with_chunk(address)
+                .map_err(|e| NodeError::General(format!("Failed to look up chunk references: {}", e)))?;
+            if files.is_empty() {
+                remaining.insert(address);
             }
         }
         
-        Ok(result)
+        let mut roots: Vec<Root> = self.file_registry.list_files()
+            .into_iter()
+            .filter(|metadata| metadata.is_directory())
+            .map(|metadata| Root::File(Box::new(metadata.clone())))
+            .collect();
+        roots.extend(self.retained_roots()?);
+        
+        let mut unreadable = Vec::new();
+        for root in &roots {
+            if remaining.is_empty() {
+                break;
+            }
+            let mut objects = HashSet::new();
+            match self.collect_root_objects(root, &mut objects) {
+                Ok(()) => remaining.retain(|address| !objects.contains(*address)),
+                Err(e) => unreadable.push(format!("{}: {}", root, e)),
+            }
+        }
+        if !unreadable.is_empty() {
+            return Ok((Vec::new(), unreadable));
+        }
+        Ok((remaining.into_iter().cloned().collect(), unreadable))
     }
     
-    /// List all registered files with detailed information
-    pub fn list_files_verbose(&self) -> NodeResult<Vec<String>> {
-        let files = self.file_registry.list_files();
-        let mut result = Vec::new();
+    /// Pin a file so no removal path can delete it. A recursive pin also
+    /// holds every entry registered below the file's logical path.
+    pub fn pin_file(&mut self, file_id: &FileId, recursive: bool) -> NodeResult<FileMetadata> {
+        let mode = if recursive { PinMode::Recursive } else { PinMode::Direct };
+        self.file_registry.pin(file_id, mode)
+            .map_err(|e| NodeError::General(format!("Failed to pin file: {}", e)))
+    }
+    
+    /// Remove a file's pin, returning the mode it had
+    pub fn unpin_file(&mut self, file_id: &FileId) -> NodeResult<Option<PinMode>> {
+        self.file_registry.unpin(file_id)
+            .map_err(|e| NodeError::General(format!("Failed to unpin file: {}", e)))
+    }
+    
+    /// Every object referenced by an entry some pin holds
+    pub fn pinned_objects(&self) -> NodeResult<HashSet<ContentAddress>> {
+        let mut pinned = HashSet::new();
+        for file_id in self.file_registry.pin_holders().keys() {
+            if let Some(metadata) = self.file_registry.get_file(file_id) {
+                self.collect_file_objects(metadata, &mut pinned)?;
+            }
+        }
+        Ok(pinned)
+    }
+    
+    /// One line per pin with its mode and what it holds
+    pub fn pin_listing(&self) -> NodeResult<Vec<String>> {
+        let pinned = self.file_registry.pinned_files();
+        if pinned.is_empty() {
+            return Ok(vec!["No pinned files.".to_string()]);
+        }
         
-        if files.is_empty() {
-            result.push("No files registered.".to_string());
-        } else {
-            result.push(format!("Registered Files ({}):", files.len()));
-            for file in files {
-                result.push(format!("File ID: {}", file.id));
-                result.push(format!("  Name: {}", file.original_name));
-                result.push(format!("  Size: {} bytes", file.total_size));
-                result.push(format!("  Chunks: {} parts", file.chunk_count));
-                result.push(format!("  Created: {}", file.created_time_string()));
-                result.push("  Chunk addresses:".to_string());
-                for (i, addr) in file.chunk_addresses.iter().enumerate() {
-                    result.push(format!("    [{}] {}", i + 1, addr));
+        let holders = self.file_registry.pin_holders();
+        let short_ids = self.file_registry.short_ids();
+        let mut result = vec![format!("Pinned Files ({}):", pinned.len())];
+        for metadata in pinned {
+            let held: Vec<&FileId> = holders.iter()
+                .filter(|(_, holder)| holder.id == metadata.id)
+                .map(|(file_id, _)| file_id)
+                .collect();
+            let mut objects = HashSet::new();
+            for file_id in &held {
+                if let Some(entry) = self.file_registry.get_file(file_id) {
+                    self.collect_file_objects(entry, &mut objects)?;
                 }
-                result.push("".to_string()); // Empty line
             }
+            let bytes: u64 = objects.iter().map(|address| self.content_store.chunk_size(address).unwrap_or(0)).sum();
+            result.push(format!("  {}  {:<9}  {} v{} ({} entries, {} objects, {} bytes)",
+                                short_ids[&metadata.id], metadata.pin.expect("pinned"), metadata.path,
+                                metadata.version, held.len(), objects.len(), bytes));
         }
-        
         Ok(result)
     }
-    pub fn list_content(&self) -> NodeResult<Vec<String>> {
-        let listing = self.content_store.list_content()?;
-        let mut result = Vec::new();
+    
+    /// Record the current set of registered files as an immutable snapshot
+    pub fn create_snapshot(&mut self, name: Option<String>) -> NodeResult<SnapshotRecord> {
+        if !self.is_running() {
+            return Err(NodeError::NotRunning);
+        }
         
-        if listing.chunks.is_empty() {
+        let snapshot = Snapshot::new(self.file_registry.list_files(), now_secs());
+        let address = self.content_store.put_chunk(&serde_json::to_vec(&snapshot)?)?;
+        let record = SnapshotRecord::new(&snapshot, address, name);
+        self.snapshots.add(&record)?;
+        Ok(record)
+    }
+    
+    /// Read a snapshot's file set back from the content store
+    pub fn load_snapshot(&self, record: &SnapshotRecord) -> NodeResult<Snapshot> {
+        let chunk = self.content_store.get_chunk(&record.address)?;
+        Ok(serde_json::from_slice(chunk.data())?)
+    }
+    
+    /// The snapshot object and every object of the files it holds
+    fn collect_snapshot_objects(&self, record: &SnapshotRecord, objects: &mut HashSet<ContentAddress>) -> NodeResult<()> {
+        for metadata in &self.load_snapshot(record)?.files {
+            self.collect_file_objects(metadata, objects)?;
+        }
+        objects.insert(record.address.clone());
+        Ok(())
+    }
+    
+    /// Make the registered files exactly those of a snapshot, by name or ID
+    /// prefix. Entries the snapshot lacks are dropped from the registry;
+    /// their chunks stay until the next gc.
+    pub fn restore_snapshot(&mut self, reference: &str) -> NodeResult<SnapshotRestore> {
+        if !self.is_running() {
+            return Err(NodeError::NotRunning);
+        }
+        
+        let snapshot = self.snapshots.resolve(reference)?;
+        let files = self.load_snapshot(&snapshot)?.files;
+        let removed = self.file_registry.replace_files(files)
+            .map_err(|e| NodeError::General(format!("Failed to restore snapshot: {}", e)))?;
+        Ok(SnapshotRestore { snapshot, removed })
+    }
+    
+    /// Delete a snapshot and release the objects only it kept alive
+    pub fn delete_snapshot(&mut self, reference: &str) -> NodeResult<SnapshotDeletion> {
+        if !self.is_running() {
+            return Err(NodeError::NotRunning);
+        }
+        
+        let snapshot = self.snapshots.resolve(reference)?;
+        let mut candidates = HashSet::new();
+        self.collect_snapshot_objects(&snapshot, &mut candidates)?;
+        self.snapshots.remove(&snapshot.id)?;
+        
+        let release = self.release_objects(&candidates, false)?;
+        Ok(SnapshotDeletion {
+            snapshot,
+            released_chunks: release.released,
+            reclaimed_bytes: release.reclaimed,
+            unreadable_roots: release.unreadable_roots,
+        })
+    }
+    
+    /// Back up a directory as a tree recorded under `name` (the directory's
+    /// name by default). Files whose size, modification time and inode match
+    /// the previous backup of that name are reused without being read.
+    /// The tree summary lists anything that could not be backed up.
+    pub fn backup_directory<P: AsRef<Path>>(&mut self, dir: P, name: Option<&str>) -> NodeResult<(BackupRecord, TreeSummary)> {
+        if !self.is_running() {
+            return Err(NodeError::NotRunning);
+        }
+        
+        let source = dir.as_ref().canonicalize()?;
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => source.file_name()
+                .and_then(|name| name.to_str())
+                .ok_or_else(|| NodeError::General(format!("Cannot name a backup of {}; pass a name", source.display())))?
+                .to_string(),
+        };
+        
+        // A stat cache that is gone or unreadable only costs a full read
+        let previous: StatCache = self.backups.latest(&name)?
+            .and_then(|latest| self.content_store.get_chunk(&latest.stats).ok())
+            .and_then(|chunk| serde_json::from_slice(chunk.data()).ok())
+            .unwrap_or_default();
+        let (summary, stats) = tree::store_directory_incremental(&self.content_store, &source, &previous)?;
+        let root = summary.root.clone().ok_or("Directory produced no root tree")?;
+        
+        let record = BackupRecord {
+            id: Uuid::now_v7(),
+            name,
+            source,
+            created_at: now_secs(),
+            root,
+            stats: self.content_store.put_chunk(&serde_json::to_vec(&stats)?)?,
+            files: summary.files,
+            directories: summary.directories,
+            total_size: summary.total_size,
+            reused: summary.reused,
+        };
+        self.backups.add(&record)?;
+        Ok((record, summary))
+    }
+    
+    /// Forget backups the policy does not keep, applied to each backup name
+    /// separately (or only to `name`), and release objects nothing else uses
+    pub fn forget_backups(&mut self, name: Option<&str>, policy: &BackupPolicy, dry_run: bool) -> NodeResult<ForgetReport> {
+        if !self.is_running() {
+            return Err(NodeError::NotRunning);
+        }
+        if policy.is_empty() {
+            return Err(NodeError::General("A retention policy needs at least one keep rule".to_string()));
+        }
+        
+        let mut by_name: HashMap<String, Vec<BackupRecord>> = HashMap::new();
+        for backup in self.backups.list(name)? {
+            by_name.entry(backup.name.clone()).or_default().push(backup);
+        }
+        
+        let mut report = ForgetReport::default();
+        let mut forget = Vec::new();
+        for backups in by_name.values() {
+            let records: Vec<&BackupRecord> = backups.iter().collect();
+            let ids = policy.backups_to_forget(&records);
+            report.kept += backups.len() - ids.len();
+            forget.extend(ids);
+        }
+        
+        report.forgotten = self.backups.list(name)?
+            .into_iter()
+            .filter(|backup| forget.contains(&backup.id))
+            .collect();
+        report.forgotten.sort_by(|a, b| a.name.cmp(&b.name).then(a.created_at.cmp(&b.created_at)));
+        if dry_run || forget.is_empty() {
+            return Ok(report);
+        }
+        
+        let mut candidates = HashSet::new();
+        for backup in &report.forgotten {
+            tree::collect_objects(&self.content_store, &backup.root, &mut candidates)?;
+            candidates.insert(backup.stats.clone());
+        }
+        self.backups.remove(&forget)?;
+        let release = self.release_objects(&candidates, false)?;
+        report.released_chunks = release.released;
+        report.reclaimed_bytes = release.reclaimed;
+        report.unreadable_roots = release.unreadable_roots;
+        Ok(report)
+    }
+    
+    /// Work out how much of the store each registered file accounts for.
+    /// Objects referenced by the file alone are unique to it; objects that
+    /// other files, trashed entries or refs also reference are shared.
+    /// Expired files waiting to be swept are left out.
+    pub fn disk_usage(&self) -> NodeResult<UsageReport> {
+        let sizes: HashMap<ContentAddress, u64> = self.content_store.list_content()?
+            .chunks
+            .into_iter()
+            .map(|chunk| (chunk.address, chunk.size))
+            .collect();
+        
+        let mut holders: HashMap<ContentAddress, usize> = HashMap::new();
+        for address in self.retained_objects()? {
+            *holders.entry(address).or_default() += 1;
+        }
+        let unexpired = self.file_registry.unexpired_files();
+        let logical_bytes = unexpired.iter().map(|metadata| metadata.total_size).sum();
+        let mut file_objects = Vec::new();
+        for metadata in unexpired {
+            let mut objects = HashSet::new();
+            self.collect_file_objects(metadata, &mut objects)?;
+            for address in &objects {
+                *holders.entry(address.clone()).or_default() += 1;
+            }
+            file_objects.push((metadata, objects));
+        }
+        
+        let mut files: Vec<FileUsage> = file_objects
+            .into_iter()
+            .map(|(metadata, objects)| {
+                let (mut shared_bytes, mut unique_bytes) = (0, 0);
+                for address in &objects {
+                    let size = sizes.get(address).copied().unwrap_or(0);
+                    if holders[address] > 1 {
+                        shared_bytes += size;
+                    } else {
+                        unique_bytes += size;
+                    }
+                }
+                FileUsage {
+                    metadata: metadata.clone(),
+                    shared_bytes,
+                    unique_bytes,
+                }
+            })
+            .collect();
+        files.sort_by(|a, b| {
+            b.unique_bytes.cmp(&a.unique_bytes)
+                .then_with(|| b.metadata.total_size.cmp(&a.metadata.total_size))
+                .then_with(|| a.metadata.path.cmp(&b.metadata.path))
+        });
+        
+        Ok(UsageReport {
+            files,
+            logical_bytes,
+            stored_bytes: sizes.values().sum(),
+        })
+    }
+    
+    /// Delete those `candidates` that are no longer referenced. If some
+    /// root could not be read nothing is deleted; a later gc can still
+    /// collect the objects once it can be.
+    fn release_objects(&self, candidates: &HashSet<ContentAddress>, keep_chunks: bool) -> NodeResult<Release> {
+        let (unreferenced, unreadable_roots) = self.unreferenced_objects(candidates)?;
+        let mut release = Release { unreferenced: unreferenced.len(), unreadable_roots, ..Release::default() };
+        if keep_chunks {
+            return Ok(release);
+        }
+        
+        for address in &unreferenced {
+            let size = self.content_store.chunk_size(address).unwrap_or(0);
+            if self.content_store.remove_chunk(address)? {
+                release.released += 1;
+                release.reclaimed += size;
+            }
+        }
+        Ok(release)
+    }
+    
+    /// Move a file to the trash. Its chunks stay live until the retention
+    /// period ends, so it can be restored until then.
+    pub fn trash_file(&mut self, file_id: &FileId) -> NodeResult<TrashEntry> {
+        self.file_registry.move_to_trash(file_id, now_secs())
+            .map_err(|e| NodeError::General(format!("Failed to move file to trash: {}", e)))
+    }
+    
+    /// Bring a trashed file back, by ID prefix or logical path
+    pub fn restore_from_trash(&mut self, reference: &str) -> NodeResult<FileMetadata> {
+        let entry = self.file_registry.resolve_trashed(reference)
+            .map_err(|e| NodeError::General(e.to_string()))?;
+        
+        // Expired entries may already have lost chunks to gc
+        if entry.is_expired(now_secs(), self.trash_retention) {
+            let mut objects = HashSet::new();
+            self.collect_file_objects(&entry.metadata, &mut objects)?;
+            for address in &objects {
+                if !self.content_store.has_chunk(address)? {
+                    return Err(NodeError::General(format!(
+                        "'{}' can no longer be restored: its retention period ended and chunk {} was collected",
+                        entry.metadata.path, address
+                    )));
+                }
+            }
+        }
+        
+        let file_id = entry.metadata.id;
+        self.file_registry.restore_from_trash(&file_id)
+            .map_err(|e| NodeError::General(format!("Failed to restore file: {}", e)))
+    }
+    
+    /// Permanently delete trashed entries deleted at least `older_than`
+    /// seconds ago (all of them if `None`) and release their chunks
+    pub fn empty_trash(&mut self, older_than: Option<u64>) -> NodeResult<TrashPurgeReport> {
+        let now = now_secs();
+        let selected: Vec<FileId> = self.file_registry.trash_entries()
+            .into_iter()
+            .filter(|entry| older_than.is_none_or(|age| now.saturating_sub(entry.deleted_at) >= age))
+            .map(|entry| entry.metadata.id)
+            .collect();
+        self.purge_trash(&selected)
+    }
+    
+    fn purge_trash(&mut self, file_ids: &[FileId]) -> NodeResult<TrashPurgeReport> {
+        let mut candidates = HashSet::new();
+        for file_id in file_ids {
+            if let Some(entry) = self.file_registry.get_trashed(file_id) {
+                self.collect_file_objects(&entry.metadata, &mut candidates)?;
+            }
+        }
+        
+        let purged = self.file_registry.purge_from_trash(file_ids)
+            .map_err(|e| NodeError::General(format!("Failed to empty trash: {}", e)))?;
+        let release = self.release_objects(&candidates, false)?;
+        Ok(TrashPurgeReport {
+            purged,
+            released_chunks: release.released,
+            reclaimed_bytes: release.reclaimed,
+            unreadable_roots: release.unreadable_roots,
+        })
+    }
+    
+    /// Remove a file from the registry, then delete the chunks no other
+    /// file still references. The entry goes first so an interruption can
+    /// only leave orphaned chunks behind, never a file with missing chunks.
+    pub fn remove_file(&mut self, file_id: &FileId, keep_chunks: bool) -> NodeResult<RemovalReport> {
+        if !self.is_running() {
+            return Err(NodeError::NotRunning);
+        }
+        
+        let metadata = self.file_registry.get_file(file_id)
+            .ok_or_else(|| NodeError::General(format!("File not found: {}", file_id)))?
+            .clone();
+        let mut candidates = HashSet::new();
+        self.collect_file_objects(&metadata, &mut candidates)?;
+        
+        self.file_registry.remove_file(file_id)
+            .map_err(|e| NodeError::General(format!("Failed to remove file: {}", e)))?;
+        
+        let release = self.release_objects(&candidates, keep_chunks)?;
+        Ok(RemovalReport {
+            logical_bytes: metadata.total_size,
+            unreferenced_chunks: release.unreferenced,
+            released_chunks: release.released,
+            reclaimed_bytes: release.reclaimed,
+            unreadable_roots: release.unreadable_roots,
+            metadata,
+        })
+    }
+    
+    /// Remove every file whose time-to-live has run out and release the
+    /// chunks nothing else references. With `dry_run`, only report them.
+    pub fn expire_files(&mut self, dry_run: bool) -> NodeResult<ExpiryReport> {
+        if !self.is_running() {
+            return Err(NodeError::NotRunning);
+        }
+        
+        // Files registered since the node started must be both seen and
+        // counted as live
+        self.file_registry.refresh()
+            .map_err(|e| NodeError::General(format!("Failed to reload the registry: {}", e)))?;
+        let expired_ids = self.file_registry.expired_ids(now_secs());
+        let expired: Vec<FileMetadata> = self.file_registry.list_files()
+            .into_iter()
+            .filter(|metadata| expired_ids.contains(&metadata.id))
+            .cloned()
+            .collect();
+        if dry_run || expired.is_empty() {
+            return Ok(ExpiryReport { expired, ..ExpiryReport::default() });
+        }
+        
+        let mut candidates = HashSet::new();
+        for metadata in &expired {
+            self.collect_file_objects(metadata, &mut candidates)?;
+        }
+        let ids: Vec<FileId> = expired.iter().map(|metadata| metadata.id).collect();
+        let expired = self.file_registry.remove_files(&ids)
+            .map_err(|e| NodeError::General(format!("Failed to remove expired files: {}", e)))?;
+        
+        let release = self.release_objects(&candidates, false)?;
+        Ok(ExpiryReport {
+            expired,
+            released_chunks: release.released,
+            reclaimed_bytes: release.reclaimed,
+            unreadable_roots: release.unreadable_roots,
+        })
+    }
+    
+    /// Purge trash entries past their retention period, then delete every
+    /// stored object that nothing references any more
+    pub fn collect_garbage(&mut self, dry_run: bool) -> NodeResult<GcReport> {
+        if !self.is_running() {
+            return Err(NodeError::NotRunning);
+        }
+        
+        let now = now_secs();
+        let expired: Vec<FileId> = self.file_registry.trash_entries()
+            .into_iter()
+            .filter(|entry| entry.is_expired(now, self.trash_retention))
+            .map(|entry| entry.metadata.id)
+            .collect();
+        if !dry_run {
+            self.file_registry.purge_from_trash(&expired)
+                .map_err(|e| NodeError::General(format!("Failed to purge expired trash: {}", e)))?;
+        }
+        
+        let live = self.live_objects()?;
+        let listing = self.content_store.list_content()?;
+        let mut report = GcReport {
+            expired_trash: expired.len(),
+            scanned_chunks: listing.chunks.len(),
+            ..GcReport::default()
+        };
+        
+        for chunk in listing.chunks.iter().filter(|chunk| !live.contains(&chunk.address)) {
+            if dry_run || self.content_store.remove_chunk(&chunk.address)? {
+                report.unreferenced_chunks += 1;
+                report.reclaimed_bytes += chunk.size;
+            }
+        }
+        Ok(report)
+    }
+    
+    /// Write every registered file, ordered by path and version, returning
+    /// how many were written
+    pub fn export_registry<W: std::io::Write>(&self, format: ExportFormat, writer: W) -> NodeResult<usize> {
+        let mut files = self.file_registry.list_files();
+        files.sort_by(|a, b| a.path.cmp(&b.path).then(a.version.cmp(&b.version)));
+        export::write_entries(&files, format, writer)?;
+        Ok(files.len())
+    }
+    
+    /// Register entries exported from another node. With `chunks_from`,
+    /// objects they need are first copied from that node's content store
+    /// (its storage directory or the content directory itself). Entries
+    /// whose objects are still missing are skipped, so they are never
+    /// registered ahead of their chunks, unless `allow_missing` is set.
+    pub fn import_registry(&mut self, entries: Vec<FileMetadata>, rekey: bool, chunks_from: Option<&Path>, allow_missing: bool) -> NodeResult<RegistryImport> {
+        if !self.is_running() {
+            return Err(NodeError::NotRunning);
+        }
+        
+        let mut result = RegistryImport::default();
+        let source = chunks_from.map(Self::open_source_store).transpose()?;
+        let store = source.as_ref().unwrap_or(&self.content_store);
+        
+        let needs: Vec<HashSet<ContentAddress>> = entries.iter()
+            .map(|metadata| {
+                let mut objects: HashSet<ContentAddress> = metadata.chunk_addresses.iter().cloned().collect();
+                if let Some(root) = &metadata.tree_root {
+                    // A tree that cannot be walked is reported through its root
+                    if tree::collect_objects(store, root, &mut objects).is_err() {
+                        objects.insert(root.clone());
+                    }
+                }
+                objects
+            })
+            .collect();
+        
+        let mut objects: Vec<ContentAddress> = needs.iter().flatten().cloned().collect::<HashSet<_>>().into_iter().collect();
+        objects.sort_by_key(|address| address.to_string());
+        for address in objects {
+            if self.content_store.has_chunk(&address)? {
+                continue;
+            }
+            match source.as_ref().map(|source| source.get_chunk(&address)) {
+                Some(Ok(chunk)) => {
+                    self.content_store.put_chunk(chunk.data())?;
+                    result.copied_chunks += 1;
+                    result.copied_bytes += chunk.data().len() as u64;
+                }
+                None | Some(Err(ContentStoreError::ContentNotFound { .. })) => result.missing.push(address),
+                Some(Err(e)) => return Err(e.into()),
+            }
+        }
+        
+        let missing: HashSet<&ContentAddress> = result.missing.iter().collect();
+        let mut ready = Vec::with_capacity(entries.len());
+        for (metadata, objects) in entries.into_iter().zip(&needs) {
+            if allow_missing || objects.iter().all(|address| !missing.contains(address)) {
+                ready.push(metadata);
+            } else {
+                result.skipped.push(metadata);
+            }
+        }
+        
+        result.registry = self.file_registry.import_entries(ready, rekey)
+            .map_err(|e| NodeError::General(format!("Failed to import registry: {}", e)))?;
+        Ok(result)
+    }
+    
+    fn open_source_store(path: &Path) -> NodeResult<ContentStore> {
+        let storage_path = [path.to_path_buf(), path.join("content")]
+            .into_iter()
+            .find(|candidate| candidate.join("objects").is_dir())
+            .ok_or_else(|| NodeError::General(format!("No content store found in {}", path.display())))?;
+        Ok(ContentStore::new(ContentStoreConfig {
+            storage_path,
+            chunk_config: ChunkConfig::default(),
+            verify_on_read: true,
+        })?)
+    }
+    
+    /// Check that a registered file can be retrieved in full: every chunk
+    /// is stored and hashes to its address, and the recorded chunk count,
+    /// size and Merkle root agree with the chunks
+    pub fn verify_file(&self, file_id: &FileId) -> NodeResult<FileCheck> {
+        if !self.is_running() {
+            return Err(NodeError::NotRunning);
+        }
+        
+        let metadata = self.file_registry.get_file(file_id)
+            .ok_or_else(|| NodeError::General(format!("File not found: {}", file_id)))?
+            .clone();
+        let mut problems = Vec::new();
+        
+        let actual_size = match &metadata.tree_root {
+            Some(root) => self.check_tree(root, &mut problems)?,
+            None => self.check_chunks(&metadata.chunk_addresses, &mut problems)?,
+        };
+        if metadata.tree_root.is_none() {
+            if metadata.chunk_count != metadata.chunk_addresses.len() {
+                problems.push(FileProblem::ChunkCountMismatch {
+                    recorded: metadata.chunk_count,
+                    actual: metadata.chunk_addresses.len(),
+                });
+            }
+            let merkle_root = MerkleTree::from_addresses(&metadata.chunk_addresses).map(|tree| tree.root());
+            if merkle_root != metadata.merkle_root {
+                problems.push(FileProblem::MerkleRootMismatch);
+            }
+        }
+        // Sizes only mean something once everything could be read
+        if let Some(actual) = actual_size.filter(|actual| *actual != metadata.total_size) {
+            problems.push(FileProblem::SizeMismatch { recorded: metadata.total_size, actual });
+        }
+        
+        Ok(FileCheck { metadata, problems })
+    }
+    
+    /// Check every registered file, ordered by path and version
+    pub fn verify_all(&self) -> NodeResult<Vec<FileCheck>> {
+        let mut files = self.file_registry.list_files();
+        files.sort_by(|a, b| a.path.cmp(&b.path).then(a.version.cmp(&b.version)));
+        files.iter().map(|metadata| self.verify_file(&metadata.id)).collect()
+    }
+    
+    /// Read every chunk back, recording missing and corrupt ones. Returns
+    /// their total size if all of them were intact.
+    fn check_chunks<'a, I>(&self, addresses: I, problems: &mut Vec<FileProblem>) -> NodeResult<Option<u64>>
+    where
+        I: IntoIterator<Item = &'a ContentAddress>,
+    {
+        let mut total = Some(0);
+        for address in addresses {
+            match self.content_store.get_chunk(address) {
+                Ok(chunk) if chunk.address() == address => {
+                    total = total.map(|total| total + chunk.data().len() as u64);
+                }
+                Ok(_) | Err(ContentStoreError::Corruption { .. }) => {
+                    problems.push(FileProblem::CorruptChunk(address.clone()));
+                    total = None;
+                }
+                Err(ContentStoreError::ContentNotFound { .. }) => {
+                    problems.push(FileProblem::MissingChunk(address.clone()));
+                    total = None;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(total)
+    }
+    
+    /// Check every object of a directory tree. Returns the size of the
+    /// files it holds if the whole tree was intact.
+    fn check_tree(&self, root: &ContentAddress, problems: &mut Vec<FileProblem>) -> NodeResult<Option<u64>> {
+        let mut objects = HashSet::new();
+        if let Err(e) = tree::collect_objects(&self.content_store, root, &mut objects) {
+            problems.push(match e {
+                tree::TreeError::Storage(ContentStoreError::ContentNotFound { address }) => FileProblem::MissingChunk(address),
+                tree::TreeError::Storage(ContentStoreError::Corruption { expected, .. }) => FileProblem::CorruptChunk(expected),
+                e => FileProblem::BrokenTree(e.to_string()),
+            });
+            return Ok(None);
+        }
+        
+        if self.check_chunks(&objects, problems)?.is_none() {
+            return Ok(None);
+        }
+        let size = tree::load_tree(&self.content_store, root)?.entries.iter().map(|entry| entry.size).sum();
+        Ok(Some(size))
+    }
+    
+    /// Generate a Merkle inclusion proof for one chunk of a registered file
+    pub fn prove_chunk(&self, file_id: &FileId, chunk_index: usize) -> NodeResult<MerkleProof> {
+        let metadata = self.file_registry.get_file(file_id)
+            .ok_or_else(|| NodeError::General(format!("File not found: {}", file_id)))?;
+        
+        let tree = MerkleTree::from_addresses(&metadata.chunk_addresses)
+            .ok_or_else(|| NodeError::General(format!("File '{}' has no chunks", metadata.original_name)))?;
+        
+        tree.proof(chunk_index).ok_or_else(|| NodeError::General(format!(
+            "Chunk index {} out of range (file has {} chunks)",
+            chunk_index, metadata.chunk_count
+        )))
+    }
+    
+    /// Build and store the BLAKE3 outboard tree for a file, returning its root
+    pub fn build_outboard(&mut self, file_id: &FileId) -> NodeResult<ContentAddress> {
+        let addresses = self.file_registry.get_file(file_id)
+            .ok_or_else(|| NodeError::General(format!("File not found: {}", file_id)))?
+            .chunk_addresses
+            .clone();
+        
+        let reader = ChunkReader::new(&self.content_store, &addresses)?;
+        let outboard = Outboard::build(reader)?;
+        self.file_registry.save_outboard(file_id, &outboard)
+            .map_err(|e| NodeError::General(format!("Failed to save outboard: {}", e)))?;
+        
+        Ok(outboard.root)
+    }
+    
+    /// Read a byte range of a file, verifying it against the file's BLAKE3 root
+    pub fn read_verified_range(&self, file_id: &FileId, start: u64, len: u64) -> NodeResult<Vec<u8>> {
+        let metadata = self.file_registry.get_file(file_id)
+            .ok_or_else(|| NodeError::General(format!("File not found: {}", file_id)))?;
+        
+        let (Some(root), Some(outboard)) = (
+            metadata.stream_root.as_ref(),
+            self.file_registry.load_outboard(file_id)
+                .map_err(|e| NodeError::General(format!("Failed to load outboard: {}", e)))?,
+        ) else {
+            return Err(NodeError::General(format!(
+                "File '{}' has no outboard tree; store it with --verified first",
+                metadata.original_name
+            )));
+        };
+        
+        let reader = ChunkReader::new(&self.content_store, &metadata.chunk_addresses)?;
+        let data = read_verified_range(reader, std::io::Cursor::new(outboard), root, start, len)?;
+        Ok(data)
+    }
+    
+    /// Get storage statistics
+    pub fn get_stats(&self) -> NodeResult<Vec<String>> {
+        let stats = self.content_store.stats()?;
+        let listing = self.content_store.list_content()?;
+        let file_count = self.file_registry.file_count();
+        let file_total_size = self.file_registry.total_size();
+        
+        let mut result = Vec::new();
+        result.push("Storage Statistics:".to_string());
+        result.push(format!("  Total chunks: {}", stats.total_chunks));
+        result.push(format!("  Total chunk size: {} bytes", stats.total_size));
+        result.push(format!("  Registered files: {}", file_count));
+        result.push(format!("  Total file size: {} bytes", file_total_size));
+        if stats.total_size > 0 {
+            result.push(format!("  Dedup ratio: {:.2}x", file_total_size as f64 / stats.total_size as f64));
+        }
+        result.push(format!("  Storage path: {}", stats.storage_path.display()));
+        
+        if !listing.chunks.is_empty() {
+            let avg_chunk_size = stats.total_size as f64 / stats.total_chunks as f64;
+            result.push(format!("  Average chunk size: {:.1} bytes", avg_chunk_size));
+            
+            if file_count > 0 {
+                let avg_file_size = file_total_size as f64 / file_count as f64;
+                result.push(format!("  Average file size: {:.1} bytes", avg_file_size));
+            }
+            
+            // Find largest and smallest chunks
+            let largest = listing.chunks.iter().max_by_key(|c| c.size);
+            let smallest = listing.chunks.iter().min_by_key(|c| c.size);
+            
+            if let Some(largest) = largest {
+                result.push(format!("  Largest chunk: {} bytes", largest.size));
+            }
+            if let Some(smallest) = smallest {
+                result.push(format!("  Smallest chunk: {} bytes", smallest.size));
+            }
+        }
+        
+        Ok(result)
+    }
+    
+    /// List registered files matching a filter
+    pub fn list_files(&self, filter: &FileFilter) -> NodeResult<Vec<String>> {
+        let files = self.file_registry.find_files(filter);
+        let mut result = Vec::new();
+        
+        if files.is_empty() && !filter.is_empty() {
+            result.push("No files match the given filters.".to_string());
+        } else if files.is_empty() {
+            result.push("No files registered.".to_string());
+        } else {
+            let short_ids = self.file_registry.short_ids();
+            result.push(format!("Registered Files ({}):", files.len()));
+            for file in files {
+                result.push(Self::format_file_line(file, &short_ids[&file.id]));
+            }
+        }
+        
+        Ok(result)
+    }
+    
+    /// Search the registry with a query, returning one page of matches
+    pub fn find_files(&self, query: &Query, options: &QueryOptions) -> NodeResult<Vec<String>> {
+        let matches = self.file_registry.search(query)
+            .map_err(|e| NodeError::General(format!("Failed to search the registry: {}", e)))?;
+        let total = matches.len();
+        let page = options.apply(matches);
+        let mut result = Vec::new();
+        
+        if page.is_empty() {
+            if total == 0 {
+                result.push("No files match the query.".to_string());
+            } else {
+                result.push(format!("No files on this page ({} matches in total).", total));
+            }
+        } else {
+            let short_ids = self.file_registry.short_ids();
+            result.push(format!(
+                "Found {} files (showing {}-{}):",
+                total,
+                options.offset + 1,
+                options.offset + page.len()
+            ));
+            for file in page {
+                result.push(Self::format_file_line(file, &short_ids[&file.id]));
+            }
+        }
+        
+        Ok(result)
+    }
+    
+    /// Files that share at least one chunk with `file_id`, the most shared
+    /// bytes first. Candidates come from the chunk index, so every file
+    /// with any overlap is found and the overlap is counted exactly.
+    ///
+    /// This stands in for the MinHash estimate similarity was first
+    /// specified with. The index already yields each overlapping file with
+    /// its shared chunks, so the Jaccard similarity costs nothing extra to
+    /// compute exactly, and small overlaps that a fixed-size signature
+    /// would round to zero are still reported.
+    pub fn similar_files(&self, file_id: &FileId, limit: usize) -> NodeResult<Vec<SimilarFile>> {
+        let target = self.unexpired_file(file_id)?;
+        if target.is_directory() {
+            return Err(NodeError::General(format!("'{}' is a directory; similarity works on files", target.path)));
+        }
+        let target_chunks: HashSet<&ContentAddress> = target.chunk_addresses.iter().collect();
+        let expired = self.file_registry.expired_ids(now_secs());
+        
+        // Shared distinct chunks and their bytes, per file
+        let mut overlaps: HashMap<FileId, (&FileMetadata, usize, u64)> = HashMap::new();
+        for address in &target_chunks {
+            let files = self.file_registry.files_with_chunk(address)
+                .map_err(|e| NodeError::General(format!("Failed to look up chunk users: {}", e)))?;
+            let mut others = files.into_iter()
+                .filter(|metadata| metadata.id != *file_id && !metadata.is_directory() && !expired.contains(&metadata.id))
+                .peekable();
+            if others.peek().is_none() {
+                continue;
+            }
+            let size = self.content_store.chunk_size(address)?;
+            for metadata in others {
+                let overlap = overlaps.entry(metadata.id).or_insert((metadata, 0, 0));
+                overlap.1 += 1;
+                overlap.2 += size;
+            }
+        }
+        
+        let mut result: Vec<SimilarFile> = overlaps.into_values()
+            .map(|(metadata, shared_chunks, shared_bytes)| {
+                let chunks = metadata.chunk_addresses.iter().collect::<HashSet<_>>().len();
+                let union = target_chunks.len() + chunks - shared_chunks;
+                SimilarFile {
+                    metadata: metadata.clone(),
+                    similarity: shared_chunks as f64 / union as f64,
+                    shared_bytes,
+                }
+            })
+            .collect();
+        result.sort_by(|a, b| {
+            b.shared_bytes.cmp(&a.shared_bytes)
+                .then_with(|| b.similarity.total_cmp(&a.similarity))
+                .then_with(|| a.metadata.path.cmp(&b.metadata.path))
+                .then_with(|| a.metadata.version.cmp(&b.metadata.version))
+        });
+        result.truncate(limit);
+        Ok(result)
+    }
+    
+    /// Compare the chunk lists of two registered files without reading
+    /// their contents
+    pub fn diff_files(&self, old_id: &FileId, new_id: &FileId) -> NodeResult<ChunkDiff> {
+        let mut sizes: HashMap<&ContentAddress, u64> = HashMap::new();
+        let mut chunk_list = |file_id: &FileId| -> NodeResult<Vec<(ContentAddress, u64)>> {
+            let metadata = self.unexpired_file(file_id)?;
+            if metadata.is_directory() {
+                return Err(NodeError::General(format!("'{}' is a directory; diff works on files", metadata.path)));
+            }
+            
+            let mut chunks = Vec::with_capacity(metadata.chunk_addresses.len());
+            for address in &metadata.chunk_addresses {
+                let size = match sizes.get(address) {
+                    Some(size) => *size,
+                    None => {
+                        let size = self.content_store.chunk_size(address)?;
+                        sizes.insert(address, size);
+                        size
+                    }
+                };
+                chunks.push((address.clone(), size));
+            }
+            Ok(chunks)
+        };
+        
+        let old = chunk_list(old_id)?;
+        let new = chunk_list(new_id)?;
+        Ok(ChunkDiff::compute(&old, &new))
+    }
+    
+    /// A registered file that has not expired; expired ones are treated as
+    /// gone even before they are swept
+    fn unexpired_file(&self, file_id: &FileId) -> NodeResult<&FileMetadata> {
+        let metadata = self.file_registry.get_file(file_id)
+            .ok_or_else(|| NodeError::General(format!("File not found: {}", file_id)))?;
+        if self.file_registry.expired_ids(now_secs()).contains(file_id) {
+            return Err(NodeError::General(format!("'{}' v{} has expired", metadata.path, metadata.version)));
+        }
+        Ok(metadata)
+    }
+    
+    /// Compute whole-file digests for entries registered before they were
+    /// recorded, returning how many were filled in. Expired entries are
+    /// about to go and are left alone.
+    pub fn backfill_digests(&mut self) -> NodeResult<usize> {
+        let missing: Vec<FileMetadata> = self.file_registry.unexpired_files()
+            .into_iter()
+            .filter(|metadata| metadata.digest.is_none() && !metadata.is_directory())
+            .filter(|metadata| !metadata.posix.as_ref().is_some_and(PosixMetadata::is_symlink))
+            .cloned()
+            .collect();
+        
+        for metadata in &missing {
+            let reader = ChunkReader::new(&self.content_store, &metadata.chunk_addresses)?;
+            let digest = digest_reader(reader)?;
+            self.file_registry.update_file(&metadata.id, |metadata| metadata.digest = Some(digest))
+                .map_err(|e| NodeError::General(format!("Failed to record digest: {}", e)))?;
+        }
+        Ok(missing.len())
+    }
+    
+    /// Report groups of registered files with identical content
+    pub fn duplicate_report(&mut self) -> NodeResult<Vec<String>> {
+        let backfilled = self.backfill_digests()?;
+        let groups = self.file_registry.duplicate_groups();
+        let short_ids = self.file_registry.short_ids();
+        let mut result = Vec::new();
+        
+        if backfilled > 0 {
+            result.push(format!("Computed digests for {} older files.", backfilled));
+        }
+        if groups.is_empty() {
+            result.push("No duplicate files.".to_string());
+            return Ok(result);
+        }
+        
+        let redundant: usize = groups.iter().map(|group| group.len() - 1).sum();
+        let redundant_bytes: u64 = groups.iter()
+            .map(|group| group[0].total_size * (group.len() as u64 - 1))
+            .sum();
+        result.push(format!(
+            "Duplicate files ({} groups, {} redundant entries, {} bytes):",
+            groups.len(), redundant, redundant_bytes
+        ));
+        for group in groups {
+            let digest = group[0].digest.as_deref().unwrap_or_default();
+            result.push(format!("  {} - {} files of {} bytes", &digest[..16.min(digest.len())], group.len(), group[0].total_size));
+            for file in group {
+                result.push(format!("    {} - {} v{} - {}", short_ids[&file.id], file.path, file.version, file.created_time_string()));
+            }
+        }
+        Ok(result)
+    }
+    
+    /// One-line summary of a registered file used by listings
+    fn format_file_line(file: &FileMetadata, short_id: &str) -> String {
+        let tags = if file.tags.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", file.tags.iter().cloned().collect::<Vec<_>>().join(", "))
+        };
+        format!(
+            "  {} - {} v{} ({} bytes, {} chunks) - {}{}",
+            short_id,
+            file.path,
+            file.version,
+            file.total_size,
+            file.chunk_count,
+            file.created_time_string(),
+            tags
+        )
+    }
+    
+    /// List registered files matching a filter with detailed information
+    pub fn list_files_verbose(&self, filter: &FileFilter) -> NodeResult<Vec<String>> {
+        let files = self.file_registry.find_files(filter);
+        let mut result = Vec::new();
+        
+        if files.is_empty() && !filter.is_empty() {
+            result.push("No files match the given filters.".to_string());
+        } else if files.is_empty() {
+            result.push("No files registered.".to_string());
+        } else {
+            result.push(format!("Registered Files ({}):", files.len()));
+            for file in files {
+                result.push(format!("File ID: {}", file.id));
+                result.push(format!("  Name: {}", file.original_name));
+                result.push(format!("  Path: {} (version {})", file.path, file.version));
+                result.push(format!("  Type: {}", file.content_type()));
+                if let Some(pin) = file.pin {
+                    result.push(format!("  Pinned: {}", pin));
+                }
+                if let Some(expires_at) = file.expires_at {
+                    result.push(format!("  Expires: {} seconds since epoch (in {})",
+                                        expires_at, format_duration(expires_at.saturating_sub(now_secs()))));
+                }
+                if let Some(parent) = file.parent {
+                    result.push(format!("  Parent: {}", parent));
+                }
+                if let Some(rename) = file.renames.last() {
+                    result.push(format!("  Renamed from: {} ({} renames)", rename.from, file.renames.len()));
+                }
+                if !file.tags.is_empty() {
+                    let tags: Vec<&str> = file.tags.iter().map(String::as_str).collect();
+                    result.push(format!("  Tags: {}", tags.join(", ")));
+                }
+                for (key, value) in &file.attributes {
+                    result.push(format!("  Attribute: {}={}", key, value));
+                }
+                if let Some(posix) = &file.posix {
+                    if let Some(target) = &posix.symlink_target {
+                        result.push(format!("  Symlink: -> {}", target));
+                    }
+                    result.push(format!("  Mode: {:04o}", posix.mode));
+                    if let (Some(uid), Some(gid)) = (posix.uid, posix.gid) {
+                        result.push(format!("  Owner: {}:{}", uid, gid));
+                    }
+                    result.push(format!("  Modified: {} seconds since epoch", posix.mtime));
+                    if !posix.xattrs.is_empty() {
+                        let names: Vec<&str> = posix.xattrs.keys().map(String::as_str).collect();
+                        result.push(format!("  Xattrs: {}", names.join(", ")));
+                    }
+                }
+                result.push(format!("  Size: {} bytes", file.total_size));
+                result.push(format!("  Chunks: {} parts", file.chunk_count));
+                result.push(format!("  Created: {}", file.created_time_string()));
+                if let Some(root) = &file.merkle_root {
+                    result.push(format!("  Merkle root: {}", root));
+                }
+                if let Some(root) = &file.stream_root {
+                    result.push(format!("  Stream root: {}", root));
+                }
+                if let Some(root) = &file.tree_root {
+                    result.push(format!("  Tree root: {}", root));
+                }
+                result.push("  Chunk addresses:".to_string());
+                for (i, addr) in file.chunk_addresses.iter().enumerate() {
+                    result.push(format!("    [{}] {}", i + 1, addr));
+                }
+                result.push("".to_string()); // Empty line
+            }
+        }
+        
+        Ok(result)
+    }
+    pub fn list_content(&self) -> NodeResult<Vec<String>> {
+        let listing = self.content_store.list_content()?;
+        let mut result = Vec::new();
+        
+        if listing.chunks.is_empty() {
             result.push("No content stored.".to_string());
         } else {
+            let short_addresses = listing.short_addresses();
             for chunk in &listing.chunks {
                 result.push(format!(
                     "  {} ({} bytes) - {}",
-                    chunk.short_address(),
+                    short_addresses[&chunk.address],
                     chunk.size,
                     chunk.created_time_string()
                 ));
@@ -446,7 +2054,6 @@ impl Node {
         
         // Add creation time if we can read it
         if let Ok(metadata) = NodeMetadata::load_from_file(&metadata_file) {
-            let created_at = std::time::UNIX_EPOCH + std::time::Duration::from_secs(metadata.created_at);
             result.push(format!("  Created: {} seconds since epoch", metadata.created_at));
         }
         
@@ -466,6 +2073,7 @@ impl Node {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::file::RefCondition;
 
     #[test]
     fn test_node_creation() {
@@ -506,4 +2114,622 @@ mod tests {
         assert_eq!(*node.get_status(), NodeState::Stopped);
         assert!(!node.is_running());
     }
-}
\ No newline at end of file
+    
+    /// Deterministic incompressible test data
+    fn noise(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed;
+        (0..len).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        }).collect()
+    }
+    
+    /// Running node whose storage lives in a temporary directory
+    fn test_node(temp: &tempfile::TempDir) -> Node {
+        let storage_dir = temp.path().to_path_buf();
+        let content_store = ContentStore::new(ContentStoreConfig {
+            storage_path: storage_dir.join("content"),
+            chunk_config: ChunkConfig::default(),
+            verify_on_read: true,
+        }).unwrap();
+        let file_registry = FileRegistry::new(&storage_dir).unwrap();
+        let refs = RefStore::new(&storage_dir).unwrap();
+        let snapshots = SnapshotStore::new(&storage_dir).unwrap();
+        let backups = BackupStore::new(&storage_dir).unwrap();
+        
+        Node {
+            id: Uuid::new_v4(),
+            state: NodeState::Running,
+            address: "127.0.0.1".to_string(),
+            port: 4001,
+            storage_dir,
+            log_level: LogLevel::Error,
+            daemon_mode: false,
+            content_store,
+            file_registry,
+            refs,
+            snapshots,
+            backups,
+            trash_retention: DEFAULT_TRASH_RETENTION,
+        }
+    }
+    
+    #[test]
+    fn test_remove_file_releases_only_unshared_chunks() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        
+        let shared: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(temp.path().join("a.bin"), &shared).unwrap();
+        let mut extended = shared.clone();
+        extended.extend((0..50_000u32).map(|i| (i % 13) as u8));
+        fs::write(temp.path().join("b.bin"), &extended).unwrap();
+        
+        let a = node.put_file_with_registry(temp.path().join("a.bin")).unwrap();
+        let b = node.put_file_with_registry(temp.path().join("b.bin")).unwrap();
+        let before = node.content_store.stats().unwrap().total_chunks;
+        
+        let report = node.remove_file(&b.id, false).unwrap();
+        assert_eq!(report.logical_bytes, 350_000);
+        assert!(report.released_chunks > 0);
+        assert!(report.released_chunks < b.chunk_count);
+        assert_eq!(node.content_store.stats().unwrap().total_chunks, before - report.released_chunks);
+        
+        // The remaining file is fully readable
+        let restored = temp.path().join("a.out");
+        node.get_file_by_id(&a.id, &restored).unwrap();
+        assert_eq!(fs::read(&restored).unwrap(), shared);
+    }
+    
+    #[test]
+    fn test_keep_chunks_leaves_garbage_for_gc() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        fs::write(temp.path().join("a.bin"), vec![3u8; 10_000]).unwrap();
+        
+        let a = node.put_file_with_registry(temp.path().join("a.bin")).unwrap();
+        let report = node.remove_file(&a.id, true).unwrap();
+        assert_eq!(report.released_chunks, 0);
+        assert_eq!(report.unreferenced_chunks, a.chunk_count);
+        
+        let dry_run = node.collect_garbage(true).unwrap();
+        assert_eq!(dry_run.unreferenced_chunks, a.chunk_count);
+        assert_eq!(node.content_store.stats().unwrap().total_chunks, a.chunk_count);
+        
+        let gc = node.collect_garbage(false).unwrap();
+        assert_eq!(gc.reclaimed_bytes, 10_000);
+        assert_eq!(node.content_store.stats().unwrap().total_chunks, 0);
+    }
+    
+    #[test]
+    fn test_similar_files_rank_by_shared_chunks() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        let base = noise(1, 400_000);
+        let mut edited = base[..300_000].to_vec();
+        edited.extend(noise(2, 100_000));
+        fs::write(temp.path().join("base.bin"), &base).unwrap();
+        fs::write(temp.path().join("edited.bin"), &edited).unwrap();
+        fs::write(temp.path().join("other.bin"), noise(3, 400_000)).unwrap();
+        // Shares only its first few chunks with a much larger file
+        let mut sliver = base[..60_000].to_vec();
+        sliver.extend(noise(4, 4_000_000));
+        fs::write(temp.path().join("sliver.bin"), &sliver).unwrap();
+        
+        let base = node.put_file_with_registry(temp.path().join("base.bin")).unwrap();
+        let edited = node.put_file_with_registry(temp.path().join("edited.bin")).unwrap();
+        node.put_file_with_registry(temp.path().join("other.bin")).unwrap();
+        let sliver = node.put_file_with_registry(temp.path().join("sliver.bin")).unwrap();
+        
+        let similar = node.similar_files(&base.id, 10).unwrap();
+        assert_eq!(similar.len(), 2);
+        assert_eq!(similar[0].metadata.id, edited.id);
+        assert!(similar[0].similarity > 0.3 && similar[0].similarity < 1.0, "{}", similar[0].similarity);
+        // Most of the unchanged prefix is found in shared chunks
+        assert!(similar[0].shared_bytes > 200_000 && similar[0].shared_bytes <= 300_000, "{}", similar[0].shared_bytes);
+        
+        // However small the overlap, it is found and counted exactly
+        assert_eq!(similar[1].metadata.id, sliver.id);
+        assert!(similar[1].similarity > 0.0 && similar[1].similarity < 0.05, "{}", similar[1].similarity);
+        assert!(similar[1].shared_bytes > 0 && similar[1].shared_bytes <= 60_000, "{}", similar[1].shared_bytes);
+        assert_eq!(node.similar_files(&base.id, 1).unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn test_disk_usage_splits_shared_and_unique_bytes() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        let base = noise(4, 300_000);
+        let mut edited = base[..200_000].to_vec();
+        edited.extend(noise(5, 100_000));
+        fs::write(temp.path().join("base.bin"), &base).unwrap();
+        fs::write(temp.path().join("edited.bin"), &edited).unwrap();
+        
+        let base = node.put_file_with_registry(temp.path().join("base.bin")).unwrap();
+        node.put_file_with_registry(temp.path().join("edited.bin")).unwrap();
+        
+        let report = node.disk_usage().unwrap();
+        assert_eq!(report.logical_bytes, 600_000);
+        assert_eq!(report.stored_bytes, node.content_store.stats().unwrap().total_size);
+        assert!(report.dedup_ratio() > 1.0);
+        for usage in &report.files {
+            assert!(usage.shared_bytes > 0 && usage.unique_bytes > 0);
+            assert_eq!(usage.shared_bytes + usage.unique_bytes, usage.metadata.total_size);
+        }
+        
+        // Unique bytes are exactly what deleting the file frees
+        let unique = report.files.iter().find(|usage| usage.metadata.id == base.id).unwrap().unique_bytes;
+        assert_eq!(node.remove_file(&base.id, false).unwrap().reclaimed_bytes, unique);
+    }
+    
+    #[test]
+    fn test_diff_files_finds_changed_tail() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        let base = noise(6, 300_000);
+        let mut edited = base[..200_000].to_vec();
+        edited.extend(noise(7, 150_000));
+        fs::write(temp.path().join("v1.bin"), &base).unwrap();
+        fs::write(temp.path().join("v2.bin"), &edited).unwrap();
+        
+        let v1 = node.put_file_with_registry(temp.path().join("v1.bin")).unwrap();
+        let v2 = node.put_file_with_registry(temp.path().join("v2.bin")).unwrap();
+        
+        let diff = node.diff_files(&v1.id, &v2.id).unwrap();
+        assert!(diff.shared.bytes > 100_000);
+        assert_eq!(diff.shared.bytes + diff.added.bytes, 350_000);
+        assert_eq!(diff.changed_ranges.len(), 1);
+        assert_eq!(diff.changed_ranges[0].end, 350_000);
+        assert!(diff.changed_ranges[0].start <= 200_000);
+        assert_eq!(diff.transfer_bytes(), diff.changed_ranges[0].len());
+        assert!(node.diff_files(&v1.id, &v1.id).unwrap().is_identical());
+    }
+    
+    #[test]
+    fn test_mime_type_detected_on_put() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png.extend(noise(8, 1_000));
+        fs::write(temp.path().join("image.dat"), &png).unwrap();
+        fs::write(temp.path().join("notes.md"), b"# Notes\n").unwrap();
+        fs::create_dir(temp.path().join("dir")).unwrap();
+        
+        let image = node.put_file_with_registry(temp.path().join("image.dat")).unwrap();
+        let notes = node.put_file_with_registry(temp.path().join("notes.md")).unwrap();
+        let (dir, _) = node.put_directory_with_registry(temp.path().join("dir"), &PutOptions::default()).unwrap();
+        assert_eq!(image.mime_type.as_deref(), Some("image/png"));
+        assert_eq!(notes.content_type(), "text/markdown");
+        assert_eq!(dir.content_type(), mime::DIRECTORY);
+        
+        let filter = FileFilter { mime: Some("image/*".to_string()), ..FileFilter::default() };
+        let matches = node.file_registry.find_files(&filter);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].id, image.id);
+    }
+    
+    #[test]
+    fn test_verify_finds_missing_and_corrupt_chunks() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        fs::create_dir_all(temp.path().join("dir")).unwrap();
+        fs::write(temp.path().join("dir/a.bin"), noise(13, 20_000)).unwrap();
+        fs::write(temp.path().join("one.bin"), noise(14, 150_000)).unwrap();
+        fs::write(temp.path().join("two.bin"), noise(15, 150_000)).unwrap();
+        
+        let (dir, _) = node.put_directory_with_registry(temp.path().join("dir"), &PutOptions::default()).unwrap();
+        let one = node.put_file_with_registry(temp.path().join("one.bin")).unwrap();
+        let two = node.put_file_with_registry(temp.path().join("two.bin")).unwrap();
+        assert!(node.verify_all().unwrap().iter().all(FileCheck::is_ok));
+        
+        // Drop a chunk of one file and overwrite a chunk of the other
+        let missing = one.chunk_addresses[0].clone();
+        node.content_store.remove_chunk(&missing).unwrap();
+        let corrupt = two.chunk_addresses[1].to_string();
+        let chunk_path = temp.path().join("content/objects").join(&corrupt[..2]).join(&corrupt[2..]);
+        fs::write(chunk_path, b"garbage").unwrap();
+        
+        assert_eq!(node.verify_file(&one.id).unwrap().problems, vec![FileProblem::MissingChunk(missing)]);
+        assert_eq!(node.verify_file(&two.id).unwrap().problems, vec![FileProblem::CorruptChunk(two.chunk_addresses[1].clone())]);
+        assert!(node.verify_file(&dir.id).unwrap().is_ok());
+        
+        // Recorded sizes and counts are checked against the chunks
+        node.file_registry.update_file(&dir.id, |metadata| metadata.total_size += 1).unwrap();
+        assert_eq!(node.verify_file(&dir.id).unwrap().problems, vec![FileProblem::SizeMismatch { recorded: 20_001, actual: 20_000 }]);
+        node.content_store.remove_chunk(dir.tree_root.as_ref().unwrap()).unwrap();
+        assert_eq!(node.verify_file(&dir.id).unwrap().problems, vec![FileProblem::MissingChunk(dir.tree_root.clone().unwrap())]);
+        
+        let broken = node.verify_all().unwrap().into_iter().filter(|check| !check.is_ok()).count();
+        assert_eq!(broken, 3);
+    }
+    
+    #[test]
+    fn test_import_registry_copies_chunks() {
+        let source_temp = tempfile::TempDir::new().unwrap();
+        let mut source = test_node(&source_temp);
+        fs::create_dir_all(source_temp.path().join("dir")).unwrap();
+        fs::write(source_temp.path().join("dir/a.bin"), noise(16, 30_000)).unwrap();
+        fs::write(source_temp.path().join("file.bin"), noise(17, 30_000)).unwrap();
+        source.put_directory_with_registry(source_temp.path().join("dir"), &PutOptions::default()).unwrap();
+        source.put_file_with_registry(source_temp.path().join("file.bin")).unwrap();
+        
+        let mut exported = Vec::new();
+        assert_eq!(source.export_registry(ExportFormat::JsonLines, &mut exported).unwrap(), 2);
+        let entries = export::read_entries(&exported[..]).unwrap();
+        
+        // Without a source store the missing objects are reported and the
+        // entries needing them are left out
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        let report = node.import_registry(entries.clone(), false, None, false).unwrap();
+        assert_eq!(report.copied_chunks, 0);
+        assert!(!report.missing.is_empty());
+        assert_eq!(report.skipped.len(), 2);
+        assert!(report.registry.imported.is_empty());
+        assert_eq!(node.file_registry.file_count(), 0);
+        
+        // Unless they are explicitly wanted anyway
+        let report = node.import_registry(entries.clone(), false, None, true).unwrap();
+        assert!(report.skipped.is_empty());
+        assert_eq!(report.registry.imported.len(), 2);
+        
+        // Only the entries whose objects are missing are skipped
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        fs::write(temp.path().join("file.bin"), noise(17, 30_000)).unwrap();
+        node.put_file(temp.path().join("file.bin")).unwrap();
+        let report = node.import_registry(entries.clone(), false, None, false).unwrap();
+        assert_eq!(report.registry.imported.len(), 1);
+        assert_eq!(report.registry.imported[0].path, "file.bin");
+        assert_eq!(report.skipped.len(), 1);
+        
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        let report = node.import_registry(entries, false, Some(source_temp.path()), false).unwrap();
+        assert_eq!(report.registry.imported.len(), 2);
+        assert!(report.missing.is_empty());
+        assert_eq!(report.copied_bytes, source.content_store.stats().unwrap().total_size);
+        assert!(node.verify_all().unwrap().iter().all(FileCheck::is_ok));
+        
+        assert!(node.import_registry(Vec::new(), false, Some(temp.path().join("nowhere").as_path()), false).is_err());
+    }
+    
+    #[test]
+    fn test_snapshots_keep_and_restore_file_set() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        for (seed, name) in [(18, "a.bin"), (19, "b.bin"), (20, "c.bin")] {
+            fs::write(temp.path().join(name), noise(seed, 40_000)).unwrap();
+        }
+        let a = node.put_file_with_registry(temp.path().join("a.bin")).unwrap();
+        let b = node.put_file_with_registry(temp.path().join("b.bin")).unwrap();
+        let record = node.create_snapshot(Some("before-cleanup".to_string())).unwrap();
+        assert_eq!((record.files, record.total_size), (2, 80_000));
+        
+        // The snapshot keeps the removed file's chunks alive
+        assert_eq!(node.remove_file(&b.id, false).unwrap().reclaimed_bytes, 0);
+        let c = node.put_file_with_registry(temp.path().join("c.bin")).unwrap();
+        assert_eq!(node.collect_garbage(false).unwrap().unreferenced_chunks, 0);
+        
+        let restore = node.restore_snapshot("before-cleanup").unwrap();
+        assert_eq!(restore.removed.iter().map(|metadata| metadata.id).collect::<Vec<_>>(), vec![c.id]);
+        let mut ids: Vec<FileId> = node.file_registry.list_files().iter().map(|metadata| metadata.id).collect();
+        ids.sort();
+        let mut expected = vec![a.id, b.id];
+        expected.sort();
+        assert_eq!(ids, expected);
+        assert!(node.verify_all().unwrap().iter().all(FileCheck::is_ok));
+        assert_eq!(node.collect_garbage(false).unwrap().reclaimed_bytes, 40_000);
+        
+        // Deleting the snapshot frees its object but not files still registered
+        let deletion = node.delete_snapshot(&record.id).unwrap();
+        assert_eq!(deletion.released_chunks, 1);
+        assert!(node.snapshots.list().unwrap().is_empty());
+        assert!(node.restore_snapshot("before-cleanup").is_err());
+    }
+
+    #[test]
+    fn test_backups_reuse_unchanged_files_and_forget() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        fs::create_dir_all(temp.path().join("home/sub")).unwrap();
+        fs::write(temp.path().join("home/a.bin"), noise(21, 20_000)).unwrap();
+        fs::write(temp.path().join("home/sub/b.bin"), noise(22, 20_000)).unwrap();
+
+        let first = node.backup_directory(temp.path().join("home"), None).unwrap().0;
+        assert_eq!((first.name.as_str(), first.files, first.reused), ("home", 2, 0));
+        let second = node.backup_directory(temp.path().join("home"), None).unwrap().0;
+        assert_eq!((second.reused, second.root.clone()), (2, first.root.clone()));
+
+        fs::write(temp.path().join("home/a.bin"), noise(23, 30_000)).unwrap();
+        let third = node.backup_directory(temp.path().join("home"), None).unwrap().0;
+        assert_eq!((third.reused, third.total_size), (1, 50_000));
+        assert_eq!(node.backups.list(Some("home")).unwrap().len(), 3);
+
+        // Backups keep their trees alive without any registry entry
+        assert_eq!(node.collect_garbage(false).unwrap().unreferenced_chunks, 0);
+
+        let policy = BackupPolicy { keep_last: Some(1), ..BackupPolicy::default() };
+        let preview = node.forget_backups(None, &policy, true).unwrap();
+        assert_eq!((preview.forgotten.len(), preview.kept, preview.released_chunks), (2, 1, 0));
+
+        let report = node.forget_backups(Some("home"), &policy, false).unwrap();
+        assert_eq!(report.forgotten.iter().map(|backup| backup.id).collect::<Vec<_>>(), vec![first.id, second.id]);
+        assert!(report.reclaimed_bytes >= 20_000);
+        assert_eq!(node.backups.list(None).unwrap(), vec![third.clone()]);
+        let mut objects = HashSet::new();
+        tree::collect_objects(&node.content_store, &third.root, &mut objects).unwrap();
+        assert!(objects.iter().all(|address| node.content_store.has_chunk(address).unwrap()));
+        assert!(node.forget_backups(None, &BackupPolicy::default(), false).is_err());
+    }
+
+    #[test]
+    fn test_pins_protect_files_and_trees() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        fs::create_dir_all(temp.path().join("dir/sub")).unwrap();
+        fs::write(temp.path().join("dir/a.bin"), noise(10, 20_000)).unwrap();
+        fs::write(temp.path().join("dir/sub/b.bin"), noise(11, 20_000)).unwrap();
+        fs::write(temp.path().join("file.bin"), noise(12, 20_000)).unwrap();
+        
+        let (dir, _) = node.put_directory_with_registry(temp.path().join("dir"), &PutOptions::default()).unwrap();
+        let file = node.put_file_with_registry(temp.path().join("file.bin")).unwrap();
+        let mut tree_objects = HashSet::new();
+        node.collect_file_objects(&dir, &mut tree_objects).unwrap();
+        
+        // A pin on a directory covers its whole tree; a recursive one also
+        // holds entries registered below its path
+        node.pin_file(&dir.id, false).unwrap();
+        assert_eq!(node.pinned_objects().unwrap(), tree_objects);
+        let below = node.put_file_with_options(temp.path().join("file.bin"), &PutOptions {
+            path: Some("dir/file.bin".to_string()),
+            duplicates: DuplicatePolicy::New,
+            ..PutOptions::default()
+        }).unwrap();
+        node.pin_file(&dir.id, true).unwrap();
+        assert!(node.remove_file(&below.id, false).is_err());
+        assert!(node.pinned_objects().unwrap().contains(&below.chunk_addresses[0]));
+        node.pin_file(&dir.id, false).unwrap();
+        assert_eq!(node.remove_file(&below.id, false).unwrap().reclaimed_bytes, 0);
+        
+        node.pin_file(&file.id, false).unwrap();
+        assert!(node.remove_file(&file.id, false).is_err());
+        assert!(node.trash_file(&dir.id).is_err());
+        assert_eq!(node.collect_garbage(false).unwrap().unreferenced_chunks, 0);
+        assert!(node.content_store.has_chunk(&file.chunk_addresses[0]).unwrap());
+        
+        assert_eq!(node.unpin_file(&file.id).unwrap(), Some(PinMode::Direct));
+        assert_eq!(node.remove_file(&file.id, false).unwrap().reclaimed_bytes, 20_000);
+        assert_eq!(node.pin_listing().unwrap().len(), 2);
+    }
+    
+    #[test]
+    fn test_unreadable_roots_keep_chunks_without_failing_removal() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        fs::write(temp.path().join("a.bin"), noise(13, 20_000)).unwrap();
+        fs::write(temp.path().join("b.bin"), noise(14, 20_000)).unwrap();
+        
+        let a = node.put_file_with_registry(temp.path().join("a.bin")).unwrap();
+        let record = node.create_snapshot(None).unwrap();
+        let b = node.put_file_with_registry(temp.path().join("b.bin")).unwrap();
+        node.content_store.remove_chunk(&record.address).unwrap();
+        
+        // The lost snapshot might still need the chunks, so the entry goes
+        // but its chunks stay
+        let report = node.remove_file(&a.id, false).unwrap();
+        assert_eq!((report.released_chunks, report.unreadable_roots.len()), (0, 1));
+        assert!(report.unreadable_roots[0].starts_with(&format!("snapshot {}", record.id)));
+        assert!(node.content_store.has_chunk(&a.chunk_addresses[0]).unwrap());
+        
+        node.snapshots.remove(&record.id).unwrap();
+        let report = node.remove_file(&b.id, false).unwrap();
+        assert!(report.unreadable_roots.is_empty());
+        assert_eq!(report.reclaimed_bytes, 20_000);
+    }
+    
+    #[test]
+    fn test_expired_files_are_hidden_and_removed() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        fs::write(temp.path().join("keep.bin"), noise(8, 50_000)).unwrap();
+        fs::write(temp.path().join("scratch.bin"), noise(9, 50_000)).unwrap();
+        
+        let keep = node.put_file_with_options(temp.path().join("keep.bin"), &PutOptions {
+            ttl: Some(3600),
+            ..PutOptions::default()
+        }).unwrap();
+        assert_eq!(keep.expires_at, Some(keep.created_at + 3600));
+        let scratch = node.put_file_with_options(temp.path().join("scratch.bin"), &PutOptions {
+            ttl: Some(0),
+            ..PutOptions::default()
+        }).unwrap();
+        
+        // An expired entry drops out of listings before it is swept
+        let listed = node.file_registry.find_files(&FileFilter::default());
+        assert_eq!(listed.iter().map(|metadata| metadata.id).collect::<Vec<_>>(), vec![keep.id]);
+        
+        // ...and out of every other report, with no digest filled in for it
+        let copy = node.put_file_with_options(temp.path().join("scratch.bin"), &PutOptions {
+            path: Some("copy.bin".to_string()),
+            duplicates: DuplicatePolicy::New,
+            ..PutOptions::default()
+        }).unwrap();
+        node.file_registry.update_file(&scratch.id, |metadata| metadata.digest = None).unwrap();
+        assert!(node.duplicate_report().unwrap().contains(&"No duplicate files.".to_string()));
+        assert!(node.file_registry.get_file(&scratch.id).unwrap().digest.is_none());
+        assert!(node.similar_files(&copy.id, 10).unwrap().is_empty());
+        assert!(node.similar_files(&scratch.id, 10).is_err());
+        assert!(node.diff_files(&scratch.id, &copy.id).is_err());
+        let usage = node.disk_usage().unwrap();
+        assert_eq!(usage.files.len(), 2);
+        assert_eq!(usage.logical_bytes, 100_000);
+        let copy_usage = usage.files.iter().find(|usage| usage.metadata.id == copy.id).unwrap();
+        assert_eq!((copy_usage.unique_bytes, copy_usage.shared_bytes), (50_000, 0));
+        node.remove_file(&copy.id, false).unwrap();
+        
+        let preview = node.expire_files(true).unwrap();
+        assert_eq!(preview.expired.len(), 1);
+        assert!(node.file_registry.get_file(&scratch.id).is_some());
+        
+        let report = node.expire_files(false).unwrap();
+        assert_eq!(report.expired[0].id, scratch.id);
+        assert_eq!(report.reclaimed_bytes, 50_000);
+        assert!(node.file_registry.get_file(&scratch.id).is_none());
+        assert!(node.file_registry.get_file(&keep.id).is_some());
+        assert!(node.expire_files(false).unwrap().expired.is_empty());
+    }
+
+    #[test]
+    fn test_expiry_sees_files_registered_elsewhere() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        fs::write(temp.path().join("log.bin"), noise(13, 30_000)).unwrap();
+        let log = node.put_file_with_registry(temp.path().join("log.bin")).unwrap();
+        
+        // Another registry on the same storage, as a CLI command would open,
+        // expires the file and registers a copy sharing its chunks
+        let mut other = FileRegistry::new(temp.path()).unwrap();
+        other.update_file(&log.id, |metadata| metadata.expires_at = Some(1)).unwrap();
+        let copy = other.register_file("copy.bin".to_string(), log.chunk_addresses.clone(), 30_000).unwrap();
+        
+        let report = node.expire_files(false).unwrap();
+        assert_eq!(report.expired.iter().map(|metadata| metadata.id).collect::<Vec<_>>(), vec![log.id]);
+        assert_eq!(report.released_chunks, 0);
+        assert!(node.content_store.has_chunk(&copy.chunk_addresses[0]).unwrap());
+    }
+    
+    #[test]
+    fn test_duplicate_policy_and_report() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        fs::write(temp.path().join("a.bin"), vec![7u8; 5_000]).unwrap();
+        fs::write(temp.path().join("b.bin"), vec![7u8; 5_000]).unwrap();
+        
+        let a = node.put_file_with_registry(temp.path().join("a.bin")).unwrap();
+        assert_eq!(a.digest.as_deref(), Some(blake3::hash(&[7u8; 5_000]).to_hex().as_str()));
+        
+        // Reuse at the same path hands back the existing entry, with the
+        // requested metadata applied instead of registering a new version
+        let reuse = PutOptions {
+            duplicates: DuplicatePolicy::Reuse,
+            path: Some("a.bin".to_string()),
+            tags: vec!["again".to_string()],
+            ttl: Some(3600),
+            ..PutOptions::default()
+        };
+        let reused = node.put_file_with_options(temp.path().join("b.bin"), &reuse).unwrap();
+        assert_eq!(reused.id, a.id);
+        assert!(reused.tags.contains("again"));
+        assert!(reused.expires_at.is_some());
+        assert_eq!(node.file_registry.file_count(), 1);
+        
+        // Put to another path, the existing entry is reused all the same
+        let reuse = PutOptions { duplicates: DuplicatePolicy::Reuse, ..PutOptions::default() };
+        let elsewhere = node.put_file_with_options(temp.path().join("b.bin"), &reuse).unwrap();
+        assert_eq!((elsewhere.id, elsewhere.path.as_str()), (a.id, "a.bin"));
+        assert!(node.file_registry.latest_version("b.bin").is_none());
+        assert_eq!(node.file_registry.file_count(), 1);
+        
+        // Warn (the default) and New both register a second entry
+        let b = node.put_file_with_registry(temp.path().join("b.bin")).unwrap();
+        assert_ne!(b.id, a.id);
+        assert_eq!(node.file_registry.files_with_digest(a.digest.as_ref().unwrap()).unwrap().len(), 2);
+        
+        // Entries from before digests existed are filled in by the report
+        node.file_registry.update_file(&a.id, |metadata| metadata.digest = None).unwrap();
+        let report = node.duplicate_report().unwrap();
+        assert_eq!(report[0], "Computed digests for 1 older files.");
+        assert!(report[1].contains("1 groups, 1 redundant entries, 5000 bytes"), "{:?}", report);
+        assert_eq!(node.file_registry.duplicate_groups()[0].len(), 2);
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_put_follows_symlinks_unless_asked_not_to() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        fs::write(temp.path().join("target.txt"), b"linked content").unwrap();
+        std::os::unix::fs::symlink("target.txt", temp.path().join("link.txt")).unwrap();
+        
+        let followed = node.put_file_with_registry(temp.path().join("link.txt")).unwrap();
+        assert_eq!(followed.path, "link.txt");
+        assert_eq!(followed.total_size, 14);
+        assert!(!followed.posix.as_ref().unwrap().is_symlink());
+        
+        let options = PutOptions { keep_symlinks: true, path: Some("kept".to_string()), ..PutOptions::default() };
+        let kept = node.put_file_with_options(temp.path().join("link.txt"), &options).unwrap();
+        assert_eq!(kept.total_size, 0);
+        assert_eq!(kept.posix.as_ref().unwrap().symlink_target.as_deref(), Some("target.txt"));
+    }
+    
+    #[test]
+    fn test_refs_resolve_and_keep_content_live() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(temp.path().join("build.bin"), &data).unwrap();
+        let build = node.put_file_with_registry(temp.path().join("build.bin")).unwrap();
+        assert!(build.chunk_count > 1);
+        
+        node.refs.set("nightly/latest", RefTarget::File(build.id), &RefCondition::Absent).unwrap();
+        assert_eq!(node.resolve("nightly/latest").unwrap(), Reference::File(build.id));
+        assert_eq!(node.resolve_file("nightly/latest").unwrap(), build.id);
+        
+        // A ref to raw content keeps that object through rm and gc
+        let first_chunk = build.chunk_addresses[0].clone();
+        node.refs.set("first-chunk", node.ref_target(&first_chunk.to_string()).unwrap(), &RefCondition::Any).unwrap();
+        let report = node.remove_file(&build.id, false).unwrap();
+        assert_eq!(report.released_chunks, build.chunk_count - 1);
+        assert!(node.content_store.has_chunk(&first_chunk).unwrap());
+        assert_eq!(node.resolve("first-chunk").unwrap(), Reference::Content(first_chunk));
+        assert!(node.resolve_file("first-chunk").is_err());
+        
+        // The file ref now dangles and says so
+        let error = node.resolve("nightly/latest").unwrap_err().to_string();
+        assert!(error.contains("no longer registered"), "{}", error);
+    }
+    
+    #[test]
+    fn test_trashed_files_stay_live_until_retention_ends() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        fs::write(temp.path().join("a.bin"), vec![5u8; 10_000]).unwrap();
+        let a = node.put_file_with_registry(temp.path().join("a.bin")).unwrap();
+        
+        node.trash_file(&a.id).unwrap();
+        let gc = node.collect_garbage(false).unwrap();
+        assert_eq!((gc.expired_trash, gc.unreferenced_chunks), (0, 0));
+        
+        let restored = node.restore_from_trash(&a.path).unwrap();
+        assert_eq!(restored.id, a.id);
+        node.get_file_by_id(&a.id, temp.path().join("a.out")).unwrap();
+        
+        // Once the retention period is over, gc purges the entry and its chunks
+        node.trash_file(&a.id).unwrap();
+        node.trash_retention = 0;
+        let gc = node.collect_garbage(false).unwrap();
+        assert_eq!(gc.expired_trash, 1);
+        assert_eq!(gc.reclaimed_bytes, 10_000);
+        assert!(node.file_registry.trash_entries().is_empty());
+    }
+    
+    #[test]
+    fn test_empty_trash_releases_chunks() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut node = test_node(&temp);
+        fs::write(temp.path().join("a.bin"), vec![6u8; 10_000]).unwrap();
+        let a = node.put_file_with_registry(temp.path().join("a.bin")).unwrap();
+        node.trash_file(&a.id).unwrap();
+        
+        // Nothing was deleted long enough ago
+        assert!(node.empty_trash(Some(3_600)).unwrap().purged.is_empty());
+        
+        let report = node.empty_trash(None).unwrap();
+        assert_eq!(report.purged.len(), 1);
+        assert_eq!(report.reclaimed_bytes, 10_000);
+        assert_eq!(node.content_store.stats().unwrap().total_chunks, 0);
+    }
+}
@@ -35,6 +35,12 @@ pub struct Chunker {
     config: ChunkConfig,
 }
 
+impl Default for Chunker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Chunker {
     /// Create chunker with default configuration
     pub fn new() -> Self {
@@ -72,8 +78,8 @@ impl Chunker {
         chunker
             .map(|chunk_info| {
                 // Extract actual data slice using offset and length
-                let start = chunk_info.offset as usize;
-                let end = start + chunk_info.length as usize;
+                let start = chunk_info.offset;
+                let end = start + chunk_info.length;
                 let chunk_data = data[start..end].to_vec();
                 let address = ContentAddress::from_data(&chunk_data);
                 Chunk { data: chunk_data, address }
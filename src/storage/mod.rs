@@ -2,7 +2,9 @@
 
 pub mod chunk;
 pub mod store;
+pub mod reader;
 
 // Re-export commonly used items
 pub use chunk::{Chunk, Chunker, ChunkConfig};
 pub use store::{ContentStore, ContentStoreConfig, StorageConfig};
+pub use reader::ChunkReader;
@@ -0,0 +1,142 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::content::ContentAddress;
+use crate::storage::store::ContentStore;
+
+/// Streaming reader over the chunks of a stored file.
+///
+/// Only the chunk covering the current position is held in memory, so large
+/// files can be read (and seeked) without reconstructing them in full.
+pub struct ChunkReader<'a> {
+    store: &'a ContentStore,
+    addresses: &'a [ContentAddress],
+    offsets: Vec<u64>, // Start offset of every chunk, plus the total length at the end
+    position: u64,
+    current: Option<(usize, Vec<u8>)>,
+}
+
+impl<'a> ChunkReader<'a> {
+    /// Create a reader for the given chunk list
+    pub fn new(store: &'a ContentStore, addresses: &'a [ContentAddress]) -> io::Result<Self> {
+        let mut offsets = Vec::with_capacity(addresses.len() + 1);
+        let mut offset = 0;
+        offsets.push(offset);
+        for address in addresses {
+            offset += store.chunk_size(address).map_err(io::Error::other)?;
+            offsets.push(offset);
+        }
+
+        Ok(Self {
+            store,
+            addresses,
+            offsets,
+            position: 0,
+            current: None,
+        })
+    }
+
+    /// Total length of the file in bytes
+    pub fn len(&self) -> u64 {
+        self.offsets.last().copied().unwrap_or(0)
+    }
+
+    /// Whether the file is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Index of the chunk that contains `position`
+    fn chunk_index(&self, position: u64) -> usize {
+        self.offsets.partition_point(|&start| start <= position) - 1
+    }
+}
+
+impl Read for ChunkReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.len() {
+            return Ok(0);
+        }
+
+        let index = self.chunk_index(self.position);
+        if self.current.as_ref().map(|(i, _)| *i) != Some(index) {
+            let chunk = self.store.get_chunk(&self.addresses[index]).map_err(io::Error::other)?;
+            self.current = Some((index, chunk.data().to_vec()));
+        }
+
+        let (_, data) = self.current.as_ref().unwrap();
+        let start = (self.position - self.offsets[index]) as usize;
+        let count = buf.len().min(data.len() - start);
+        buf[..count].copy_from_slice(&data[start..start + count]);
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl Seek for ChunkReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len().checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+
+        self.position = target.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative position")
+        })?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{ChunkConfig, ContentStoreConfig};
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (ContentStore, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let config = ContentStoreConfig {
+            storage_path: temp_dir.path().to_path_buf(),
+            chunk_config: ChunkConfig {
+                min_size: 16,
+                target_size: 32,
+                max_size: 64,
+                use_content_defined: false,
+            },
+            verify_on_read: true,
+        };
+        (ContentStore::new(config).unwrap(), temp_dir)
+    }
+
+    #[test]
+    fn test_read_whole_file() {
+        let (store, _temp) = create_test_store();
+        let data: Vec<u8> = (0..200u8).collect();
+        let addresses = store.put_data(&data).unwrap();
+
+        let mut reader = ChunkReader::new(&store, &addresses).unwrap();
+        assert_eq!(reader.len(), 200);
+
+        let mut read_back = Vec::new();
+        reader.read_to_end(&mut read_back).unwrap();
+        assert_eq!(read_back, data);
+    }
+
+    #[test]
+    fn test_seek_across_chunks() {
+        let (store, _temp) = create_test_store();
+        let data: Vec<u8> = (0..200u8).collect();
+        let addresses = store.put_data(&data).unwrap();
+
+        let mut reader = ChunkReader::new(&store, &addresses).unwrap();
+        reader.seek(SeekFrom::Start(30)).unwrap();
+        let mut buf = [0u8; 10];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, &data[30..40]);
+
+        reader.seek(SeekFrom::End(-5)).unwrap();
+        let mut tail = Vec::new();
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, &data[195..]);
+    }
+}
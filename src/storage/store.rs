@@ -2,7 +2,9 @@ use std::path::{Path, PathBuf};
 use std::fs;
 use std::io;
 
-use crate::content::ContentAddress;
+use std::collections::HashMap;
+
+use crate::content::{ContentAddress, PrefixIndex};
 use crate::storage::chunk::{Chunk, Chunker, ChunkConfig};
 
 /// Configuration for storage behavior
@@ -44,6 +46,9 @@ pub enum ContentStoreError {
     #[error("Invalid content address: {0}")]
     InvalidAddress(String),
     
+    #[error("Content address error: {0}")]
+    ContentAddress(#[from] crate::content::address::ContentAddressError),
+    
     #[error("Corruption detected: expected {expected}, got {actual}")]
     Corruption { expected: ContentAddress, actual: ContentAddress },
 }
@@ -163,6 +168,17 @@ impl ContentStore {
         Ok(self.chunk_path(address).exists())
     }
     
+    /// Get the size in bytes of a stored chunk without reading it
+    pub fn chunk_size(&self, address: &ContentAddress) -> Result<u64> {
+        match fs::metadata(self.chunk_path(address)) {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(ContentStoreError::ContentNotFound {
+                address: address.clone()
+            }),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
     /// Store a file by chunking it and return a list of chunk addresses
     pub fn put_file<P: AsRef<Path>>(&self, file_path: P) -> Result<Vec<ContentAddress>> {
         let data = fs::read(file_path)?;
@@ -242,6 +258,30 @@ impl ContentStore {
         }
     }
     
+    /// Find all stored chunks whose address starts with `prefix`.
+    /// The algorithm part (e.g. `sha256:`) is optional.
+    pub fn addresses_with_prefix(&self, prefix: &str) -> Result<Vec<ContentAddress>> {
+        let prefix = prefix.to_ascii_lowercase();
+        let listing = self.list_content()?;
+        let index = PrefixIndex::new(listing.chunks.iter().map(|chunk| chunk.address.to_string()));
+        
+        let queries = if prefix.contains(':') {
+            vec![prefix]
+        } else if !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            vec![format!("sha256:{}", prefix), format!("blake3:{}", prefix)]
+        } else {
+            vec![]
+        };
+        
+        let mut matches = Vec::new();
+        for query in queries {
+            for key in index.matches(&query) {
+                matches.push(key.parse()?);
+            }
+        }
+        Ok(matches)
+    }
+    
     /// Get the file system path for a chunk given its address
     fn chunk_path(&self, address: &ContentAddress) -> PathBuf {
         let hash_str = address.to_string();
@@ -296,7 +336,7 @@ impl ContentStore {
         enumerate_chunks(&self.objects_dir, &mut chunks, &mut total_chunks, &mut total_size)?;
         
         // Sort chunks by creation time (newest first)
-        chunks.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        chunks.sort_by_key(|chunk| std::cmp::Reverse(chunk.created_at));
         
         let stats = ContentStoreStats {
             total_chunks,
@@ -318,16 +358,6 @@ pub struct ChunkInfo {
 }
 
 impl ChunkInfo {
-    /// Format the address for display (shortened)
-    pub fn short_address(&self) -> String {
-        let addr_str = self.address.to_string();
-        if addr_str.len() > 16 {
-            format!("{}...{}", &addr_str[0..8], &addr_str[addr_str.len()-8..])
-        } else {
-            addr_str
-        }
-    }
-    
     /// Format the creation time
     pub fn created_time_string(&self) -> String {
         match std::time::SystemTime::now().duration_since(self.created_at) {
@@ -363,6 +393,24 @@ pub struct ContentListing {
     pub stats: ContentStoreStats,
 }
 
+/// Minimum number of hex digits shown in abbreviated content addresses
+pub const MIN_ADDRESS_PREFIX_LEN: usize = 8;
+
+impl ContentListing {
+    /// Shortest unique abbreviation of every listed address (algorithm prefix included)
+    pub fn short_addresses(&self) -> HashMap<ContentAddress, String> {
+        let index = PrefixIndex::new(self.chunks.iter().map(|chunk| chunk.address.to_string()));
+        self.chunks
+            .iter()
+            .map(|chunk| {
+                let key = chunk.address.to_string();
+                let min_len = chunk.address.algorithm().to_string().len() + 1 + MIN_ADDRESS_PREFIX_LEN;
+                (chunk.address.clone(), index.shortest_unique(&key, min_len).to_string())
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -447,6 +495,22 @@ mod tests {
         assert!(matches!(result, Err(ContentStoreError::Corruption { .. })));
     }
     
+    #[test]
+    fn test_addresses_with_prefix() {
+        let (store, _temp) = create_test_store();
+        
+        let address = store.put_chunk(b"prefix lookup").unwrap();
+        let hex = hex::encode(address.hash_bytes());
+        
+        assert_eq!(store.addresses_with_prefix(&hex[..6]).unwrap(), vec![address.clone()]);
+        assert_eq!(store.addresses_with_prefix(&address.to_string()).unwrap(), vec![address.clone()]);
+        assert!(store.addresses_with_prefix("blake3:").unwrap().is_empty());
+        assert!(store.addresses_with_prefix("not-hex").unwrap().is_empty());
+        
+        let short = store.list_content().unwrap().short_addresses()[&address].clone();
+        assert_eq!(short, format!("sha256:{}", &hex[..MIN_ADDRESS_PREFIX_LEN]));
+    }
+    
     #[test]
     fn test_stats() {
         let (store, _temp) = create_test_store();
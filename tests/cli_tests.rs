@@ -27,6 +27,7 @@ fn test_start_command_default() {
     let mut cmd = Command::cargo_bin("nebula").unwrap();
     cmd.arg("start")
        .arg("--daemon")  // Use daemon mode to avoid interactive waiting
+       .arg("--once")    // Sweep once and exit rather than run until stopped
        .arg("--storage")
        .arg(temp_dir.path());
     
@@ -42,6 +43,7 @@ fn test_start_command_custom_port() {
     let mut cmd = Command::cargo_bin("nebula").unwrap();
     cmd.arg("start")
        .arg("--daemon")  // Use daemon mode to avoid interactive waiting
+       .arg("--once")    // Sweep once and exit rather than run until stopped
        .arg("--port").arg("8080")
        .arg("--storage").arg(temp_dir.path());
     
@@ -50,6 +52,30 @@ fn test_start_command_custom_port() {
         .stdout(predicate::str::contains("Starting node in daemon mode"));
 }
 
+#[cfg(unix)]
+#[test]
+fn test_daemon_stops_on_sigterm() {
+    let temp_dir = TempDir::new().unwrap();
+    
+    let daemon = std::process::Command::new(assert_cmd::cargo::cargo_bin("nebula"))
+        .args(["start", "--daemon", "--port", "4901"])
+        .env("HOME", temp_dir.path())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    
+    let killed = std::process::Command::new("kill")
+        .args(["-TERM", &daemon.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+    
+    let output = daemon.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Shutting down daemon"));
+}
+
 #[test]
 fn test_config_show() {
     let temp_dir = TempDir::new().unwrap();
@@ -80,3 +106,17 @@ fn test_verbose_logging() {
         .success()
         .stdout(predicate::str::contains("Current configuration"));
 }
+
+#[test]
+fn test_verify_rejects_malformed_proof() {
+    let mut cmd = Command::cargo_bin("nebula").unwrap();
+    cmd.arg("verify")
+       .arg("--proof")
+       .arg("not-a-proof")
+       .arg("--root")
+       .arg(format!("blake3:{}", "00".repeat(32)));
+    
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Error"));
+}
@@ -22,6 +22,8 @@ impl MultiNodeTest {
                 log_level: nebula::config::LogLevel::Error, // Quiet during tests
                 daemon_mode: false,
                 verbose: false,
+                trash_retention: nebula::file::trash::DEFAULT_TRASH_RETENTION,
+                duplicate_policy: nebula::config::DuplicatePolicy::default(),
             };
             
             let node = Node::new(